@@ -0,0 +1,35 @@
+#ifndef TMPTOML_H
+#define TMPTOML_H
+
+/* C interface of tmptoml, available when built with the `ffi` feature:
+ *
+ *     cargo build --release --features ffi
+ *
+ * and linked against target/release/libtmptoml.{so,dylib,a}.
+ */
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TMPTOML_OK 0
+#define TMPTOML_ERR 1
+#define TMPTOML_INVALID_ARGUMENT 2
+
+/* Renders the template file with the values of the group and the optional
+ * secondary group (may be NULL) of the config file.
+ *
+ * Returns TMPTOML_OK and stores the rendered template in `out`, or another
+ * status and an error message in `err`. Strings stored in `out` and `err`
+ * must be released with tmptoml_free_string. */
+int tmptoml_render(const char *config, const char *template_path, const char *group,
+                   const char *secondary, char **out, char **err);
+
+/* Releases a string returned by tmptoml_render. */
+void tmptoml_free_string(char *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TMPTOML_H */
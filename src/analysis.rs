@@ -0,0 +1,185 @@
+// Static analysis of Tera templates, finding the context variables they
+// reference without rendering them.
+
+use std::collections::BTreeSet;
+use tera::{
+    ast::{Expr, ExprVal, FunctionCall, Node},
+    Template,
+};
+
+/// Returns the context variable an identifier starts from, e.g. `db` for
+/// `db.host` or `hosts` for `hosts[0]`.
+pub(crate) fn root_variable(ident: &str) -> &str {
+    ident.split(['.', '[']).next().unwrap_or(ident)
+}
+
+// `hosts[index]` looks `index` up in the context as well, quoted keys and
+// numeric indices are literals.
+fn bracket_variables(ident: &str, variables: &mut BTreeSet<String>) {
+    for part in ident.split('[').skip(1) {
+        let inner = part.split(']').next().unwrap_or_default().trim();
+        let is_literal = inner.starts_with(['"', '\'', '`']) || inner.parse::<i64>().is_ok();
+        if !inner.is_empty() && !is_literal {
+            variables.insert(root_variable(inner).to_string());
+        }
+    }
+}
+
+fn function_call_variables(call: &FunctionCall, variables: &mut BTreeSet<String>) {
+    for arg in call.args.values() {
+        expression_variables(arg, variables);
+    }
+}
+
+fn expression_value_variables(value: &ExprVal, variables: &mut BTreeSet<String>) {
+    match value {
+        ExprVal::Ident(ident) => {
+            variables.insert(root_variable(ident).to_string());
+            bracket_variables(ident, variables);
+        }
+        ExprVal::Math(math) => {
+            expression_variables(&math.lhs, variables);
+            expression_variables(&math.rhs, variables);
+        }
+        ExprVal::Logic(logic) => {
+            expression_variables(&logic.lhs, variables);
+            expression_variables(&logic.rhs, variables);
+        }
+        ExprVal::Test(test) => {
+            variables.insert(root_variable(&test.ident).to_string());
+            for arg in &test.args {
+                expression_variables(arg, variables);
+            }
+        }
+        ExprVal::MacroCall(call) => {
+            for arg in call.args.values() {
+                expression_variables(arg, variables);
+            }
+        }
+        ExprVal::FunctionCall(call) => function_call_variables(call, variables),
+        ExprVal::Array(items) => {
+            for item in items {
+                expression_variables(item, variables);
+            }
+        }
+        ExprVal::StringConcat(concat) => {
+            for value in &concat.values {
+                expression_value_variables(value, variables);
+            }
+        }
+        ExprVal::In(in_expr) => {
+            expression_variables(&in_expr.lhs, variables);
+            expression_variables(&in_expr.rhs, variables);
+        }
+        ExprVal::String(_) | ExprVal::Int(_) | ExprVal::Float(_) | ExprVal::Bool(_) => {}
+    }
+}
+
+/// Collects the root names of the variables an expression reads, including
+/// those used in filter arguments.
+pub(crate) fn expression_variables(expr: &Expr, variables: &mut BTreeSet<String>) {
+    expression_value_variables(&expr.val, variables);
+    for filter in &expr.filters {
+        function_call_variables(filter, variables);
+    }
+}
+
+// Macro bodies only see their arguments, so only the default values of the
+// arguments can read the context.
+fn node_variables(nodes: &[Node], variables: &mut BTreeSet<String>, complete: &mut bool) {
+    for node in nodes {
+        match node {
+            Node::VariableBlock(_, expr) => expression_variables(expr, variables),
+            Node::MacroDefinition(_, definition, _) => {
+                for default in definition.args.values().flatten() {
+                    expression_variables(default, variables);
+                }
+            }
+            Node::Set(_, set) => expression_variables(&set.value, variables),
+            Node::FilterSection(_, section, _) => {
+                function_call_variables(&section.filter, variables);
+                node_variables(&section.body, variables, complete);
+            }
+            Node::Block(_, block, _) => node_variables(&block.body, variables, complete),
+            Node::Forloop(_, forloop, _) => {
+                expression_variables(&forloop.container, variables);
+                node_variables(&forloop.body, variables, complete);
+                if let Some(empty_body) = &forloop.empty_body {
+                    node_variables(empty_body, variables, complete);
+                }
+            }
+            Node::If(condition, _) => {
+                for (_, expr, body) in &condition.conditions {
+                    expression_variables(expr, variables);
+                    node_variables(body, variables, complete);
+                }
+                if let Some((_, body)) = &condition.otherwise {
+                    node_variables(body, variables, complete);
+                }
+            }
+            Node::Extends(..) | Node::Include(..) => *complete = false,
+            Node::Super
+            | Node::Text(_)
+            | Node::ImportMacro(..)
+            | Node::Raw(..)
+            | Node::Break(_)
+            | Node::Continue(_)
+            | Node::Comment(..) => {}
+        }
+    }
+}
+
+fn node_includes(nodes: &[Node], includes: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            Node::Include(_, names, _) => includes.extend(names.iter().cloned()),
+            Node::MacroDefinition(_, definition, _) => node_includes(&definition.body, includes),
+            Node::FilterSection(_, section, _) => node_includes(&section.body, includes),
+            Node::Block(_, block, _) => node_includes(&block.body, includes),
+            Node::Forloop(_, forloop, _) => {
+                node_includes(&forloop.body, includes);
+                if let Some(empty_body) = &forloop.empty_body {
+                    node_includes(empty_body, includes);
+                }
+            }
+            Node::If(condition, _) => {
+                for (_, _, body) in &condition.conditions {
+                    node_includes(body, includes);
+                }
+                if let Some((_, body)) = &condition.otherwise {
+                    node_includes(body, includes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects the names of the templates a template includes, imports macros
+/// from or extends.
+pub(crate) fn template_dependencies(template: &Template) -> BTreeSet<String> {
+    let mut dependencies = BTreeSet::new();
+    node_includes(&template.ast, &mut dependencies);
+    dependencies.extend(template.parent.iter().cloned());
+    dependencies.extend(
+        template
+            .imported_macro_files
+            .iter()
+            .map(|(file, _)| file.clone()),
+    );
+    dependencies
+}
+
+/// Collects the root names of the variables a template reads. Variables the
+/// template binds itself are included when they are read. The second value is
+/// false when the template can read variables that aren't listed, through an
+/// included or parent template or the whole context.
+pub(crate) fn template_variables(template: &Template) -> (BTreeSet<String>, bool) {
+    let mut variables = BTreeSet::new();
+    let mut complete = true;
+    node_variables(&template.ast, &mut variables, &mut complete);
+    if variables.contains("__tera_context") {
+        complete = false;
+    }
+    (variables, complete)
+}
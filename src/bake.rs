@@ -0,0 +1,182 @@
+// Self-contained executables regenerating a fixed set of files. A baked
+// executable is a copy of tmptoml with a config, a bundle of templates and
+// the groups to render them for appended to it, so that hosts can regenerate
+// their files without any other file around.
+
+use crate::{
+    batch::{render_compiled_batch, Batch, BatchOptions},
+    bundle::Bundle,
+    renderer::LoadedConfig,
+    timings::Timings,
+    tmptoml::{parse_group_path, GroupPath, RenderOptions, TmpTomlErr},
+};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+
+// Ends every baked executable, preceded by the length of the payload.
+const BAKE_TRAILER: &[u8] = b"tmptoml-baked-1";
+
+#[derive(Debug)]
+pub enum BakeErr {
+    Read(String),
+    Invalid(String),
+    Write(String),
+}
+
+impl From<BakeErr> for TmpTomlErr {
+    fn from(err: BakeErr) -> Self {
+        TmpTomlErr::Bake(err)
+    }
+}
+
+/// What a baked executable renders: the templates of a bundle for the
+/// default group selections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Baked {
+    pub config: String,
+    pub bundle: Bundle,
+    pub group_paths: Vec<GroupPath>,
+}
+
+impl Baked {
+    fn to_json(&self) -> Value {
+        let templates: serde_json::Map<String, Value> = self
+            .bundle
+            .templates
+            .iter()
+            .map(|(name, source)| (name.clone(), Value::String(source.clone())))
+            .collect();
+        let selections: Vec<String> = self
+            .group_paths
+            .iter()
+            .map(|group_path| group_path.join("."))
+            .collect();
+        json!({
+            "config": self.config,
+            "templates": templates,
+            "selections": selections,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Baked> {
+        let templates = value
+            .get("templates")?
+            .as_object()?
+            .iter()
+            .map(|(name, source)| Some((name.clone(), source.as_str()?.to_string())))
+            .collect::<Option<_>>()?;
+        let group_paths = value
+            .get("selections")?
+            .as_array()?
+            .iter()
+            .map(|selection| Some(parse_group_path(selection.as_str()?)))
+            .collect::<Option<_>>()?;
+        Some(Baked {
+            config: value.get("config")?.as_str()?.to_string(),
+            bundle: Bundle { templates },
+            group_paths,
+        })
+    }
+}
+
+/// Writes a copy of `executable` that renders `baked` when run, checking
+/// that the config parses and the templates compile first.
+pub fn bake(executable: &Path, baked: &Baked, output: &Path) -> Result<(), TmpTomlErr> {
+    LoadedConfig::parse(&baked.config)?;
+    baked.bundle.renderer(RenderOptions::default())?;
+    let mut content = fs::read(executable)
+        .map_err(|err| BakeErr::Read(format!("{}: {}", executable.display(), err)))?;
+    if content.ends_with(BAKE_TRAILER) {
+        return Err(BakeErr::Invalid(format!(
+            "{} is already a baked executable",
+            executable.display()
+        ))
+        .into());
+    }
+    let payload = baked.to_json().to_string().into_bytes();
+    content.extend_from_slice(&payload);
+    content.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    content.extend_from_slice(BAKE_TRAILER);
+    fs::write(output, content)
+        .map_err(|err| BakeErr::Write(format!("{}: {}", output.display(), err)))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(output, fs::Permissions::from_mode(0o755))
+            .map_err(|err| BakeErr::Write(format!("{}: {}", output.display(), err)))?;
+    }
+    Ok(())
+}
+
+/// Reads what `executable` was baked with, `None` for an executable that
+/// wasn't baked.
+pub fn read_baked(executable: &Path) -> Result<Option<Baked>, BakeErr> {
+    let read_err =
+        |err: std::io::Error| BakeErr::Read(format!("{}: {}", executable.display(), err));
+    let invalid = || BakeErr::Invalid(format!("{}: corrupt baked payload", executable.display()));
+    let mut file = fs::File::open(executable).map_err(read_err)?;
+    let footer_len = (BAKE_TRAILER.len() + 8) as u64;
+    let file_len = file.metadata().map_err(read_err)?.len();
+    if file_len < footer_len {
+        return Ok(None);
+    }
+    let mut footer = vec![0; footer_len as usize];
+    file.seek(SeekFrom::End(-(footer_len as i64)))
+        .and_then(|_| file.read_exact(&mut footer))
+        .map_err(read_err)?;
+    if !footer.ends_with(BAKE_TRAILER) {
+        return Ok(None);
+    }
+    let mut payload_len = [0; 8];
+    payload_len.copy_from_slice(&footer[..8]);
+    let payload_len = u64::from_le_bytes(payload_len);
+    if payload_len > file_len - footer_len {
+        return Err(invalid());
+    }
+    let mut payload = vec![0; payload_len as usize];
+    file.seek(SeekFrom::Start(file_len - footer_len - payload_len))
+        .and_then(|_| file.read_exact(&mut payload))
+        .map_err(read_err)?;
+    let value: Value = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+    Baked::from_json(&value).map(Some).ok_or_else(invalid)
+}
+
+/// Renders the baked templates like `render_batch`, for `group_paths` or,
+/// when empty, the baked selections.
+pub fn render_baked(
+    baked: &Baked,
+    output_dir: &Path,
+    group_paths: &[GroupPath],
+    batch_options: &BatchOptions,
+    options: RenderOptions,
+) -> Result<Batch, TmpTomlErr> {
+    let group_paths = match group_paths {
+        [] => &baked.group_paths,
+        group_paths => group_paths,
+    };
+    let mut timings = Timings::default();
+    let started = Instant::now();
+    let config = LoadedConfig::parse(&baked.config)?;
+    timings.config = started.elapsed();
+
+    let started = Instant::now();
+    let renderer = baked.bundle.renderer(options)?;
+    timings.compile = started.elapsed();
+
+    render_compiled_batch(
+        Arc::new(config),
+        Arc::new(renderer),
+        &BTreeMap::new(),
+        output_dir,
+        group_paths,
+        batch_options,
+        timings,
+    )
+}
@@ -0,0 +1,372 @@
+// Batch rendering of a template directory for several group selections, each
+// selection being written to its own subdirectory of the output directory.
+
+use crate::{
+    compression::{encode_output, Compression, OutputEncoding},
+    exclude::TemplateFilter,
+    renderer::{LoadedConfig, Renderer},
+    timings::Timings,
+    tmptoml::{parse_toml_to_config, GroupPath, RenderOptions, TmpTomlErr},
+    transaction::Transaction,
+    warnings::{skipped_table_notices, Notice},
+};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub enum BatchErr {
+    Write(String),
+    InvalidPattern(String),
+    /// A file took longer to render than the per-file timeout.
+    TimedOut(String),
+    /// Sections have invalid `_tags`, or none has the wanted tags.
+    Tags(String),
+    /// An option of `batch` that `plan` can't honour, as it writes nothing.
+    NotPlanned(String),
+    /// Files failed to render while the batch continued on errors. The files
+    /// that did render were written.
+    Failed(Vec<BatchFailure>),
+}
+
+impl From<BatchErr> for TmpTomlErr {
+    fn from(err: BatchErr) -> Self {
+        TmpTomlErr::Batch(err)
+    }
+}
+
+/// A template rendered for a group selection and the file it was written to.
+#[derive(Debug, Clone)]
+pub struct BatchOutput {
+    pub group_path: GroupPath,
+    pub template: String,
+    pub path: PathBuf,
+    /// Time spent rendering, building the context included.
+    pub timings: Timings,
+    /// The config file and the template files the output was rendered from.
+    pub inputs: Vec<PathBuf>,
+}
+
+/// A template that failed to render, or to be written, for a group
+/// selection.
+#[derive(Debug)]
+pub struct BatchFailure {
+    pub group_path: GroupPath,
+    pub template: String,
+    pub path: PathBuf,
+    pub error: TmpTomlErr,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Template files left out of the batch.
+    pub filter: TemplateFilter,
+    /// Records a file failing to render and carries on with the others,
+    /// instead of aborting the whole batch.
+    pub continue_on_error: bool,
+    /// Gives up on a file that takes longer to render, e.g. a loop over a
+    /// huge range after a config mistake. Tera can't stop a render, so the
+    /// thread rendering the file keeps running until the render ends or the
+    /// process exits, while the batch commits or rolls back without it. Its
+    /// output is dropped, so it never writes a file.
+    pub timeout_per_file: Option<Duration>,
+    /// Number of files rendered at once. Zero renders one at a time.
+    pub jobs: usize,
+    /// Reports the files in the order they are rendered in with a single
+    /// job, instead of the order they finish in.
+    pub ordered: bool,
+    /// Compresses the written files, adding `.gz` or `.zst` to their names.
+    pub compression: Option<Compression>,
+    /// Encodes the written files, after compressing them.
+    pub encoding: Option<OutputEncoding>,
+}
+
+#[derive(Debug)]
+pub struct Batch {
+    pub outputs: Vec<BatchOutput>,
+    /// Only ever non-empty when continuing on errors.
+    pub failures: Vec<BatchFailure>,
+    /// Time spent reading the config and compiling the templates, shared by
+    /// every output.
+    pub timings: Timings,
+    /// The tables of the config the group paths leave out of the context.
+    pub notices: Vec<Notice>,
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl Batch {
+    /// Summarises the run as JSON: every file written, every file that
+    /// failed, the tables left out of the context and the time spent.
+    pub fn report(&self) -> Value {
+        let outputs: Vec<Value> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                json!({
+                    "group": output.group_path.join("."),
+                    "template": output.template,
+                    "path": output.path.display().to_string(),
+                    "render_ms": duration_ms(output.timings.render),
+                })
+            })
+            .collect();
+        let failures: Vec<Value> = self
+            .failures
+            .iter()
+            .map(|failure| {
+                json!({
+                    "group": failure.group_path.join("."),
+                    "template": failure.template,
+                    "path": failure.path.display().to_string(),
+                    "error": format!("{:?}", failure.error),
+                })
+            })
+            .collect();
+        let notices: Vec<Value> = self
+            .notices
+            .iter()
+            .map(|notice| json!({"key": notice.key, "message": notice.message}))
+            .collect();
+        json!({
+            "success": self.failures.is_empty(),
+            "outputs": outputs,
+            "failures": failures,
+            "notices": notices,
+            "timings": {
+                "config_ms": duration_ms(self.timings.config),
+                "compile_ms": duration_ms(self.timings.compile),
+            },
+        })
+    }
+}
+
+// The file a template is written to for a group path:
+// `output_dir/<group.path>/<template>`, with the extension of its compression.
+pub(crate) fn output_path(
+    output_dir: &Path,
+    group_path: &GroupPath,
+    template: &str,
+    compression: Option<Compression>,
+) -> PathBuf {
+    let mut path = output_dir.join(group_path.join(".")).join(template);
+    if let Some(compression) = compression {
+        path.as_mut_os_string()
+            .push(format!(".{}", compression.extension()));
+    }
+    path
+}
+
+// Tera can't interrupt a render, so a render with a timeout happens on its
+// own thread, which is left to finish in the background once it times out.
+// Only the batch's thread stages files, so the abandoned render's output goes
+// nowhere but a closed channel.
+pub(crate) fn render_file(
+    renderer: &Arc<Renderer>,
+    config: &Arc<LoadedConfig>,
+    template: &str,
+    group_path: &GroupPath,
+    timeout: Option<Duration>,
+) -> Result<String, TmpTomlErr> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return renderer.render(template, config, std::slice::from_ref(group_path)),
+    };
+    let (sender, receiver) = mpsc::channel();
+    let (renderer, config) = (Arc::clone(renderer), Arc::clone(config));
+    let (name, group_paths) = (template.to_string(), vec![group_path.clone()]);
+    thread::spawn(move || {
+        let _ = sender.send(renderer.render(&name, &config, &group_paths));
+    });
+    receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+        Err(BatchErr::TimedOut(format!(
+            "{} for {} after {:?}",
+            template,
+            group_path.join("."),
+            timeout
+        ))
+        .into())
+    })
+}
+
+/// Renders every template of `template_dir` that the filter doesn't exclude
+/// for every group path, writing `output_dir/<group.path>/<template>`. Each
+/// template is compiled once. Files are only moved into place once every
+/// render succeeded, so a failed or timed out batch leaves the output
+/// directory untouched. Files are rendered group by group, the templates of
+/// a group in their dependency order. When continuing on errors, the files
+/// that rendered are written and the others recorded as failures instead.
+pub fn render_batch(
+    config_file_path: &Path,
+    template_dir: &Path,
+    output_dir: &Path,
+    group_paths: &[GroupPath],
+    batch_options: &BatchOptions,
+    options: RenderOptions,
+) -> Result<Batch, TmpTomlErr> {
+    let mut timings = Timings::default();
+    let started = Instant::now();
+    let config = match options.config_lookup {
+        true => LoadedConfig::new(parse_toml_to_config(config_file_path.to_str())?),
+        false => LoadedConfig::from_file(config_file_path, group_paths)?,
+    };
+    timings.config = started.elapsed();
+
+    let started = Instant::now();
+    let renderer = Renderer::from_dir(template_dir, &batch_options.filter, options)?;
+    timings.compile = started.elapsed();
+
+    let inputs: BTreeMap<String, Vec<PathBuf>> = renderer
+        .template_names()
+        .into_iter()
+        .map(|template| {
+            let templates = renderer
+                .dependencies(template)
+                .into_iter()
+                .chain(std::iter::once(template))
+                .map(|name| template_dir.join(name));
+            let inputs = std::iter::once(config_file_path.to_path_buf())
+                .chain(templates)
+                .collect();
+            (template.clone(), inputs)
+        })
+        .collect();
+    render_compiled_batch(
+        Arc::new(config),
+        Arc::new(renderer),
+        &inputs,
+        output_dir,
+        group_paths,
+        batch_options,
+        timings,
+    )
+}
+
+/// Renders a batch of templates already compiled, `inputs` listing the files
+/// each template was read from.
+pub(crate) fn render_compiled_batch(
+    config: Arc<LoadedConfig>,
+    renderer: Arc<Renderer>,
+    inputs: &BTreeMap<String, Vec<PathBuf>>,
+    output_dir: &Path,
+    group_paths: &[GroupPath],
+    batch_options: &BatchOptions,
+    timings: Timings,
+) -> Result<Batch, TmpTomlErr> {
+    // Every file of the batch, in the order it is reported with `ordered`.
+    let order = renderer.dependency_order();
+    let manifest: Vec<(&GroupPath, &String)> = group_paths
+        .iter()
+        .flat_map(|group_path| order.iter().map(move |template| (group_path, *template)))
+        .collect();
+    let jobs = batch_options.jobs.clamp(1, manifest.len().max(1));
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+
+    let mut transaction = Transaction::default();
+    let mut outputs = Vec::new();
+    let mut failures = Vec::new();
+    let aborted = thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (renderer, config, manifest) = (&renderer, &config, &manifest);
+            let (next, stop) = (&next, &stop);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let (group_path, template) = match manifest.get(index) {
+                        Some(file) => *file,
+                        None => break,
+                    };
+                    let started = Instant::now();
+                    let rendered = render_file(
+                        renderer,
+                        config,
+                        template,
+                        group_path,
+                        batch_options.timeout_per_file,
+                    )
+                    .and_then(|rendered| {
+                        encode_output(
+                            rendered.into_bytes(),
+                            batch_options.compression,
+                            batch_options.encoding,
+                        )
+                    });
+                    if sender.send((index, rendered, started.elapsed())).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for (index, rendered, render_time) in receiver {
+            let (group_path, template) = manifest[index];
+            let path = output_path(output_dir, group_path, template, batch_options.compression);
+            let staged = rendered.and_then(|rendered| Ok(transaction.stage(&path, &rendered)?));
+            if let Err(err) = staged {
+                if !batch_options.continue_on_error {
+                    stop.store(true, Ordering::Relaxed);
+                    return Some(err);
+                }
+                failures.push((
+                    index,
+                    BatchFailure {
+                        group_path: group_path.clone(),
+                        template: template.to_string(),
+                        path,
+                        error: err,
+                    },
+                ));
+                continue;
+            }
+            outputs.push((
+                index,
+                BatchOutput {
+                    group_path: group_path.clone(),
+                    template: template.to_string(),
+                    path,
+                    timings: Timings {
+                        render: render_time,
+                        ..Timings::default()
+                    },
+                    inputs: inputs.get(template).cloned().unwrap_or_default(),
+                },
+            ));
+        }
+        None
+    });
+    if let Some(err) = aborted {
+        transaction.rollback();
+        return Err(err);
+    }
+    if batch_options.ordered {
+        outputs.sort_by_key(|(index, _)| *index);
+        failures.sort_by_key(|(index, _)| *index);
+    }
+    let outputs = outputs.into_iter().map(|(_, output)| output).collect();
+    let failures = failures.into_iter().map(|(_, failure)| failure).collect();
+    transaction.commit()?;
+    Ok(Batch {
+        outputs,
+        failures,
+        timings,
+        notices: skipped_table_notices(
+            config.config(),
+            group_paths,
+            renderer.options().include_siblings,
+        ),
+    })
+}
@@ -0,0 +1,97 @@
+// Benchmarking of a template: compiling and rendering it repeatedly against
+// the same context, to spot expensive constructs like giant loops or
+// repeated includes before they slow down batch renders.
+
+use crate::{
+    lookup::with_lookup_config,
+    timings::format_timing,
+    tmptoml::{
+        build_template_values, compile_tera_template, parse_toml_to_config,
+        render_compiled_template, resolve_group_paths_with_options, RenderOptions, TemplateSource,
+        TmpTomlErr,
+    },
+};
+use std::{
+    fmt,
+    num::NonZeroUsize,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// The time each iteration of a benchmark spent compiling and rendering the
+/// template, sorted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bench {
+    pub compile: Vec<Duration>,
+    pub render: Vec<Duration>,
+}
+
+/// The duration `percent` percent of the sorted `durations` are at most, by
+/// the nearest rank.
+pub fn percentile(durations: &[Duration], percent: usize) -> Duration {
+    let rank = (durations.len() * percent).div_ceil(100);
+    durations.get(rank.max(1) - 1).copied().unwrap_or_default()
+}
+
+/// Compiles and renders the template `iterations` times with the values of
+/// the groups of the options. The config is read and the context built once,
+/// so only the template is measured.
+pub fn bench_template(
+    config_file_path: &Path,
+    template_file_path: &Path,
+    options: &RenderOptions,
+    iterations: NonZeroUsize,
+) -> Result<Bench, TmpTomlErr> {
+    let toml_config = parse_toml_to_config(config_file_path.to_str())?;
+    let mut group_values =
+        resolve_group_paths_with_options(&toml_config, &options.group_paths, options)?;
+    options.value_providers.provide(&mut group_values)?;
+    let template_values = build_template_values(&group_values, options)?;
+    let lookup_config = options.config_lookup.then(|| Arc::new(toml_config));
+
+    let mut bench = Bench {
+        compile: Vec::with_capacity(iterations.get()),
+        render: Vec::with_capacity(iterations.get()),
+    };
+    for _ in 0..iterations.get() {
+        let started = Instant::now();
+        let compiled = compile_tera_template(
+            TemplateSource::File(template_file_path),
+            &template_values,
+            options,
+        )?;
+        bench.compile.push(started.elapsed());
+
+        let started = Instant::now();
+        with_lookup_config(lookup_config.clone(), || {
+            render_compiled_template(&compiled, template_values.clone())
+        })?;
+        bench.render.push(started.elapsed());
+    }
+    bench.compile.sort();
+    bench.render.sort();
+    Ok(bench)
+}
+
+impl fmt::Display for Bench {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![format!("iterations: {}", self.compile.len())];
+        for (phase, durations) in [
+            ("template compilation", &self.compile),
+            ("rendering", &self.render),
+        ] {
+            lines.push(format!("{}:", phase));
+            let total: Duration = durations.iter().sum();
+            let mean = total / durations.len().max(1) as u32;
+            lines.push(format_timing("  mean", mean));
+            for percent in [50, 90, 99] {
+                let label = format!("  p{}", percent);
+                lines.push(format_timing(&label, percentile(durations, percent)));
+            }
+            let max = durations.last().copied().unwrap_or_default();
+            lines.push(format_timing("  max", max));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
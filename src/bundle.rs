@@ -0,0 +1,194 @@
+// Bundles of templates shipped as a single file. Tera can't serialize the
+// templates it parsed, so a bundle holds the template sources, checked to
+// compile when bundling, and loading one compiles them from a single read
+// instead of walking and reading a whole template directory.
+
+use crate::{
+    analysis::template_dependencies,
+    coercion::context_coercions,
+    exclude::TemplateFilter,
+    passthrough::extract_regions,
+    renderer::{read_template_dir, LoadedConfig, Renderer},
+    sensitive::sensitive_keys,
+    timings::Timings,
+    tmptoml::{
+        build_template_values, parse_toml_to_config, resolve_group_paths_with_options,
+        RenderOptions, Rendered, TeraRenderErr, TmpTomlErr,
+    },
+    warnings::{collect_warnings, sensitive_output_warnings, skipped_table_notices},
+};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeSet, fs, path::Path, time::Instant};
+use tera::Template;
+
+const BUNDLE_HEADER: &str = "tmptoml-bundle 1\n";
+
+#[derive(Debug)]
+pub enum BundleErr {
+    Read(String),
+    Invalid(String),
+    Write(String),
+}
+
+impl From<BundleErr> for TmpTomlErr {
+    fn from(err: BundleErr) -> Self {
+        TmpTomlErr::Bundle(err)
+    }
+}
+
+/// Templates named by their path relative to the directory they were
+/// bundled from, e.g. `nginx/site.conf`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bundle {
+    pub templates: Vec<(String, String)>,
+}
+
+impl Bundle {
+    /// Bundles every file below `template_dir` that `filter` doesn't
+    /// exclude, failing if any of them doesn't compile.
+    pub fn from_dir(template_dir: &Path, filter: &TemplateFilter) -> Result<Bundle, TmpTomlErr> {
+        let bundle = Bundle {
+            templates: read_template_dir(template_dir, filter)?,
+        };
+        bundle.renderer(RenderOptions::default())?;
+        Ok(bundle)
+    }
+
+    pub fn read(path: &Path) -> Result<Bundle, BundleErr> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| BundleErr::Read(format!("{}: {}", path.display(), err)))?;
+        let invalid = |reason: &str| BundleErr::Invalid(format!("{}: {}", path.display(), reason));
+        let content = content
+            .strip_prefix(BUNDLE_HEADER)
+            .ok_or_else(|| invalid("not a tmptoml bundle"))?;
+        let value: Value =
+            serde_json::from_str(content).map_err(|err| invalid(&err.to_string()))?;
+        let templates = match value.get("templates") {
+            Some(Value::Object(templates)) => templates,
+            _ => return Err(invalid("missing templates")),
+        };
+        let templates = templates
+            .iter()
+            .map(|(name, source)| match source {
+                Value::String(source) => Ok((name.clone(), source.clone())),
+                _ => Err(invalid(&format!("template `{}` is not a string", name))),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Bundle { templates })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), BundleErr> {
+        let templates: Map<String, Value> = self
+            .templates
+            .iter()
+            .map(|(name, source)| (name.clone(), Value::String(source.clone())))
+            .collect();
+        let content = format!("{}{}", BUNDLE_HEADER, json!({ "templates": templates }));
+        fs::write(path, content)
+            .map_err(|err| BundleErr::Write(format!("{}: {}", path.display(), err)))
+    }
+
+    /// Compiles the bundled templates.
+    pub fn renderer(&self, options: RenderOptions) -> Result<Renderer, TmpTomlErr> {
+        Renderer::new(self.templates.clone(), options)
+    }
+
+    fn source(&self, name: &str) -> Option<&String> {
+        self.templates
+            .iter()
+            .find(|(template, _)| template == name)
+            .map(|(_, source)| source)
+    }
+
+    // The named template along with the templates it includes, imports or
+    // extends, directly or through other templates, so that rendering one
+    // template of a large bundle doesn't compile all of them.
+    fn templates_for(&self, name: &str, options: &RenderOptions) -> Vec<(String, String)> {
+        let mut pending = vec![name.to_string()];
+        let mut names = BTreeSet::new();
+        while let Some(name) = pending.pop() {
+            let source = match self.source(&name) {
+                Some(source) => source,
+                None => continue,
+            };
+            if !names.insert(name.clone()) {
+                continue;
+            }
+            let dependencies = extract_regions(source, &options.passthrough_markers)
+                .ok()
+                .and_then(|(source, _)| Template::new(&name, None, &source).ok())
+                .map(|template| template_dependencies(&template))
+                .unwrap_or_default();
+            pending.extend(dependencies);
+        }
+        self.templates
+            .iter()
+            .filter(|(name, _)| names.contains(name))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Renders the named template of a bundle like
+/// `render_template_with_warnings` renders a template file. The bundled
+/// templates can include, import or extend each other by name, and only the
+/// named template and the templates it depends on are compiled.
+pub fn render_bundled_template(
+    config_file_path: &Path,
+    bundle: &Bundle,
+    name: &str,
+    options: &RenderOptions,
+) -> Result<Rendered, TmpTomlErr> {
+    let mut timings = Timings::default();
+    let started = Instant::now();
+    let config = LoadedConfig::new(parse_toml_to_config(config_file_path.to_str())?);
+    timings.config = started.elapsed();
+
+    let started = Instant::now();
+    let mut group_values =
+        resolve_group_paths_with_options(config.config(), &options.group_paths, options)?;
+    options.value_providers.provide(&mut group_values)?;
+    let template_values = build_template_values(&group_values, options)?;
+    timings.context = started.elapsed();
+
+    let started = Instant::now();
+    let template_source = bundle
+        .source(name)
+        .ok_or_else(|| TeraRenderErr::TemplateNotFound(name.to_string()))?;
+    let (template_source, _) = extract_regions(template_source, &options.passthrough_markers)?;
+    let mut warnings = collect_warnings(
+        config.config(),
+        &group_values,
+        &template_values,
+        &template_source,
+        options,
+    )?;
+    timings.warnings = started.elapsed();
+
+    let started = Instant::now();
+    let renderer = Renderer::new(bundle.templates_for(name, options), options.clone())?;
+    timings.compile = started.elapsed();
+
+    let started = Instant::now();
+    let output = renderer.render(name, &config, &options.group_paths)?;
+    timings.render = started.elapsed();
+    warnings.extend(sensitive_output_warnings(
+        sensitive_keys(config.config(), &options.group_paths),
+        &group_values,
+        &output,
+        options.secret_output,
+    ));
+    warnings.sort();
+
+    Ok(Rendered {
+        output,
+        warnings,
+        notices: skipped_table_notices(
+            config.config(),
+            &options.group_paths,
+            options.include_siblings,
+        ),
+        coercions: context_coercions(&group_values),
+        timings,
+    })
+}
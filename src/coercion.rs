@@ -0,0 +1,82 @@
+// Records of the values whose type changes on their way into a template
+// context or an export format, e.g. a datetime passed to templates as a
+// string, so that teams wanting exact type fidelity can see or refuse them.
+
+use crate::tmptoml::Group;
+use std::fmt;
+
+/// A value of `key` converted from one type to another.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Coercion {
+    pub key: String,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+impl fmt::Display for Coercion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "COERCION: {}: {} coerced to {}",
+            self.key, self.from, self.to
+        )
+    }
+}
+
+pub(crate) fn type_name(value: &toml::Value) -> &'static str {
+    match value {
+        toml::Value::String(_) => "string",
+        toml::Value::Integer(_) => "integer",
+        toml::Value::Float(_) => "float",
+        toml::Value::Boolean(_) => "boolean",
+        toml::Value::Datetime(_) => "datetime",
+        toml::Value::Array(_) => "array",
+        toml::Value::Table(_) => "table",
+    }
+}
+
+pub(crate) fn join_key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+// JSON has no datetimes, passed as their TOML string form, and no infinite
+// or NaN floats, passed as null.
+fn json_coercions(key: &str, value: &toml::Value, coercions: &mut Vec<Coercion>) {
+    let to = match value {
+        toml::Value::Datetime(_) => "string",
+        toml::Value::Float(float) if !float.is_finite() => "null",
+        toml::Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                json_coercions(&format!("{}[{}]", key, index), value, coercions);
+            }
+            return;
+        }
+        toml::Value::Table(table) => {
+            for (sub_key, value) in table {
+                json_coercions(&join_key(key, sub_key), value, coercions);
+            }
+            return;
+        }
+        _ => return,
+    };
+    coercions.push(Coercion {
+        key: key.to_string(),
+        from: type_name(value),
+        to,
+    });
+}
+
+/// The coercions of the group values on their way into the template
+/// context, sorted by key.
+pub(crate) fn context_coercions(group_values: &Group) -> Vec<Coercion> {
+    let mut coercions = Vec::new();
+    for (key, value) in group_values {
+        json_coercions(key, value, &mut coercions);
+    }
+    coercions.sort();
+    coercions
+}
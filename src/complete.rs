@@ -0,0 +1,71 @@
+// Completion of group IDs for the shell completions, which ask for the groups
+// of the config file on the command line being completed.
+
+use crate::{
+    sensitive::is_marked,
+    tmptoml::{group_ids, parse_group_path, secondary_group_ids, Config},
+};
+
+fn sub_group_ids<'a>(toml_config: &'a Config, group_path: &[String]) -> Vec<&'a String> {
+    let (group_id, sub_group_ids) = match group_path.split_first() {
+        Some(split) => split,
+        None => return group_ids(toml_config).collect(),
+    };
+    let group_section = match toml_config.get(group_id) {
+        Some(group_section) => group_section,
+        None => return Vec::new(),
+    };
+    let mut current_section: Option<&toml::value::Table> = None;
+    for sub_group_id in sub_group_ids {
+        let sub_group = match current_section {
+            None => group_section.get(sub_group_id),
+            Some(section) => section.get(sub_group_id),
+        };
+        match sub_group {
+            Some(toml::Value::Table(table)) => current_section = Some(table),
+            _ => return Vec::new(),
+        }
+    }
+    match current_section {
+        None => secondary_group_ids(group_section),
+        Some(section) => section
+            .iter()
+            .filter(|(_, value)| value.is_table() && !is_marked(value))
+            .map(|(key, _)| key)
+            .collect(),
+    }
+}
+
+/// Lists the IDs of the groups directly below `group_path` that start with
+/// `partial`, sorted. An empty path lists the top-level groups.
+pub fn complete_group_ids(
+    toml_config: &Config,
+    group_path: &[String],
+    partial: &str,
+) -> Vec<String> {
+    let mut ids: Vec<String> = sub_group_ids(toml_config, group_path)
+        .into_iter()
+        .filter(|id| id.starts_with(partial))
+        .cloned()
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Completes a dotted group path such as `qa.sys`, listing the matching
+/// paths in full, e.g. `qa.system1`.
+pub fn complete_group_path(toml_config: &Config, partial: &str) -> Vec<String> {
+    let mut group_path = parse_group_path(partial);
+    let last = group_path.pop().unwrap_or_default();
+    complete_group_ids(toml_config, &group_path, &last)
+        .into_iter()
+        .map(|id| {
+            group_path
+                .iter()
+                .chain(std::iter::once(&id))
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(".")
+        })
+        .collect()
+}
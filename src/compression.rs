@@ -0,0 +1,169 @@
+// Compression of configs, templates and rendered output. Compressed configs
+// and templates are decompressed transparently, recognized by their magic
+// bytes rather than their extension, and rendered output can be compressed
+// and base64 encoded, e.g. for a Kubernetes Secret or a firmware blob. Both
+// are done with the flate2 and zstd crates, built with the `compression`
+// feature.
+
+use crate::tmptoml::{ReadFileErr, TmpTomlErr};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::str::FromStr;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            other => Err(format!("unsupported compression: {}", other)),
+        }
+    }
+}
+
+impl Compression {
+    fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// The extension of files compressed this way, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputEncoding {
+    Base64,
+    /// UTF-16 little endian with a byte order mark, the encoding legacy
+    /// Windows services expect of their configs.
+    Utf16LeBom,
+}
+
+impl FromStr for OutputEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "base64" => Ok(OutputEncoding::Base64),
+            "utf16-le-bom" => Ok(OutputEncoding::Utf16LeBom),
+            other => Err(format!("unsupported output encoding: {}", other)),
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decompressed(compression: Compression, content: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    // Like `gzip -d`, every member of a multi-member gzip file is read.
+    let mut decompressed = Vec::new();
+    match compression {
+        Compression::Gzip => flate2::read::MultiGzDecoder::new(content)
+            .read_to_end(&mut decompressed)
+            .map(|_| ()),
+        Compression::Zstd => zstd::stream::copy_decode(content, &mut decompressed),
+    }
+    .map_err(|err| format!("{}: {}", compression.name(), err))?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompressed(compression: Compression, _content: &[u8]) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "{} files can't be read without the compression feature",
+        compression.name()
+    ))
+}
+
+// The gzip header is left without name and timestamp, like `gzip -n` does.
+#[cfg(feature = "compression")]
+fn compressed(compression: Compression, content: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Write;
+
+    match compression {
+        Compression::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(content).and_then(|_| encoder.finish())
+        }
+        // Level 0 is zstd's default level.
+        Compression::Zstd => zstd::encode_all(content, 0),
+    }
+    .map_err(|err| format!("{}: {}", compression.name(), err))
+}
+
+#[cfg(not(feature = "compression"))]
+fn compressed(compression: Compression, _content: &[u8]) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "{} output can't be written without the compression feature",
+        compression.name()
+    ))
+}
+
+/// Decompresses `content` read from `path` if it is gzip or zstd
+/// compressed, returning it unchanged otherwise.
+pub(crate) fn decompress(path: &str, content: Vec<u8>) -> Result<Vec<u8>, ReadFileErr> {
+    let compression = if content.starts_with(GZIP_MAGIC) {
+        Compression::Gzip
+    } else if content.starts_with(ZSTD_MAGIC) {
+        Compression::Zstd
+    } else {
+        return Ok(content);
+    };
+    decompressed(compression, &content)
+        .map_err(|reason| ReadFileErr::Decompress(format!("{}: {}", path, reason)))
+}
+
+/// Standard base64 with padding, as Kubernetes Secrets expect.
+pub fn base64_encode(content: &[u8]) -> String {
+    STANDARD.encode(content)
+}
+
+fn utf16_le_bom(output: &[u8]) -> Result<Vec<u8>, TmpTomlErr> {
+    let text = std::str::from_utf8(output)
+        .map_err(|err| TmpTomlErr::Output(format!("output isn't valid UTF-8 text: {}", err)))?;
+    let mut encoded = vec![0xff, 0xfe];
+    for unit in text.encode_utf16() {
+        encoded.extend_from_slice(&unit.to_le_bytes());
+    }
+    Ok(encoded)
+}
+
+/// Compresses and then encodes rendered output. Compressed output leaves out
+/// names and timestamps, so the same output always compresses the same.
+/// UTF-16 being a text encoding, output is transcoded to it before being
+/// compressed.
+pub fn encode_output(
+    output: Vec<u8>,
+    compression: Option<Compression>,
+    encoding: Option<OutputEncoding>,
+) -> Result<Vec<u8>, TmpTomlErr> {
+    let output = match encoding {
+        Some(OutputEncoding::Utf16LeBom) => utf16_le_bom(&output)?,
+        _ => output,
+    };
+    let output = match compression {
+        Some(compression) => compressed(compression, &output).map_err(TmpTomlErr::Output)?,
+        None => output,
+    };
+    Ok(match encoding {
+        Some(OutputEncoding::Base64) => base64_encode(&output).into_bytes(),
+        Some(OutputEncoding::Utf16LeBom) | None => output,
+    })
+}
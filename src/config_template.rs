@@ -0,0 +1,80 @@
+// Templates named by the config itself. A group or any of its nested groups
+// can name the template it is rendered with in a `_template` key, so that the
+// config fully describes how each of its sections is rendered.
+
+use crate::{
+    entries::sub_section,
+    selective::parse_toml_groups_file,
+    tmptoml::{Config, GroupPath, TeraRenderErr, TmpTomlErr},
+};
+use std::path::Path;
+
+/// The key naming a group's template. It is not one of the group's values.
+pub const TEMPLATE_KEY: &str = "_template";
+
+fn template_value(value: Option<&toml::Value>) -> Result<Option<String>, TmpTomlErr> {
+    match value {
+        None => Ok(None),
+        Some(toml::Value::String(template)) => Ok(Some(template.clone())),
+        Some(other) => Err(TeraRenderErr::InvalidTemplate(format!(
+            "`{}` must be a string but is {}",
+            TEMPLATE_KEY, other
+        ))
+        .into()),
+    }
+}
+
+/// Returns the template named along the group paths. Like any other value,
+/// the `_template` of a nested group overrides the one of its parent and
+/// later group paths override earlier ones.
+pub fn group_template(
+    toml_config: &Config,
+    group_paths: &[GroupPath],
+) -> Result<Option<String>, TmpTomlErr> {
+    let mut template = None;
+    for group_path in group_paths {
+        let (group_id, sub_group_ids) = match group_path.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+        let group = match toml_config.get(group_id) {
+            Some(group) => group,
+            None => continue,
+        };
+        if let Some(named) = template_value(group.get(TEMPLATE_KEY))? {
+            template = Some(named);
+        }
+        let mut current_section: Option<&toml::value::Table> = None;
+        for sub_group_id in sub_group_ids {
+            let value_of = |key: &str| match current_section {
+                None => group.get(key),
+                Some(section) => section.get(key),
+            };
+            let section = match sub_section(value_of, sub_group_id) {
+                Some(section) => section,
+                None => break,
+            };
+            if let Some(named) = template_value(section.get(TEMPLATE_KEY))? {
+                template = Some(named);
+            }
+            current_section = Some(section);
+        }
+    }
+    Ok(template)
+}
+
+/// Reads the template the group paths of a config file name, failing when
+/// none of them does.
+pub fn config_template(
+    config_file_path: &Path,
+    group_paths: &[GroupPath],
+) -> Result<String, TmpTomlErr> {
+    let toml_config = parse_toml_groups_file(config_file_path.to_str(), group_paths)?;
+    group_template(&toml_config, group_paths)?.ok_or_else(|| {
+        let selection: Vec<String> = group_paths
+            .iter()
+            .map(|group_path| group_path.join("."))
+            .collect();
+        TeraRenderErr::TemplateNotSet(selection.join(", ")).into()
+    })
+}
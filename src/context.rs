@@ -0,0 +1,320 @@
+// Building of the structured template context from resolved group values and
+// any additional values passed in by the caller.
+
+use crate::{
+    precedence::Precedence,
+    tmptoml::{Group, ReadFileErr, TmpTomlErr},
+};
+use serde_json::{Map, Value};
+use std::{io::Read, process::Command, str::FromStr};
+
+pub type ContextValues = Map<String, Value>;
+
+#[derive(Debug)]
+pub enum ContextErr {
+    InvalidJson(serde_json::Error),
+    InvalidToml(toml::de::Error),
+    NotAnObject(String),
+    InvalidOverride(String),
+    /// A context command couldn't be run or exited unsuccessfully.
+    CommandFailed(String),
+    /// The context is larger or deeper than the limits allow.
+    LimitExceeded(String),
+}
+
+impl From<ContextErr> for TmpTomlErr {
+    fn from(err: ContextErr) -> Self {
+        TmpTomlErr::Context(err)
+    }
+}
+
+/// Converts a TOML value into the equivalent JSON value. Datetimes have no
+/// JSON equivalent and are represented by their TOML string form.
+pub fn toml_to_json(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::String(value) => Value::String(value.clone()),
+        toml::Value::Integer(value) => Value::from(*value),
+        toml::Value::Float(value) => Value::from(*value),
+        toml::Value::Boolean(value) => Value::Bool(*value),
+        toml::Value::Datetime(value) => Value::String(value.to_string()),
+        toml::Value::Array(values) => Value::Array(values.iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.clone(), toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+pub fn group_to_context_values(group: &Group) -> ContextValues {
+    group
+        .iter()
+        .map(|(key, value)| (key.clone(), toml_to_json(value)))
+        .collect()
+}
+
+/// Parses a JSON document whose top level is an object, e.g. the value of
+/// `--context-json`.
+pub fn parse_context_json(source: &str) -> Result<ContextValues, ContextErr> {
+    match serde_json::from_str(source).map_err(ContextErr::InvalidJson)? {
+        Value::Object(values) => Ok(values),
+        other => Err(ContextErr::NotAnObject(other.to_string())),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextFormat {
+    Json,
+    Toml,
+}
+
+impl FromStr for ContextFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ContextFormat::Json),
+            "toml" => Ok(ContextFormat::Toml),
+            other => Err(format!("unsupported context format: {}", other)),
+        }
+    }
+}
+
+/// Parses a JSON object or TOML document into context values.
+pub fn parse_context(source: &str, format: ContextFormat) -> Result<ContextValues, ContextErr> {
+    match format {
+        ContextFormat::Json => parse_context_json(source),
+        ContextFormat::Toml => {
+            let table: toml::value::Table =
+                toml::from_str(source).map_err(ContextErr::InvalidToml)?;
+            Ok(table
+                .iter()
+                .map(|(key, value)| (key.clone(), toml_to_json(value)))
+                .collect())
+        }
+    }
+}
+
+/// Reads and parses a context document piped to stdin.
+pub fn read_stdin_context(format: ContextFormat) -> Result<ContextValues, TmpTomlErr> {
+    let mut source = String::new();
+    std::io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|_| ReadFileErr::FileNotFound("<stdin>".to_string()))?;
+    Ok(parse_context(&source, format)?)
+}
+
+/// Runs `command` through the shell and parses its output, a JSON object when
+/// it starts with `{` and a TOML document otherwise. Its stderr is passed
+/// through.
+pub fn run_context_command(command: &str) -> Result<ContextValues, ContextErr> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let output = Command::new(shell)
+        .args([flag, command])
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(|err| ContextErr::CommandFailed(format!("{}: {}", command, err)))?;
+    if !output.status.success() {
+        return Err(ContextErr::CommandFailed(format!(
+            "{}: {}",
+            command, output.status
+        )));
+    }
+    let source = String::from_utf8_lossy(&output.stdout);
+    let format = if source.trim_start().starts_with('{') {
+        ContextFormat::Json
+    } else {
+        ContextFormat::Toml
+    };
+    parse_context(&source, format)
+}
+
+/// Deep merges `overlay` into `base`. Objects present on both sides are merged
+/// key by key, any other value of the overlay replaces the one in the base.
+pub fn merge_context_values(base: &mut ContextValues, overlay: ContextValues) {
+    merge_context_values_with_precedence(base, overlay, Precedence::LastWins)
+}
+
+/// Deep merges `overlay` into `base` like `merge_context_values`, keeping the
+/// values of the base when the earlier layer wins.
+pub fn merge_context_values_with_precedence(
+    base: &mut ContextValues,
+    overlay: ContextValues,
+    precedence: Precedence,
+) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(base_object)), Value::Object(overlay_object)) => {
+                merge_context_values_with_precedence(base_object, overlay_object, precedence)
+            }
+            (Some(_), _) if precedence.keeps_existing() => {}
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Applies a `key=value` override to `values`. The value is parsed as a TOML
+/// value so that `debug=true` yields a boolean and `port=8080` an integer,
+/// falling back to a string when it isn't valid TOML. `nan` and `inf` have no
+/// JSON equivalent and are rejected. With `typed` set to false the value is
+/// always a string.
+///
+/// Dotted keys (`database.pool.size=10`) set nested values, failing when an
+/// earlier override set one of the parents to a value that isn't a table. A
+/// trailing `[]` (`hosts[]=a`) appends to a list, so repeated overrides build
+/// it up.
+pub fn apply_override(
+    values: &mut ContextValues,
+    assignment: &str,
+    typed: bool,
+) -> Result<(), ContextErr> {
+    let invalid = || ContextErr::InvalidOverride(assignment.to_string());
+    let (key, raw_value) = assignment.split_once('=').ok_or_else(invalid)?;
+    let value = if typed {
+        match parse_toml_value(raw_value) {
+            Some(toml::Value::Float(number)) if !number.is_finite() => return Err(invalid()),
+            Some(value) => toml_to_json(&value),
+            None => Value::String(raw_value.to_string()),
+        }
+    } else {
+        Value::String(raw_value.to_string())
+    };
+
+    let (key, append) = match key.strip_suffix("[]") {
+        Some(key) => (key, true),
+        None => (key, false),
+    };
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments
+        .iter()
+        .any(|segment| segment.is_empty() || segment.contains(['[', ']']))
+    {
+        return Err(invalid());
+    }
+
+    let (last, parents) = segments.split_last().ok_or_else(invalid)?;
+    let mut target = values;
+    for segment in parents {
+        target = target
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Object(ContextValues::new()))
+            .as_object_mut()
+            .ok_or_else(invalid)?;
+    }
+
+    if append {
+        let entry = target
+            .entry(last.to_string())
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if !entry.is_array() {
+            *entry = Value::Array(Vec::new());
+        }
+        entry.as_array_mut().ok_or_else(invalid)?.push(value);
+    } else {
+        target.insert(last.to_string(), value);
+    }
+    Ok(())
+}
+
+fn parse_toml_value(raw_value: &str) -> Option<toml::Value> {
+    let mut document: toml::value::Table =
+        toml::from_str(&format!("value = {}", raw_value)).ok()?;
+    document.remove("value")
+}
+
+/// Caps on the size of the template context, so that a pathological config,
+/// e.g. a machine generated one with a million keys, fails with an error
+/// instead of exhausting the render. Unset limits aren't checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContextLimits {
+    /// Number of keys, those of nested tables included.
+    pub max_keys: Option<usize>,
+    /// Levels of nested tables and arrays, the top level being 1.
+    pub max_depth: Option<usize>,
+    /// Size of the context as JSON, in bytes.
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Default)]
+struct ContextSize {
+    keys: usize,
+    depth: usize,
+    bytes: usize,
+}
+
+impl ContextLimits {
+    pub fn is_unlimited(&self) -> bool {
+        *self == ContextLimits::default()
+    }
+
+    fn exceeded(&self, size: &ContextSize) -> Option<String> {
+        let exceeds = |limit: Option<usize>, value: usize| limit.filter(|limit| value > *limit);
+        if let Some(limit) = exceeds(self.max_keys, size.keys) {
+            return Some(format!("the context has more than {} keys", limit));
+        }
+        if let Some(limit) = exceeds(self.max_depth, size.depth) {
+            return Some(format!(
+                "the context is nested more than {} levels deep",
+                limit
+            ));
+        }
+        exceeds(self.max_bytes, size.bytes)
+            .map(|limit| format!("the context is larger than {} bytes", limit))
+    }
+
+    // Stops measuring as soon as a limit is exceeded, so that a huge context
+    // isn't walked whole.
+    fn measure(
+        &self,
+        value: &Value,
+        depth: usize,
+        size: &mut ContextSize,
+    ) -> Result<(), ContextErr> {
+        size.depth = size.depth.max(depth);
+        match value {
+            Value::Object(values) => {
+                size.bytes += 2;
+                for (key, value) in values {
+                    size.keys += 1;
+                    size.bytes += key.len() + 4;
+                    self.measure(value, depth + 1, size)?;
+                }
+            }
+            Value::Array(values) => {
+                size.bytes += 2;
+                for value in values {
+                    size.bytes += 1;
+                    self.measure(value, depth + 1, size)?;
+                }
+            }
+            Value::String(string) => size.bytes += string.len() + 2,
+            Value::Number(number) => size.bytes += number.to_string().len(),
+            Value::Bool(_) | Value::Null => size.bytes += 5,
+        }
+        match self.exceeded(size) {
+            Some(reason) => Err(ContextErr::LimitExceeded(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails when `values` exceed any of the limits.
+    pub fn check(&self, values: &ContextValues) -> Result<(), ContextErr> {
+        if self.is_unlimited() {
+            return Ok(());
+        }
+        let mut size = ContextSize::default();
+        for (key, value) in values {
+            size.keys += 1;
+            size.bytes += key.len() + 4;
+            self.measure(value, 1, &mut size)?;
+        }
+        Ok(())
+    }
+}
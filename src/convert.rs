@@ -0,0 +1,219 @@
+// Converters from other template syntaxes into Tera templates, used to ease
+// the migration of existing templates onto tmptoml.
+
+use crate::tmptoml::{read_file, TmpTomlErr};
+use std::{path::Path, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertFrom {
+    Envsubst,
+}
+
+impl FromStr for ConvertFrom {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "envsubst" => Ok(ConvertFrom::Envsubst),
+            other => Err(format!("unsupported template syntax: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// The converted Tera template.
+    pub template: String,
+    /// Variable names referenced by the source, in order of first appearance.
+    pub variables: Vec<String>,
+}
+
+pub fn convert_template(from: ConvertFrom, source: &str) -> Conversion {
+    match from {
+        ConvertFrom::Envsubst => convert_envsubst(source),
+    }
+}
+
+pub fn convert_template_file(from: ConvertFrom, path: &Path) -> Result<Conversion, TmpTomlErr> {
+    let source = read_file(path.to_str())?;
+    Ok(convert_template(from, &source))
+}
+
+/// Rewrites `${VAR}` and `$VAR` placeholders into `{{ VAR }}` expressions.
+/// Shell-style defaults (`${VAR:-fallback}`/`${VAR-fallback}`) become Tera
+/// `default` filters, `$$` is an escaped literal `$`, and anything already
+/// looking like Tera syntax is escaped so it survives rendering untouched.
+pub fn convert_envsubst(source: &str) -> Conversion {
+    let chars: Vec<char> = source.chars().collect();
+    let mut template = String::with_capacity(source.len());
+    let mut variables: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '{' && matches!(chars.get(i + 1), Some('{') | Some('%') | Some('#')) {
+            template.push_str(&format!("{{{{ \"{{{}\" }}}}", chars[i + 1]));
+            i += 2;
+            continue;
+        }
+        if c == '$' && chars.get(i + 1) == Some(&'$') {
+            template.push('$');
+            i += 2;
+            continue;
+        }
+        if c == '$' {
+            if let Some((placeholder, consumed)) = parse_placeholder(&chars[i + 1..]) {
+                if !variables.contains(&placeholder.name) {
+                    variables.push(placeholder.name.clone());
+                }
+                template.push_str(&placeholder.to_tera());
+                i += 1 + consumed;
+                continue;
+            }
+        }
+        template.push(c);
+        i += 1;
+    }
+
+    Conversion {
+        template,
+        variables,
+    }
+}
+
+struct Placeholder {
+    name: String,
+    default: Option<String>,
+}
+
+impl Placeholder {
+    fn to_tera(&self) -> String {
+        match &self.default {
+            Some(default) => format!(
+                "{{{{ {} | default(value={}) }}}}",
+                self.name,
+                tera_string_literal(default)
+            ),
+            None => format!("{{{{ {} }}}}", self.name),
+        }
+    }
+}
+
+// Tera string literals have no escape sequences, so pick a quote character
+// that does not occur in the value.
+fn tera_string_literal(value: &str) -> String {
+    let quote = ['"', '\'', '`']
+        .iter()
+        .find(|quote| !value.contains(**quote))
+        .unwrap_or(&'"');
+    format!("{}{}{}", quote, value, quote)
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn parse_name(chars: &[char]) -> Option<String> {
+    match chars.first() {
+        Some(c) if is_name_start(*c) => {
+            Some(chars.iter().take_while(|c| is_name_char(**c)).collect())
+        }
+        _ => None,
+    }
+}
+
+// Parses the placeholder following a `$`, returning it together with the
+// number of characters it spans.
+fn parse_placeholder(chars: &[char]) -> Option<(Placeholder, usize)> {
+    if chars.first() != Some(&'{') {
+        let name = parse_name(chars)?;
+        let consumed = name.len();
+        return Some((
+            Placeholder {
+                name,
+                default: None,
+            },
+            consumed,
+        ));
+    }
+
+    let close = chars.iter().position(|c| *c == '}')?;
+    let inner = &chars[1..close];
+    let name = parse_name(inner)?;
+    let rest: String = inner[name.len()..].iter().collect();
+    let default = if rest.is_empty() {
+        None
+    } else if let Some(default) = rest.strip_prefix(":-").or_else(|| rest.strip_prefix('-')) {
+        if ['"', '\'', '`']
+            .iter()
+            .all(|quote| default.contains(*quote))
+        {
+            return None;
+        }
+        Some(default.to_string())
+    } else {
+        return None;
+    };
+    Some((Placeholder { name, default }, close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_and_braced_placeholders_become_expressions() {
+        let conversion = convert_envsubst("host=$HOST port=${PORT} again=$HOST");
+        assert_eq!(
+            conversion.template,
+            "host={{ HOST }} port={{ PORT }} again={{ HOST }}"
+        );
+        assert_eq!(conversion.variables, ["HOST", "PORT"]);
+    }
+
+    #[test]
+    fn defaults_become_default_filters() {
+        assert_eq!(
+            convert_envsubst("${PORT:-8080} ${USER-nobody}").template,
+            "{{ PORT | default(value=\"8080\") }} {{ USER | default(value=\"nobody\") }}"
+        );
+        assert_eq!(
+            convert_envsubst("${GREETING:-say \"hi\"}").template,
+            "{{ GREETING | default(value='say \"hi\"') }}"
+        );
+        assert_eq!(
+            convert_envsubst("${EMPTY:-}").template,
+            "{{ EMPTY | default(value=\"\") }}"
+        );
+    }
+
+    #[test]
+    fn escaped_dollars_are_kept_literally() {
+        let conversion = convert_envsubst("cost: $$5, var: $$HOME, both: $$$HOME");
+        assert_eq!(
+            conversion.template,
+            "cost: $5, var: $HOME, both: ${{ HOME }}"
+        );
+        assert_eq!(conversion.variables, ["HOME"]);
+    }
+
+    #[test]
+    fn unsupported_expansions_are_left_untouched() {
+        let source = "$1 ${NAME:=x} ${NAME/a/b} ${UNCLOSED $";
+        let conversion = convert_envsubst(source);
+        assert_eq!(conversion.template, source);
+        assert!(conversion.variables.is_empty());
+    }
+
+    #[test]
+    fn existing_tera_syntax_is_escaped() {
+        assert_eq!(
+            convert_envsubst("{{ x }} {% if %} {# c #}").template,
+            "{{ \"{{\" }} x }} {{ \"{%\" }} if %} {{ \"{#\" }} c #}"
+        );
+    }
+}
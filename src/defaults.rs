@@ -0,0 +1,56 @@
+// Default group selection for renders that don't select a group, from the
+// `[_defaults]` table of the config or the environment, so that interactive
+// use doesn't need the same group IDs on every invocation.
+
+use crate::{
+    selective::parse_toml_groups_file,
+    tmptoml::{Config, GroupPath, TmpTomlErr},
+};
+use std::path::Path;
+
+/// The table holding the default `group` and `secondary` IDs. It is not a
+/// group itself.
+pub const DEFAULTS_TABLE: &str = "_defaults";
+pub const GROUP_ENV_VAR: &str = "TMPTOML_GROUP";
+pub const SECONDARY_ENV_VAR: &str = "TMPTOML_SECONDARY";
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Returns the default group path: `TMPTOML_GROUP` or else the `group` of
+/// the `[_defaults]` table, followed by `TMPTOML_SECONDARY` or else the
+/// `secondary` of the table. The table's secondary group only applies to its
+/// own group. Empty when neither defines a group.
+pub fn default_group_path(toml_config: &Config) -> GroupPath {
+    let defaults = toml_config.get(DEFAULTS_TABLE);
+    let default = |key: &str| {
+        defaults
+            .and_then(|defaults| defaults.get(key))
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+    let (group_id, secondary_group_id) = match env_var(GROUP_ENV_VAR) {
+        Some(group_id) => (Some(group_id), env_var(SECONDARY_ENV_VAR)),
+        None => (
+            default("group"),
+            env_var(SECONDARY_ENV_VAR).or_else(|| default("secondary")),
+        ),
+    };
+    match group_id {
+        Some(group_id) => std::iter::once(group_id)
+            .chain(secondary_group_id)
+            .collect(),
+        None => GroupPath::new(),
+    }
+}
+
+/// Reads the default group path of a config file, parsing only its
+/// `[_defaults]` table.
+pub fn default_group_path_file(config_file_path: &Path) -> Result<GroupPath, TmpTomlErr> {
+    let toml_config = parse_toml_groups_file(
+        config_file_path.to_str(),
+        &[vec![DEFAULTS_TABLE.to_string()]],
+    )?;
+    Ok(default_group_path(&toml_config))
+}
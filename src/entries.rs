@@ -0,0 +1,67 @@
+// Selection of an entry of an array of tables as a level of a group path,
+// e.g. `qa.databases[name=primary]`, so that inventory-style configs listing
+// their systems as `[[qa.databases]]` can render one of them with its values
+// promoted into the context, like those of a secondary group.
+
+// Splits `key[field=value]` into the key and the field and value selecting
+// the entry, the value optionally quoted.
+fn parse_selector(segment: &str) -> Option<(&str, &str, &str)> {
+    let (key, selector) = segment.strip_suffix(']')?.split_once('[')?;
+    let (field, value) = selector.split_once('=')?;
+    let value = value.trim();
+    let value = ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value);
+    Some((key.trim(), field.trim(), value))
+}
+
+fn matches_value(value: &toml::Value, wanted: &str) -> bool {
+    match value {
+        toml::Value::String(string) => string == wanted,
+        value => value.to_string() == wanted,
+    }
+}
+
+/// Looks up the section a segment of a group path names, `value_of` looking
+/// up the values of the current section: the table of a key, or for
+/// `key[field=value]` the first table of the array of the key whose `field`
+/// is `value`.
+pub(crate) fn sub_section<'a>(
+    value_of: impl Fn(&str) -> Option<&'a toml::Value>,
+    segment: &str,
+) -> Option<&'a toml::value::Table> {
+    match parse_selector(segment) {
+        Some((key, field, wanted)) => value_of(key)?
+            .as_array()?
+            .iter()
+            .filter_map(|entry| entry.as_table())
+            .find(|entry| {
+                entry
+                    .get(field)
+                    .is_some_and(|value| matches_value(value, wanted))
+            }),
+        None => value_of(segment)?.as_table(),
+    }
+}
+
+/// Lists the selectors of every entry of the array of tables a selector
+/// looks in, by the field it selects with, to suggest them when no entry
+/// matches.
+pub(crate) fn entry_selectors<'a>(
+    value_of: impl Fn(&str) -> Option<&'a toml::Value>,
+    segment: &str,
+) -> Option<Vec<String>> {
+    let (key, field, _) = parse_selector(segment)?;
+    let entries = value_of(key)?.as_array()?;
+    Some(
+        entries
+            .iter()
+            .filter_map(|entry| entry.as_table()?.get(field))
+            .map(|value| match value {
+                toml::Value::String(string) => format!("{}[{}={}]", key, field, string),
+                value => format!("{}[{}={}]", key, field, value),
+            })
+            .collect(),
+    )
+}
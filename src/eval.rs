@@ -0,0 +1,26 @@
+// Evaluation of a single Tera expression against the context of a group,
+// for shell scripts needing one derived value without a template file.
+
+use crate::tmptoml::{read_file, render_str, RenderOptions, TmpTomlErr};
+use std::path::Path;
+
+/// Wraps a bare expression like `port + 100` in `{{ }}`, leaving expressions
+/// already written as a template, e.g. `{{ port + 100 }}`, as they are.
+pub fn expression_template(expression: &str) -> String {
+    let trimmed = expression.trim();
+    match trimmed.starts_with("{{") || trimmed.starts_with("{%") {
+        true => trimmed.to_string(),
+        false => format!("{{{{ {} }}}}", trimmed),
+    }
+}
+
+/// Evaluates the expression with the values of the groups of the options,
+/// like rendering a template made of it.
+pub fn eval_expression(
+    config_file_path: &Path,
+    expression: &str,
+    options: &RenderOptions,
+) -> Result<String, TmpTomlErr> {
+    let config_content = read_file(config_file_path.to_str())?;
+    render_str(&config_content, &expression_template(expression), options)
+}
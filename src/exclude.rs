@@ -0,0 +1,67 @@
+// Selection of the files of a template directory to render, leaving out
+// editor swap files, READMEs, fixtures and the like. Files are excluded by
+// `.tmptomlignore` files, which use the `.gitignore` syntax, by `--exclude`
+// globs and optionally by `.gitignore` files.
+
+use crate::{
+    batch::BatchErr,
+    tmptoml::{check_filesystem_access, TeraRenderErr, TmpTomlErr},
+};
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use std::path::{Path, PathBuf};
+
+pub(crate) const IGNORE_FILE: &str = ".tmptomlignore";
+
+#[derive(Debug, Clone, Default)]
+pub struct TemplateFilter {
+    /// Globs of files to leave out, relative to the template directory.
+    pub exclude: Vec<String>,
+    /// Whether `.gitignore` files are honored too.
+    pub gitignore: bool,
+}
+
+/// Lists the files below `template_dir` that aren't excluded, sorted.
+pub(crate) fn list_template_files(
+    template_dir: &Path,
+    filter: &TemplateFilter,
+) -> Result<Vec<PathBuf>, TmpTomlErr> {
+    check_filesystem_access(&template_dir.display().to_string())?;
+    let invalid_pattern = |pattern: &str, err: ignore::Error| {
+        BatchErr::InvalidPattern(format!("{}: {}", pattern, err))
+    };
+    let mut overrides = OverrideBuilder::new(template_dir);
+    for pattern in &filter.exclude {
+        overrides
+            .add(&format!("!{}", pattern))
+            .map_err(|err| invalid_pattern(pattern, err))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|err| invalid_pattern(&filter.exclude.join(", "), err))?;
+
+    let walker = WalkBuilder::new(template_dir)
+        .hidden(false)
+        .follow_links(true)
+        .ignore(false)
+        .git_ignore(filter.gitignore)
+        .git_exclude(filter.gitignore)
+        .git_global(false)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .overrides(overrides)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = entry
+            .map_err(|_| TeraRenderErr::TemplateNotFound(template_dir.display().to_string()))?;
+        let is_file = entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file());
+        if is_file && entry.file_name() != IGNORE_FILE {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,312 @@
+// Exports of resolved group values for consumption by other tools.
+
+use crate::{
+    coercion::{join_key, type_name, Coercion},
+    context::toml_to_json,
+    selective::parse_toml_groups_file,
+    sensitive::{remove_sensitive, sensitive_keys},
+    tmptoml::{resolve_group, TmpTomlErr},
+};
+use std::{path::Path, str::FromStr};
+use toml::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Toml,
+    /// `export KEY='value'` lines, e.g. for direnv's `.envrc`.
+    Shell,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toml" => Ok(ExportFormat::Toml),
+            "shell" => Ok(ExportFormat::Shell),
+            other => Err(format!("unsupported export format: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportErr {
+    Serialize(String),
+    /// The exported document doesn't read back as the values exported, one
+    /// message per value lost or changed.
+    LossyRoundtrip(Vec<String>),
+}
+
+impl From<ExportErr> for TmpTomlErr {
+    fn from(err: ExportErr) -> Self {
+        TmpTomlErr::Export(err)
+    }
+}
+
+/// An exported document along with the values coerced to another type to fit
+/// its format.
+#[derive(Debug, Clone)]
+pub struct Exported {
+    pub output: String,
+    pub coercions: Vec<Coercion>,
+    /// The format of the document.
+    pub format: ExportFormat,
+    /// The values exported.
+    pub values: toml::value::Table,
+}
+
+/// Writes the merged values of a group and secondary group, exactly as a
+/// template would see them, as a single flat document with sorted keys.
+/// Sensitive values are left out.
+pub fn export_group(
+    config_file_path: &Path,
+    group_id: &str,
+    sec_group_id: &str,
+    format: ExportFormat,
+) -> Result<String, TmpTomlErr> {
+    export_group_with_coercions(config_file_path, group_id, sec_group_id, format, false)
+        .map(|exported| exported.output)
+}
+
+/// Exports the group like `export_group`, additionally reporting every value
+/// the format can't represent with its own type, e.g. integers exported as
+/// shell strings. Sensitive values are left out unless `include_sensitive`
+/// is set.
+pub fn export_group_with_coercions(
+    config_file_path: &Path,
+    group_id: &str,
+    sec_group_id: &str,
+    format: ExportFormat,
+    include_sensitive: bool,
+) -> Result<Exported, TmpTomlErr> {
+    let toml_config =
+        parse_toml_groups_file(config_file_path.to_str(), &[vec![group_id.to_string()]])?;
+    let mut values = resolve_group(&toml_config, group_id, sec_group_id)?;
+    if !include_sensitive {
+        let group_path = vec![group_id.to_string(), sec_group_id.to_string()];
+        remove_sensitive(&mut values, &sensitive_keys(&toml_config, &[group_path]));
+    }
+    let values: toml::value::Table = values.into_iter().collect();
+    match format {
+        ExportFormat::Toml => Ok(Exported {
+            output: toml::to_string(&Value::Table(values.clone()))
+                .map_err(|err| ExportErr::Serialize(err.to_string()))?,
+            coercions: Vec::new(),
+            format,
+            values,
+        }),
+        ExportFormat::Shell => {
+            let mut exports = Vec::new();
+            let mut coercions = Vec::new();
+            shell_exports("", "", &values, &mut exports, &mut coercions)?;
+            coercions.sort();
+            let lines: Vec<String> = exports
+                .iter()
+                .map(|export| format!("export {}={}", export.name, shell_quote(&export.value)))
+                .collect();
+            Ok(Exported {
+                output: lines.join("\n"),
+                coercions,
+                format,
+                values,
+            })
+        }
+    }
+}
+
+impl Exported {
+    /// Reads the exported document back and checks it holds the values
+    /// exported, failing with every value lost or changed. The coercions the
+    /// format makes on purpose, like shell values all being strings, aren't
+    /// losses.
+    pub fn verify_roundtrip(&self) -> Result<(), ExportErr> {
+        let mut losses = Vec::new();
+        match self.format {
+            ExportFormat::Toml => match toml::from_str::<Value>(&self.output) {
+                Ok(Value::Table(read)) => toml_losses("", &self.values, &read, &mut losses),
+                Ok(_) => losses.push("the document isn't a table".to_string()),
+                Err(err) => losses.push(format!("the document doesn't parse: {}", err)),
+            },
+            ExportFormat::Shell => {
+                let mut exports = Vec::new();
+                shell_exports("", "", &self.values, &mut exports, &mut Vec::new())?;
+                shell_losses(&exports, &self.output, &mut losses);
+            }
+        }
+        match losses.is_empty() {
+            true => Ok(()),
+            false => Err(ExportErr::LossyRoundtrip(losses)),
+        }
+    }
+}
+
+// NaN floats are equal to each other here, they are exported and read back
+// as `nan` alike.
+fn same_value(exported: &Value, read: &Value) -> bool {
+    match (exported, read) {
+        (Value::Float(exported), Value::Float(read)) if exported.is_nan() => read.is_nan(),
+        (Value::Array(exported), Value::Array(read)) => {
+            exported.len() == read.len()
+                && exported
+                    .iter()
+                    .zip(read)
+                    .all(|(exported, read)| same_value(exported, read))
+        }
+        (Value::Table(exported), Value::Table(read)) => {
+            exported.len() == read.len()
+                && exported
+                    .iter()
+                    .all(|(key, value)| read.get(key).is_some_and(|read| same_value(value, read)))
+        }
+        (exported, read) => exported == read,
+    }
+}
+
+fn toml_losses(
+    parent_key: &str,
+    exported: &toml::value::Table,
+    read: &toml::value::Table,
+    losses: &mut Vec<String>,
+) {
+    for (key, value) in exported {
+        let key_path = join_key(parent_key, key);
+        match (value, read.get(key)) {
+            (Value::Table(exported), Some(Value::Table(read))) => {
+                toml_losses(&key_path, exported, read, losses)
+            }
+            (_, None) => losses.push(format!("{}: missing when read back", key_path)),
+            (value, Some(read)) if !same_value(value, read) => losses.push(format!(
+                "{}: exported as {} but read back as {}",
+                key_path, value, read
+            )),
+            _ => {}
+        }
+    }
+    for key in read.keys().filter(|key| !exported.contains_key(*key)) {
+        losses.push(format!(
+            "{}: not exported but read back",
+            join_key(parent_key, key)
+        ));
+    }
+}
+
+// Reads `export NAME='value'` lines back like a shell would, the quoting
+// being the only syntax the export writes.
+fn read_shell_exports(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, quoted) = line.strip_prefix("export ")?.split_once('=')?;
+            let value = quoted
+                .strip_prefix('\'')?
+                .strip_suffix('\'')?
+                .replace("'\\''", "'");
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+fn shell_losses(exports: &[ShellExport], output: &str, losses: &mut Vec<String>) {
+    let read = read_shell_exports(output);
+    for export in exports {
+        if export.value.contains('\0') {
+            losses.push(format!(
+                "{}: contains a NUL byte, which environment variables can't hold",
+                export.key
+            ));
+        }
+        let others: Vec<&str> = exports
+            .iter()
+            .filter(|other| other.name == export.name && other.key != export.key)
+            .map(|other| other.key.as_str())
+            .collect();
+        if !others.is_empty() {
+            losses.push(format!(
+                "{}: exported as {} like {}",
+                export.key,
+                export.name,
+                others.join(", ")
+            ));
+            continue;
+        }
+        match read.iter().rev().find(|(name, _)| *name == export.name) {
+            None => losses.push(format!("{}: missing when read back", export.key)),
+            Some((_, value)) if *value != export.value => losses.push(format!(
+                "{}: exported as {} but read back as {}",
+                export.key,
+                shell_quote(&export.value),
+                shell_quote(value)
+            )),
+            Some(_) => {}
+        }
+    }
+}
+
+// Environment variable names are upper case, anything but letters, digits
+// and underscores becoming an underscore.
+fn shell_variable_name(prefix: &str, key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => c.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect();
+    let name = format!("{}{}", prefix, name);
+    match name.chars().next() {
+        Some('0'..='9') => format!("_{}", name),
+        _ => name,
+    }
+}
+
+// Single quotes keep everything literal, a single quote itself ending the
+// quoting for an escaped quote.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// A value exported as an environment variable, with the key it was exported
+// from.
+struct ShellExport {
+    key: String,
+    name: String,
+    value: String,
+}
+
+// Tables are flattened into `TABLE_KEY` names, arrays exported as JSON.
+// Every value but strings is coerced to a string.
+fn shell_exports(
+    prefix: &str,
+    parent_key: &str,
+    values: &toml::value::Table,
+    exports: &mut Vec<ShellExport>,
+    coercions: &mut Vec<Coercion>,
+) -> Result<(), ExportErr> {
+    for (key, value) in values {
+        let name = shell_variable_name(prefix, key);
+        let key = join_key(parent_key, key);
+        let exported = match value {
+            Value::Table(table) => {
+                shell_exports(&format!("{}_", name), &key, table, exports, coercions)?;
+                continue;
+            }
+            Value::String(string) => string.clone(),
+            Value::Array(_) => serde_json::to_string(&toml_to_json(value))
+                .map_err(|err| ExportErr::Serialize(err.to_string()))?,
+            other => other.to_string(),
+        };
+        if !value.is_str() {
+            coercions.push(Coercion {
+                key: key.clone(),
+                from: type_name(value),
+                to: "string",
+            });
+        }
+        exports.push(ShellExport {
+            key,
+            name,
+            value: exported,
+        });
+    }
+    Ok(())
+}
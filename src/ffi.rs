@@ -0,0 +1,115 @@
+// C ABI for embedding tmptoml's rendering in non-Rust programs. The matching
+// declarations live in include/tmptoml.h.
+
+use crate::tmptoml;
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr,
+};
+
+pub const TMPTOML_OK: c_int = 0;
+pub const TMPTOML_ERR: c_int = 1;
+pub const TMPTOML_INVALID_ARGUMENT: c_int = 2;
+
+unsafe fn to_str<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+    CStr::from_ptr(value).to_str().ok()
+}
+
+// Interior NUL bytes can't be represented in a C string, so they are dropped.
+fn to_c_string(value: String) -> *mut c_char {
+    CString::new(value.replace('\0', ""))
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+unsafe fn set_output(target: *mut *mut c_char, value: String) {
+    if !target.is_null() {
+        *target = to_c_string(value);
+    }
+}
+
+/// Renders the template file with the values of the group and (optional)
+/// secondary group of the config file.
+///
+/// On success returns `TMPTOML_OK` and stores the rendered template in `out`,
+/// otherwise stores an error message in `err`. Strings stored in `out` and
+/// `err` must be released with `tmptoml_free_string`.
+///
+/// # Safety
+///
+/// `config`, `template` and `group` must be valid NUL-terminated UTF-8
+/// strings, `secondary` must be one or NULL, and `out` and `err` must be
+/// valid pointers or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn tmptoml_render(
+    config: *const c_char,
+    template: *const c_char,
+    group: *const c_char,
+    secondary: *const c_char,
+    out: *mut *mut c_char,
+    err: *mut *mut c_char,
+) -> c_int {
+    for target in [out, err] {
+        if !target.is_null() {
+            *target = ptr::null_mut();
+        }
+    }
+
+    let (config, template, group) = match (to_str(config), to_str(template), to_str(group)) {
+        (Some(config), Some(template), Some(group)) => (config, template, group),
+        _ => {
+            set_output(
+                err,
+                "config, template and group must be valid UTF-8 strings".to_string(),
+            );
+            return TMPTOML_INVALID_ARGUMENT;
+        }
+    };
+    let mut group_path = vec![group.to_string()];
+    if !secondary.is_null() {
+        match to_str(secondary) {
+            Some(secondary) => group_path.push(secondary.to_string()),
+            None => {
+                set_output(err, "secondary must be a valid UTF-8 string".to_string());
+                return TMPTOML_INVALID_ARGUMENT;
+            }
+        }
+    }
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        tmptoml::render_template_path(Path::new(config), Path::new(template), &group_path)
+    }));
+    match result {
+        Ok(Ok(rendered)) => {
+            set_output(out, rendered);
+            TMPTOML_OK
+        }
+        Ok(Err(render_err)) => {
+            set_output(err, format!("{:?}", render_err));
+            TMPTOML_ERR
+        }
+        Err(_) => {
+            set_output(err, "tmptoml panicked while rendering".to_string());
+            TMPTOML_ERR
+        }
+    }
+}
+
+/// Releases a string returned by `tmptoml_render`.
+///
+/// # Safety
+///
+/// `value` must be NULL or a pointer previously returned through `out` or
+/// `err` of `tmptoml_render`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tmptoml_free_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
@@ -0,0 +1,486 @@
+// Custom Tera filters available to every template, on top of Tera's
+// built-in filters.
+
+use crate::{
+    inflect,
+    locale::{find_locale, Locale, DEFAULT_LOCALE},
+    tmptoml::{RenderOptions, TeraRenderErr},
+};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::{collections::HashMap, fmt::Write};
+use tera::{Tera, Value};
+
+pub(crate) fn register_filters(
+    tera: &mut Tera,
+    options: &RenderOptions,
+) -> Result<(), TeraRenderErr> {
+    let default_locale = match &options.locale {
+        Some(code) => {
+            find_locale(code).ok_or_else(|| TeraRenderErr::UnknownLocale(code.clone()))?
+        }
+        None => DEFAULT_LOCALE,
+    };
+
+    tera.register_filter(
+        "format_number",
+        move |value: &Value, args: &HashMap<String, Value>| {
+            format_number(value, args, default_locale)
+        },
+    );
+    tera.register_filter(
+        "format_date",
+        move |value: &Value, args: &HashMap<String, Value>| {
+            format_date(value, args, default_locale)
+        },
+    );
+    tera.register_filter("pluralize", pluralize);
+    tera.register_filter("singularize", singularize);
+    tera.register_filter("ordinal", ordinal);
+    tera.register_filter("toyaml", toyaml);
+    tera.register_filter("indent", indent);
+    tera.register_filter("nindent", nindent);
+    tera.register_filter("join", join);
+    tera.register_filter("split", split);
+    tera.register_filter("zip", zip);
+    tera.register_filter("uniq", uniq);
+    Ok(())
+}
+
+fn filter_locale(
+    filter: &str,
+    args: &HashMap<String, Value>,
+    default_locale: &'static Locale,
+) -> tera::Result<&'static Locale> {
+    match args.get("locale") {
+        Some(Value::String(code)) => find_locale(code).ok_or_else(|| {
+            tera::Error::msg(format!(
+                "Filter `{}` received an unknown locale: {}",
+                filter, code
+            ))
+        }),
+        Some(other) => Err(tera::Error::msg(format!(
+            "Filter `{}` expected the `locale` argument to be a string but got {}",
+            filter, other
+        ))),
+        None => Ok(default_locale),
+    }
+}
+
+fn group_digits(digits: &str, separator: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() * 2);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Formats a number with the digit grouping and decimal separator of the
+/// locale, e.g. `1234567.891 | format_number(decimals=2)` renders
+/// `1.234.567,89` for `de-DE`. Integers default to no decimals and floats to
+/// two.
+fn format_number(
+    value: &Value,
+    args: &HashMap<String, Value>,
+    default_locale: &'static Locale,
+) -> tera::Result<Value> {
+    let locale = filter_locale("format_number", args, default_locale)?;
+    let decimals = match args.get("decimals") {
+        Some(decimals) => Some(decimals.as_u64().ok_or_else(|| {
+            tera::Error::msg(format!(
+                "Filter `format_number` expected the `decimals` argument to be a positive integer but got {}",
+                decimals
+            ))
+        })? as usize),
+        None => None,
+    };
+
+    let formatted = match value {
+        Value::Number(number) if number.is_i64() || number.is_u64() => {
+            let digits = number.to_string();
+            match decimals {
+                Some(decimals) if decimals > 0 => format!("{}.{}", digits, "0".repeat(decimals)),
+                _ => digits,
+            }
+        }
+        _ => {
+            let number = match value {
+                Value::Number(number) => number.as_f64(),
+                Value::String(number) => number.trim().parse::<f64>().ok(),
+                _ => None,
+            }
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| {
+                tera::Error::msg(format!(
+                    "Filter `format_number` was used on a value that isn't a number: {}",
+                    value
+                ))
+            })?;
+            format!("{:.*}", decimals.unwrap_or(2), number)
+        }
+    };
+
+    let (sign, unsigned) = match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.chars().any(|c| c.is_ascii_digit() && c != '0') => {
+            ("-", unsigned)
+        }
+        Some(unsigned) => ("", unsigned),
+        None => ("", formatted.as_str()),
+    };
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (unsigned, None),
+    };
+
+    let mut localized = format!("{}{}", sign, group_digits(integer, locale.group_separator));
+    if let Some(fraction) = fraction {
+        localized.push_str(locale.decimal_separator);
+        localized.push_str(fraction);
+    }
+    Ok(Value::String(localized))
+}
+
+fn parse_date(value: &Value) -> Option<DateTime<FixedOffset>> {
+    let utc = FixedOffset::east(0);
+    match value {
+        Value::Number(timestamp) => timestamp
+            .as_i64()
+            .map(|timestamp| Utc.timestamp(timestamp, 0).with_timezone(&utc)),
+        Value::String(date) => DateTime::parse_from_rfc3339(date).ok().or_else(|| {
+            ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+                .or_else(|| {
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .ok()
+                        .map(|date| date.and_hms(0, 0, 0))
+                })
+                .and_then(|date| utc.from_local_datetime(&date).single())
+        }),
+        _ => None,
+    }
+}
+
+// Replaces the month and weekday name specifiers of a strftime format with
+// the names of the locale, leaving everything else to chrono.
+fn localize_date_format(format: &str, date: &DateTime<FixedOffset>, locale: &Locale) -> String {
+    let month = date.month0() as usize;
+    let weekday = date.weekday().num_days_from_monday() as usize;
+    let mut localized = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localized.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('B') => localized.push_str(locale.months[month]),
+            Some('b') | Some('h') => localized.push_str(locale.months_abbr[month]),
+            Some('A') => localized.push_str(locale.weekdays[weekday]),
+            Some('a') => localized.push_str(locale.weekdays_abbr[weekday]),
+            Some(specifier) => {
+                localized.push('%');
+                localized.push(specifier);
+            }
+            None => localized.push('%'),
+        }
+    }
+    localized
+}
+
+/// Formats a date with month and weekday names of the locale. Accepts RFC
+/// 3339 strings, `YYYY-MM-DD[ HH:MM:SS]` strings and Unix timestamps; the
+/// `format` argument uses chrono's strftime syntax and defaults to the
+/// locale's long date format.
+fn format_date(
+    value: &Value,
+    args: &HashMap<String, Value>,
+    default_locale: &'static Locale,
+) -> tera::Result<Value> {
+    let locale = filter_locale("format_date", args, default_locale)?;
+    let format = match args.get("format") {
+        Some(Value::String(format)) => format.as_str(),
+        Some(other) => {
+            return Err(tera::Error::msg(format!(
+                "Filter `format_date` expected the `format` argument to be a string but got {}",
+                other
+            )))
+        }
+        None => locale.date_format,
+    };
+    let date = parse_date(value).ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `format_date` was used on a value that isn't a date: {}",
+            value
+        ))
+    })?;
+
+    let mut formatted = String::new();
+    write!(
+        formatted,
+        "{}",
+        date.format(&localize_date_format(format, &date, locale))
+    )
+    .map_err(|_| {
+        tera::Error::msg(format!(
+            "Filter `format_date` received an invalid format: {}",
+            format
+        ))
+    })?;
+    Ok(Value::String(formatted))
+}
+
+fn string_arg<'a>(
+    filter: &str,
+    name: &str,
+    args: &'a HashMap<String, Value>,
+) -> tera::Result<Option<&'a str>> {
+    match args.get(name) {
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(other) => Err(tera::Error::msg(format!(
+            "Filter `{}` expected the `{}` argument to be a string but got {}",
+            filter, name, other
+        ))),
+        None => Ok(None),
+    }
+}
+
+fn is_one(number: f64) -> bool {
+    (number.abs() - 1.).abs() <= f64::EPSILON
+}
+
+/// Pluralizes a word, e.g. `"server" | pluralize(count=3)` renders `servers`
+/// and `"server" | pluralize(count=1)` keeps `server`. Without `count` the
+/// plural is always returned and `plural="..."` overrides the computed form.
+///
+/// Used on a number it behaves like Tera's built-in filter and returns the
+/// `singular` (default empty) or `plural` (default `s`) suffix.
+fn pluralize(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let plural = string_arg("pluralize", "plural", args)?;
+    match value {
+        Value::Number(number) => {
+            let singular = string_arg("pluralize", "singular", args)?;
+            let is_singular = number.as_f64().map(is_one).unwrap_or(false);
+            let suffix = if is_singular {
+                singular.unwrap_or("")
+            } else {
+                plural.unwrap_or("s")
+            };
+            Ok(Value::String(suffix.to_string()))
+        }
+        Value::String(word) => {
+            let count = match args.get("count") {
+                Some(count) => Some(count.as_f64().ok_or_else(|| {
+                    tera::Error::msg(format!(
+                        "Filter `pluralize` expected the `count` argument to be a number but got {}",
+                        count
+                    ))
+                })?),
+                None => None,
+            };
+            if count.map(is_one).unwrap_or(false) {
+                return Ok(Value::String(word.clone()));
+            }
+            Ok(Value::String(match plural {
+                Some(plural) => plural.to_string(),
+                None => inflect::pluralize(word),
+            }))
+        }
+        other => Err(tera::Error::msg(format!(
+            "Filter `pluralize` was used on a value that is neither a string nor a number: {}",
+            other
+        ))),
+    }
+}
+
+fn singularize(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    match value {
+        Value::String(word) => Ok(Value::String(inflect::singularize(word))),
+        other => Err(tera::Error::msg(format!(
+            "Filter `singularize` was used on a value that isn't a string: {}",
+            other
+        ))),
+    }
+}
+
+fn ordinal(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let number = match value {
+        Value::Number(number) => number.as_i64().or_else(|| {
+            number
+                .as_f64()
+                .filter(|n| n.fract() == 0.)
+                .map(|n| n as i64)
+        }),
+        Value::String(number) => number.trim().parse::<i64>().ok(),
+        _ => None,
+    };
+    match number {
+        Some(number) => Ok(Value::String(inflect::ordinal(number))),
+        None => Err(tera::Error::msg(format!(
+            "Filter `ordinal` was used on a value that isn't an integer: {}",
+            value
+        ))),
+    }
+}
+
+/// Serializes a value to YAML like Helm's `toYaml`: without the document
+/// marker and without a trailing newline.
+fn toyaml(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let yaml = serde_yaml::to_string(value).map_err(|err| {
+        tera::Error::msg(format!(
+            "Filter `toyaml` failed to serialize the value: {}",
+            err
+        ))
+    })?;
+    let yaml = yaml.strip_prefix("---\n").unwrap_or(&yaml);
+    Ok(Value::String(yaml.trim_end_matches('\n').to_string()))
+}
+
+fn indent_lines(
+    filter: &str,
+    value: &Value,
+    args: &HashMap<String, Value>,
+) -> tera::Result<String> {
+    let width = match args.get("n").or_else(|| args.get("width")) {
+        Some(width) => width.as_u64().ok_or_else(|| {
+            tera::Error::msg(format!(
+                "Filter `{}` expected the `n` argument to be a positive integer but got {}",
+                filter, width
+            ))
+        })? as usize,
+        None => {
+            return Err(tera::Error::msg(format!(
+                "Filter `{}` expected an `n` argument",
+                filter
+            )))
+        }
+    };
+    let text = match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let padding = " ".repeat(width);
+    Ok(text
+        .split('\n')
+        .map(|line| format!("{}{}", padding, line))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// Indents every line by `n` spaces, like Helm's `indent`.
+fn indent(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(Value::String(indent_lines("indent", value, args)?))
+}
+
+/// Like `indent` but starting with a newline, like Helm's `nindent`, so that
+/// `key: {{ value | toyaml | nindent(n=4) }}` nests a block under `key`.
+fn nindent(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    Ok(Value::String(format!(
+        "\n{}",
+        indent_lines("nindent", value, args)?
+    )))
+}
+
+fn array_value<'a>(filter: &str, value: &'a Value) -> tera::Result<&'a Vec<Value>> {
+    value.as_array().ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `{}` was used on a value that isn't an array: {}",
+            filter, value
+        ))
+    })
+}
+
+/// Joins the items of an array like Tera's built-in filter, wrapping every
+/// item in `prefix` and `suffix`, e.g. `hosts | join(sep=" ", suffix=":80")`
+/// renders `10.0.0.1:80 10.0.0.2:80`.
+fn join(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let items = array_value("join", value)?;
+    let sep = string_arg("join", "sep", args)?.unwrap_or("");
+    let prefix = string_arg("join", "prefix", args)?.unwrap_or("");
+    let suffix = string_arg("join", "suffix", args)?.unwrap_or("");
+    let joined = items
+        .iter()
+        .map(|item| match item {
+            Value::String(item) => format!("{}{}{}", prefix, item, suffix),
+            other => format!("{}{}{}", prefix, other, suffix),
+        })
+        .collect::<Vec<String>>()
+        .join(sep);
+    Ok(Value::String(joined))
+}
+
+/// Splits a string into a list like Tera's built-in filter, taking `sep` as
+/// well as Tera's `pat`. With `trim=true`, e.g. `"a, b,,c" | split(sep=",",
+/// trim=true)` returns `["a", "b", "c"]`: items are trimmed and empty ones
+/// dropped, as in comma-separated config values.
+fn split(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let text = value.as_str().ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `split` was used on a value that isn't a string: {}",
+            value
+        ))
+    })?;
+    let sep = match string_arg("split", "pat", args)? {
+        Some(pat) => pat,
+        None => string_arg("split", "sep", args)?
+            .ok_or_else(|| tera::Error::msg("Filter `split` expected a `sep` argument"))?,
+    };
+    let sep = sep.replace("\\n", "\n").replace("\\t", "\t");
+    let trim = match args.get("trim") {
+        Some(Value::Bool(trim)) => *trim,
+        Some(other) => {
+            return Err(tera::Error::msg(format!(
+                "Filter `split` expected the `trim` argument to be a boolean but got {}",
+                other
+            )))
+        }
+        None => false,
+    };
+    let items = text.split(&sep);
+    Ok(Value::from(if trim {
+        items
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<&str>>()
+    } else {
+        items.collect()
+    }))
+}
+
+/// Pairs the items of two arrays, e.g. `names | zip(with=ports)` returns
+/// `[["web", 80], ["api", 8080]]`, stopping at the end of the shorter one.
+fn zip(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let items = array_value("zip", value)?;
+    let others = match args.get("with") {
+        Some(Value::Array(others)) => others,
+        Some(other) => {
+            return Err(tera::Error::msg(format!(
+                "Filter `zip` expected the `with` argument to be an array but got {}",
+                other
+            )))
+        }
+        None => return Err(tera::Error::msg("Filter `zip` expected a `with` argument")),
+    };
+    Ok(Value::Array(
+        items
+            .iter()
+            .zip(others)
+            .map(|(item, other)| Value::Array(vec![item.clone(), other.clone()]))
+            .collect(),
+    ))
+}
+
+/// Drops the repeated items of an array, keeping the first occurrence of
+/// each in order. Unlike Tera's `unique`, items can be of any type, tables
+/// and arrays included, and strings are compared case sensitively.
+fn uniq(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let mut unique: Vec<Value> = Vec::new();
+    for item in array_value("uniq", value)? {
+        if !unique.contains(item) {
+            unique.push(item.clone());
+        }
+    }
+    Ok(Value::Array(unique))
+}
@@ -0,0 +1,52 @@
+// Listing of the effective values of every group of a config, used to debug
+// how values are merged.
+
+use crate::{
+    sensitive::{redact_sensitive, sensitive_keys},
+    tmptoml::{
+        group_ids, parse_toml_to_config, resolve_group_path, secondary_group_ids, Group, TmpTomlErr,
+    },
+};
+use std::{collections::BTreeMap, path::Path};
+
+/// Prints every group / secondary group combination of the config with the
+/// flattened values a template would see, their types and their values.
+/// Groups without secondary groups are listed with their own values.
+/// Sensitive values are redacted.
+pub fn flatten_config_file(config_file_path: &Path) -> Result<String, TmpTomlErr> {
+    let toml_config = parse_toml_to_config(config_file_path.to_str())?;
+    let mut group_ids: Vec<&String> = group_ids(&toml_config).collect();
+    group_ids.sort();
+    let resolve = |group_path: Vec<String>| -> Result<Group, TmpTomlErr> {
+        let mut values = resolve_group_path(&toml_config, &group_path)?;
+        redact_sensitive(&mut values, &sensitive_keys(&toml_config, &[group_path]));
+        Ok(values)
+    };
+
+    let mut sections: Vec<String> = Vec::new();
+    for group_id in group_ids {
+        let group_section = &toml_config[group_id];
+        let sec_group_ids = secondary_group_ids(group_section);
+        if sec_group_ids.is_empty() {
+            let values = resolve(vec![group_id.to_string()])?;
+            sections.push(format_section(group_id, &values));
+        }
+        for sec_group_id in sec_group_ids {
+            let values = resolve(vec![group_id.to_string(), sec_group_id.to_string()])?;
+            sections.push(format_section(
+                &format!("{}.{}", group_id, sec_group_id),
+                &values,
+            ));
+        }
+    }
+    Ok(sections.join("\n").trim_end().to_string())
+}
+
+fn format_section(name: &str, values: &Group) -> String {
+    let sorted: BTreeMap<&String, &toml::Value> = values.iter().collect();
+    let mut section = format!("[{}]\n", name);
+    for (key, value) in sorted {
+        section.push_str(&format!("{} ({}) = {}\n", key, value.type_str(), value));
+    }
+    section
+}
@@ -0,0 +1,226 @@
+// Custom Tera functions reading files, so that templates can inline snippets
+// such as SSH public keys or license headers. Templates only ever get to read
+// below the root directory the caller allows, and can't read files at all
+// when none is given.
+
+use crate::tmptoml::{check_filesystem_access, RenderOptions};
+use chrono::{TimeZone, Utc};
+use globset::GlobBuilder;
+use ignore::WalkBuilder;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tera::{Tera, Value};
+
+pub(crate) fn register_functions(tera: &mut Tera, options: &RenderOptions) {
+    let root = options.restrict_root.clone();
+    tera.register_function("file", move |args: &HashMap<String, Value>| {
+        file(root.as_deref(), args)
+    });
+    let root = options.restrict_root.clone();
+    tera.register_function("glob", move |args: &HashMap<String, Value>| {
+        glob(root.as_deref(), args)
+    });
+    let root = options.restrict_root.clone();
+    tera.register_function("checksum", move |args: &HashMap<String, Value>| {
+        checksum(root.as_deref(), args)
+    });
+    // Rendered artifacts get hashed for attestation, so reproducible renders
+    // replace Tera's builtin functions reading the clock, the environment or
+    // a random number generator.
+    if options.reproducible {
+        let source_date_epoch = options.source_date_epoch;
+        tera.register_function("now", move |args: &HashMap<String, Value>| {
+            pinned_now(source_date_epoch, args)
+        });
+        for function in ["get_env", "get_random"] {
+            tera.register_function(function, move |_: &HashMap<String, Value>| {
+                Err(tera::Error::msg(format!(
+                    "Function `{}` is disabled by --reproducible",
+                    function
+                )))
+            });
+        }
+    }
+}
+
+fn string_arg<'a>(
+    function: &str,
+    args: &'a HashMap<String, Value>,
+    name: &str,
+) -> tera::Result<&'a str> {
+    match args.get(name) {
+        Some(Value::String(value)) => Ok(value),
+        Some(other) => Err(tera::Error::msg(format!(
+            "Function `{}` expected the `{}` argument to be a string but got {}",
+            function, name, other
+        ))),
+        None => Err(tera::Error::msg(format!(
+            "Function `{}` requires the `{}` argument",
+            function, name
+        ))),
+    }
+}
+
+fn bool_arg(function: &str, args: &HashMap<String, Value>, name: &str) -> tera::Result<bool> {
+    match args.get(name) {
+        Some(Value::Bool(value)) => Ok(*value),
+        Some(other) => Err(tera::Error::msg(format!(
+            "Function `{}` expected the `{}` argument to be a boolean but got {}",
+            function, name, other
+        ))),
+        None => Ok(false),
+    }
+}
+
+/// `now()` in reproducible mode, returning the source date rather than the
+/// current time, always in UTC since the local time zone depends on the
+/// host.
+fn pinned_now(
+    source_date_epoch: Option<i64>,
+    args: &HashMap<String, Value>,
+) -> tera::Result<Value> {
+    let timestamp = bool_arg("now", args, "timestamp")?;
+    bool_arg("now", args, "utc")?;
+    let epoch = source_date_epoch.ok_or_else(|| {
+        tera::Error::msg(
+            "Function `now` needs SOURCE_DATE_EPOCH or --source-date-epoch with --reproducible",
+        )
+    })?;
+    if timestamp {
+        return Ok(Value::from(epoch));
+    }
+    Utc.timestamp_opt(epoch, 0)
+        .single()
+        .map(|date| Value::String(date.to_rfc3339()))
+        .ok_or_else(|| {
+            tera::Error::msg(format!(
+                "Function `now` received an invalid source date {}",
+                epoch
+            ))
+        })
+}
+
+fn allowed_root(function: &str, root: Option<&Path>) -> tera::Result<PathBuf> {
+    let root = root.ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Function `{}` can only read files below a root directory, set with --restrict-root",
+            function
+        ))
+    })?;
+    check_filesystem_access(&root.display().to_string())
+        .map_err(|err| tera::Error::msg(format!("{:?}", err)))?;
+    root.canonicalize().map_err(|err| {
+        tera::Error::msg(format!(
+            "Function `{}` can't access the root directory {}: {}",
+            function,
+            root.display(),
+            err
+        ))
+    })
+}
+
+// Resolves a path relative to the root directory, refusing paths leading
+// outside of it through `..` or symbolic links.
+fn resolve_below_root(function: &str, root: Option<&Path>, path: &str) -> tera::Result<PathBuf> {
+    let root = allowed_root(function, root)?;
+    let resolved = root.join(path).canonicalize().map_err(|err| {
+        tera::Error::msg(format!(
+            "Function `{}` can't read {}: {}",
+            function, path, err
+        ))
+    })?;
+    if !resolved.starts_with(&root) {
+        return Err(tera::Error::msg(format!(
+            "Function `{}` can't read {}, it is outside of {}",
+            function,
+            path,
+            root.display()
+        )));
+    }
+    Ok(resolved)
+}
+
+/// `file(path="keys/deploy.pub")` returns the content of a file, the path
+/// being relative to the root directory. Paths leading outside of the root,
+/// through `..` or symbolic links, are refused.
+fn file(root: Option<&Path>, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let path = string_arg("file", args, "path")?;
+    let resolved = resolve_below_root("file", root, path)?;
+    fs::read_to_string(&resolved)
+        .map(Value::String)
+        .map_err(|err| tera::Error::msg(format!("Function `file` can't read {}: {}", path, err)))
+}
+
+/// `checksum(value=data)` returns the SHA-256 of a value as hex, strings
+/// being hashed as they are and other values as their JSON, whose tables are
+/// sorted by key. `checksum(file="nginx.conf")` hashes a file below the root
+/// directory instead. `algorithm` only accepts `sha256`, so that a template
+/// asking for another algorithm fails rather than getting a SHA-256.
+fn checksum(root: Option<&Path>, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let content = match (args.get("value"), args.get("file")) {
+        (Some(Value::String(value)), None) => value.clone().into_bytes(),
+        (Some(value), None) => value.to_string().into_bytes(),
+        (None, Some(_)) => {
+            let path = string_arg("checksum", args, "file")?;
+            let resolved = resolve_below_root("checksum", root, path)?;
+            fs::read(&resolved).map_err(|err| {
+                tera::Error::msg(format!("Function `checksum` can't read {}: {}", path, err))
+            })?
+        }
+        _ => {
+            return Err(tera::Error::msg(
+                "Function `checksum` requires either the `value` or the `file` argument",
+            ))
+        }
+    };
+    let algorithm = match args.get("algorithm") {
+        Some(_) => string_arg("checksum", args, "algorithm")?,
+        None => "sha256",
+    };
+    match algorithm {
+        "sha256" => Ok(Value::String(format!("{:x}", Sha256::digest(&content)))),
+        other => Err(tera::Error::msg(format!(
+            "Function `checksum` only supports the sha256 algorithm but got {}",
+            other
+        ))),
+    }
+}
+
+/// `glob(pattern="keys/*.pub")` lists the files below the root directory
+/// matching the pattern, relative to the root and sorted. `*` doesn't match
+/// across directories, `**` does.
+fn glob(root: Option<&Path>, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let root = allowed_root("glob", root)?;
+    let pattern = string_arg("glob", args, "pattern")?;
+    let matcher = GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|err| {
+            tera::Error::msg(format!(
+                "Function `glob` received an invalid pattern {}: {}",
+                pattern, err
+            ))
+        })?
+        .compile_matcher();
+    // Symbolic links aren't followed, so that the walk stays below the root.
+    let walker = WalkBuilder::new(&root).standard_filters(false).build();
+    let mut paths = Vec::new();
+    for entry in walker.flatten() {
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+        if matcher.is_match(relative) {
+            paths.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    paths.sort();
+    Ok(Value::from(paths))
+}
@@ -0,0 +1,91 @@
+// Importers that turn data from other tools into tmptoml's group /
+// secondary-group config layout.
+
+use crate::tmptoml::{read_file, TmpTomlErr};
+use std::{path::Path, str::FromStr};
+use toml::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFrom {
+    Helm,
+}
+
+impl FromStr for ImportFrom {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "helm" => Ok(ImportFrom::Helm),
+            other => Err(format!("unsupported import source: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportErr {
+    InvalidYaml(serde_yaml::Error),
+    NotAMapping,
+    Serialize(toml::ser::Error),
+}
+
+impl From<ImportErr> for TmpTomlErr {
+    fn from(err: ImportErr) -> Self {
+        TmpTomlErr::Import(err)
+    }
+}
+
+pub fn import_file(from: ImportFrom, path: &Path, group_id: &str) -> Result<String, TmpTomlErr> {
+    let source = read_file(path.to_str())?;
+    match from {
+        ImportFrom::Helm => Ok(import_helm_values(&source, group_id)?),
+    }
+}
+
+/// Converts a Helm values document into a config with a single `group_id`
+/// group. Top-level scalars become group values, top-level mappings become
+/// secondary groups and deeper nesting is kept as nested tables. YAML nulls
+/// have no TOML equivalent and are dropped.
+pub fn import_helm_values(source: &str, group_id: &str) -> Result<String, ImportErr> {
+    let values: serde_yaml::Value = serde_yaml::from_str(source).map_err(ImportErr::InvalidYaml)?;
+    let group = match yaml_to_toml(values) {
+        Some(Value::Table(table)) => table,
+        None => toml::value::Table::new(),
+        Some(_) => return Err(ImportErr::NotAMapping),
+    };
+
+    let mut config = toml::value::Table::new();
+    config.insert(group_id.to_string(), Value::Table(group));
+    toml::to_string(&Value::Table(config)).map_err(ImportErr::Serialize)
+}
+
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(key) => key,
+        serde_yaml::Value::Bool(key) => key.to_string(),
+        serde_yaml::Value::Number(key) => key.to_string(),
+        other => serde_yaml::to_string(&other)
+            .map(|key| key.trim_start_matches("---").trim().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn yaml_to_toml(value: serde_yaml::Value) -> Option<Value> {
+    match value {
+        serde_yaml::Value::Null => None,
+        serde_yaml::Value::Bool(value) => Some(Value::Boolean(value)),
+        serde_yaml::Value::Number(number) => match number.as_i64() {
+            Some(value) => Some(Value::Integer(value)),
+            None => number.as_f64().map(Value::Float),
+        },
+        serde_yaml::Value::String(value) => Some(Value::String(value)),
+        serde_yaml::Value::Sequence(items) => Some(Value::Array(
+            items.into_iter().filter_map(yaml_to_toml).collect(),
+        )),
+        serde_yaml::Value::Mapping(mapping) => Some(Value::Table(
+            mapping
+                .into_iter()
+                .filter_map(|(key, value)| yaml_to_toml(value).map(|value| (yaml_key(key), value)))
+                .collect(),
+        )),
+    }
+}
@@ -0,0 +1,163 @@
+// English inflection rules backing the pluralize, singularize and ordinal
+// filters. They cover the words commonly found in generated messages rather
+// than the whole language.
+
+const IRREGULAR: [(&str, &str); 19] = [
+    ("person", "people"),
+    ("child", "children"),
+    ("man", "men"),
+    ("woman", "women"),
+    ("mouse", "mice"),
+    ("goose", "geese"),
+    ("foot", "feet"),
+    ("tooth", "teeth"),
+    ("ox", "oxen"),
+    ("index", "indices"),
+    ("matrix", "matrices"),
+    ("vertex", "vertices"),
+    ("status", "statuses"),
+    ("bus", "buses"),
+    ("virus", "viruses"),
+    ("cache", "caches"),
+    ("knife", "knives"),
+    ("life", "lives"),
+    ("wife", "wives"),
+];
+
+const UNCOUNTABLE: [&str; 12] = [
+    "data",
+    "deer",
+    "equipment",
+    "fish",
+    "information",
+    "metadata",
+    "moose",
+    "news",
+    "series",
+    "sheep",
+    "software",
+    "species",
+];
+
+const O_PLURAL_ES: [&str; 5] = ["echo", "hero", "potato", "tomato", "veto"];
+
+// Applies the casing of `original` (all caps or capitalized) to `word`.
+fn match_case(original: &str, word: &str) -> String {
+    let has_letters = original.chars().any(|c| c.is_alphabetic());
+    if has_letters && original.chars().all(|c| !c.is_lowercase()) {
+        return word.to_uppercase();
+    }
+    match original.chars().next() {
+        Some(first) if first.is_uppercase() => {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(word_first) => word_first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+        _ => word.to_string(),
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+// Splits off the last word so compound phrases ("load balancer") inflect
+// their final word only.
+fn split_last_word(phrase: &str) -> (&str, &str) {
+    match phrase.rfind(|c: char| !c.is_alphanumeric()) {
+        Some(index) => {
+            let split = index + phrase[index..].chars().next().map_or(1, |c| c.len_utf8());
+            (&phrase[..split], &phrase[split..])
+        }
+        None => ("", phrase),
+    }
+}
+
+fn pluralize_word(word: &str) -> String {
+    let lower = word.to_lowercase();
+    if lower.is_empty() || UNCOUNTABLE.contains(&lower.as_str()) {
+        return word.to_string();
+    }
+    if let Some((_, plural)) = IRREGULAR.iter().find(|(singular, _)| *singular == lower) {
+        return plural.to_string();
+    }
+    if IRREGULAR.iter().any(|(_, plural)| *plural == lower) {
+        return lower;
+    }
+
+    let before_last = lower.chars().rev().nth(1);
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|suffix| lower.ends_with(suffix))
+    {
+        if lower.ends_with("is") && lower.len() > 3 {
+            return format!("{}es", &lower[..lower.len() - 2]);
+        }
+        format!("{}es", lower)
+    } else if lower.ends_with('y') && before_last.map(|c| !is_vowel(c)).unwrap_or(false) {
+        format!("{}ies", &lower[..lower.len() - 1])
+    } else if lower.ends_with("lf") || lower.ends_with("eaf") {
+        format!("{}ves", &lower[..lower.len() - 1])
+    } else if O_PLURAL_ES.contains(&lower.as_str()) {
+        format!("{}es", lower)
+    } else {
+        format!("{}s", lower)
+    }
+}
+
+fn singularize_word(word: &str) -> String {
+    let lower = word.to_lowercase();
+    if lower.is_empty() || UNCOUNTABLE.contains(&lower.as_str()) {
+        return word.to_string();
+    }
+    if let Some((singular, _)) = IRREGULAR.iter().find(|(_, plural)| *plural == lower) {
+        return singular.to_string();
+    }
+    if IRREGULAR.iter().any(|(singular, _)| *singular == lower) {
+        return lower;
+    }
+
+    let strip = |suffix: &str| lower[..lower.len() - suffix.len()].to_string();
+    if lower.ends_with("ies") && lower.len() > 4 {
+        format!("{}y", strip("ies"))
+    } else if lower.ends_with("lves") || lower.ends_with("eaves") {
+        format!("{}f", strip("ves"))
+    } else if lower.ends_with("yses") {
+        format!("{}is", strip("es"))
+    } else if ["sses", "shes", "ches", "xes", "zes", "oes"]
+        .iter()
+        .any(|suffix| lower.ends_with(suffix))
+    {
+        strip("es")
+    } else if lower.ends_with('s') && !lower.ends_with("ss") && !lower.ends_with("us") {
+        strip("s")
+    } else {
+        lower
+    }
+}
+
+/// Returns the plural form of the last word of `phrase`, keeping its casing.
+pub fn pluralize(phrase: &str) -> String {
+    let (prefix, word) = split_last_word(phrase);
+    format!("{}{}", prefix, match_case(word, &pluralize_word(word)))
+}
+
+/// Returns the singular form of the last word of `phrase`, keeping its casing.
+pub fn singularize(phrase: &str) -> String {
+    let (prefix, word) = split_last_word(phrase);
+    format!("{}{}", prefix, match_case(word, &singularize_word(word)))
+}
+
+/// Returns the English ordinal of a number, e.g. `1st`, `12th` or `23rd`.
+pub fn ordinal(number: i64) -> String {
+    let suffix = match (number.abs() % 10, number.abs() % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
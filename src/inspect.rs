@@ -0,0 +1,448 @@
+// Positions of the variables, filters, functions and statements of a template,
+// for editor tooling highlighting them without parsing Tera itself. Tera's
+// AST doesn't keep positions, so the tags are scanned from the source once
+// Tera accepts the template.
+
+use crate::{
+    partial::{find_tag_end, tag_content},
+    tmptoml::{
+        parse_toml_to_config, read_file, resolve_group_paths, GroupPath, TeraRenderErr, TmpTomlErr,
+    },
+};
+use serde_json::{json, Value};
+use std::{collections::BTreeSet, fmt, path::Path, str::FromStr};
+use tera::Template;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Variable,
+    Filter,
+    Function,
+    /// The keyword of a `{% %}` statement, e.g. `for` or `endif`.
+    Statement,
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ItemKind::Variable => "variable",
+            ItemKind::Filter => "filter",
+            ItemKind::Function => "function",
+            ItemKind::Statement => "statement",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A name found in a template. Offsets are in bytes, lines and columns start
+/// at 1 and columns count characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateItem {
+    pub kind: ItemKind,
+    /// The dotted path of variables, e.g. `db.host`.
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    /// Whether a variable is bound by the template itself: a loop variable,
+    /// a `set` variable or a macro argument.
+    pub local: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for InspectFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(InspectFormat::Text),
+            "json" => Ok(InspectFormat::Json),
+            other => Err(format!("unsupported inspect format: {}", other)),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "or", "not", "in", "is", "true", "false", "True", "False",
+];
+
+fn is_identifier_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_'
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+// Scans the tags of a template, keeping the names the template binds in
+// nested scopes like `partial` does.
+struct Scanner<'a> {
+    source: &'a str,
+    scopes: Vec<BTreeSet<String>>,
+    items: Vec<TemplateItem>,
+    /// The spans of the string literals of expressions, quotes included.
+    strings: Vec<(usize, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn push(&mut self, kind: ItemKind, name: &str, start: usize, end: usize) {
+        let root = name.split('.').next().unwrap_or(name);
+        let local = kind == ItemKind::Variable && self.is_local(root);
+        self.items.push(TemplateItem {
+            kind,
+            name: name.to_string(),
+            start,
+            end,
+            line: 0,
+            column: 0,
+            local,
+        });
+    }
+
+    fn skip_whitespace(&self, mut position: usize, end: usize) -> usize {
+        let bytes = self.source.as_bytes();
+        while position < end && bytes[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        position
+    }
+
+    fn identifier_end(&self, mut position: usize, end: usize) -> usize {
+        let bytes = self.source.as_bytes();
+        while position < end && is_identifier_byte(bytes[position]) {
+            position += 1;
+        }
+        position
+    }
+
+    /// Records the names of the expression between `start` and `end`.
+    fn expression(&mut self, start: usize, end: usize) {
+        let bytes = self.source.as_bytes();
+        let mut position = start;
+        // The last significant character before the current one.
+        let mut previous = b' ';
+        let mut after_is = false;
+        while position < end {
+            let byte = bytes[position];
+            if matches!(byte, b'"' | b'\'' | b'`') {
+                let closing = self.source[position + 1..end]
+                    .find(byte as char)
+                    .map_or(end, |offset| position + 1 + offset + 1);
+                self.strings.push((position, closing));
+                position = closing;
+                previous = b'"';
+                continue;
+            }
+            if byte.is_ascii_digit() {
+                while position < end
+                    && (bytes[position].is_ascii_digit() || bytes[position] == b'.')
+                {
+                    position += 1;
+                }
+                previous = b'0';
+                continue;
+            }
+            if !is_identifier_start(byte) {
+                if !byte.is_ascii_whitespace() {
+                    previous = byte;
+                }
+                position += 1;
+                continue;
+            }
+
+            let name_start = position;
+            let mut name_end = self.identifier_end(position, end);
+            let word = &self.source[name_start..name_end];
+            // Attributes of a path are part of its name, tests follow `is`.
+            if previous == b'.' || after_is && word != "not" {
+                after_is = false;
+                previous = b'a';
+                position = name_end;
+                continue;
+            }
+            if KEYWORDS.contains(&word) {
+                after_is = word == "is" || after_is && word == "not";
+                previous = b'a';
+                position = name_end;
+                continue;
+            }
+            while name_end + 1 < end
+                && bytes[name_end] == b'.'
+                && is_identifier_start(bytes[name_end + 1])
+            {
+                name_end = self.identifier_end(name_end + 1, end);
+            }
+            let next = self.skip_whitespace(name_end, end);
+            let name = &self.source[name_start..name_end];
+            if previous == b'|' {
+                self.push(ItemKind::Filter, name, name_start, name_end);
+            } else if self.source[next..end].starts_with("::") {
+                // A macro namespace, the macro name following it.
+                position = next + 2;
+                previous = b':';
+                continue;
+            } else if bytes.get(next) == Some(&b'(') && next < end {
+                self.push(ItemKind::Function, name, name_start, name_end);
+            } else if bytes.get(next) == Some(&b'=')
+                && next < end
+                && bytes.get(next + 1) != Some(&b'=')
+            {
+                // A keyword argument's name.
+            } else if previous != b':' {
+                self.push(ItemKind::Variable, name, name_start, name_end);
+            }
+            previous = b'a';
+            position = name_end;
+        }
+    }
+
+    /// Records a `{% %}` statement whose content spans `start` to `end`,
+    /// returning where scanning continues.
+    fn statement(&mut self, start: usize, end: usize, tag_end: usize) -> Result<usize, TmpTomlErr> {
+        let keyword_end = self.identifier_end(start, end);
+        let keyword = &self.source[start..keyword_end];
+        self.push(ItemKind::Statement, keyword, start, keyword_end);
+        let rest = self.skip_whitespace(keyword_end, end);
+        match keyword {
+            "raw" => {
+                // Nothing is read up to the endraw tag.
+                let mut search = tag_end;
+                while let Some(offset) = self.source[search..].find("{%") {
+                    let raw_start = search + offset;
+                    let raw_end = find_tag_end(self.source, raw_start, "%}")
+                        .ok_or_else(|| invalid_template("unclosed raw block"))?;
+                    if tag_content(&self.source[raw_start..raw_end]) == "endraw" {
+                        return Ok(raw_start);
+                    }
+                    search = raw_end;
+                }
+                return Err(invalid_template("unclosed raw block").into());
+            }
+            "for" => {
+                let content = &self.source[rest..end];
+                let (names, container) = match content.find(" in ") {
+                    Some(offset) => (&content[..offset], rest + offset + " in ".len()),
+                    None => (content, end),
+                };
+                let mut scope: BTreeSet<String> = names
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .collect();
+                scope.insert("loop".to_string());
+                // The container is evaluated outside of the loop's scope.
+                self.expression(container, end);
+                self.scopes.push(scope);
+            }
+            "macro" => {
+                let name_end = self.identifier_end(rest, end);
+                self.push(
+                    ItemKind::Function,
+                    &self.source[rest..name_end],
+                    rest,
+                    name_end,
+                );
+                let args = self.source[name_end..end]
+                    .split_once('(')
+                    .and_then(|(_, args)| args.rsplit_once(')'))
+                    .map(|(args, _)| args)
+                    .unwrap_or_default();
+                self.scopes.push(
+                    args.split(',')
+                        .map(|arg| arg.split('=').next().unwrap_or_default().trim().to_string())
+                        .filter(|arg| !arg.is_empty())
+                        .collect(),
+                );
+            }
+            "endfor" | "endmacro" if self.scopes.len() > 1 => {
+                self.scopes.pop();
+            }
+            "set" | "set_global" => {
+                let name_end = self.identifier_end(rest, end);
+                let name = self.source[rest..name_end].to_string();
+                if let Some(offset) = self.source[name_end..end].find('=') {
+                    self.expression(name_end + offset + 1, end);
+                }
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name);
+                }
+            }
+            "import" => {
+                if let Some(offset) = self.source[rest..end].rfind(" as ") {
+                    let namespace = self.source[rest + offset + " as ".len()..end].trim();
+                    self.scopes[0].insert(namespace.to_string());
+                }
+            }
+            "filter" => {
+                let name_end = self.identifier_end(rest, end);
+                self.push(
+                    ItemKind::Filter,
+                    &self.source[rest..name_end],
+                    rest,
+                    name_end,
+                );
+                self.expression(name_end, end);
+            }
+            "if" | "elif" => self.expression(rest, end),
+            _ => {}
+        }
+        Ok(tag_end)
+    }
+}
+
+fn invalid_template(err: impl fmt::Display) -> TeraRenderErr {
+    TeraRenderErr::InvalidTemplate(format!("Failed to parse template file with error: {}", err))
+}
+
+// The content of a tag without its delimiters, whitespace control and
+// surrounding whitespace.
+fn content_span(source: &str, start: usize, end: usize) -> (usize, usize) {
+    let bytes = source.as_bytes();
+    let (mut start, mut end) = (start + 2, end - 2);
+    while start < end && (bytes[start] == b'-' || bytes[start].is_ascii_whitespace()) {
+        start += 1;
+    }
+    while end > start && (bytes[end - 1] == b'-' || bytes[end - 1].is_ascii_whitespace()) {
+        end -= 1;
+    }
+    (start, end)
+}
+
+fn scan(source: &str) -> Result<Scanner<'_>, TmpTomlErr> {
+    Template::new("template", None, source).map_err(invalid_template)?;
+    let mut scanner = Scanner {
+        source,
+        scopes: vec![BTreeSet::new()],
+        items: Vec::new(),
+        strings: Vec::new(),
+    };
+    let mut position = 0;
+    while let Some(offset) = source[position..].find('{') {
+        let start = position + offset;
+        let closing = match source.as_bytes().get(start + 1) {
+            Some(b'{') => "}}",
+            Some(b'%') => "%}",
+            Some(b'#') => "#}",
+            _ => {
+                position = start + 1;
+                continue;
+            }
+        };
+        let end = find_tag_end(source, start, closing)
+            .ok_or_else(|| invalid_template(format!("unclosed tag at byte {}", start)))?;
+        let (content_start, content_end) = content_span(source, start, end);
+        position = match closing {
+            "}}" => {
+                scanner.expression(content_start, content_end);
+                end
+            }
+            "%}" => scanner.statement(content_start, content_end, end)?,
+            _ => end,
+        };
+    }
+    Ok(scanner)
+}
+
+/// The spans of the string literals in the tags of a template, quotes
+/// included, in the order they appear.
+pub(crate) fn string_literals(source: &str) -> Result<Vec<(usize, usize)>, TmpTomlErr> {
+    Ok(scan(source)?.strings)
+}
+
+/// Lists the variables, filters, functions and statements of a template in
+/// the order they appear. Variables the template binds itself are marked as
+/// local.
+pub fn inspect_template(source: &str) -> Result<Vec<TemplateItem>, TmpTomlErr> {
+    let mut items = scan(source)?.items;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut scanned = 0;
+    for item in &mut items {
+        for (offset, c) in source[scanned..item.start].char_indices() {
+            if c == '\n' {
+                line += 1;
+                line_start = scanned + offset + 1;
+            }
+        }
+        scanned = item.start;
+        item.line = line;
+        item.column = source[line_start..item.start].chars().count() + 1;
+    }
+    Ok(items)
+}
+
+fn item_json(item: &TemplateItem, defined: Option<bool>) -> Value {
+    let mut value = json!({
+        "kind": item.kind.to_string(),
+        "name": item.name,
+        "start": item.start,
+        "end": item.end,
+        "line": item.line,
+        "column": item.column,
+    });
+    if item.kind == ItemKind::Variable {
+        value["local"] = Value::Bool(item.local);
+        if let Some(defined) = defined {
+            value["defined"] = Value::Bool(defined);
+        }
+    }
+    value
+}
+
+/// Describes the items of a template file. Given a config and group paths,
+/// variables are also marked as defined or not by the selected groups.
+pub fn inspect_file(
+    template_file_path: &Path,
+    config: Option<(&Path, &[GroupPath])>,
+    format: InspectFormat,
+) -> Result<String, TmpTomlErr> {
+    let source = read_file(template_file_path.to_str()).map_err(TeraRenderErr::from)?;
+    let items = inspect_template(&source)?;
+    let defined_keys = match config {
+        Some((config_file_path, group_paths)) => {
+            let toml_config = parse_toml_to_config(config_file_path.to_str())?;
+            let group_values = resolve_group_paths(&toml_config, group_paths)?;
+            Some(group_values.into_keys().collect::<BTreeSet<String>>())
+        }
+        None => None,
+    };
+    let defined = |item: &TemplateItem| {
+        defined_keys.as_ref().map(|keys| {
+            item.local || keys.contains(item.name.split('.').next().unwrap_or_default())
+        })
+    };
+    Ok(match format {
+        InspectFormat::Json => serde_json::to_string_pretty(&json!({
+            "template": template_file_path.display().to_string(),
+            "items": items
+                .iter()
+                .map(|item| item_json(item, defined(item)))
+                .collect::<Vec<Value>>(),
+        }))
+        .unwrap_or_default(),
+        InspectFormat::Text => items
+            .iter()
+            .map(|item| {
+                let note = match (item.kind, item.local, defined(item)) {
+                    (ItemKind::Variable, true, _) => " (local)",
+                    (ItemKind::Variable, false, Some(false)) => " (undefined)",
+                    _ => "",
+                };
+                format!(
+                    "{}:{}: {} {}{}",
+                    item.line, item.column, item.kind, item.name, note
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+    })
+}
@@ -1,4 +1,110 @@
+mod analysis;
+mod bake;
+mod batch;
+mod bench;
+mod bundle;
+mod coercion;
+mod complete;
+mod compression;
+mod config_template;
+mod context;
+mod convert;
+mod defaults;
+mod entries;
+mod eval;
+mod exclude;
+mod export;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod filters;
+mod flatten;
+mod functions;
+mod import;
+mod inflect;
+mod inspect;
+mod lint;
+mod locale;
+mod lookup;
+mod manifest;
+mod network;
+mod partial;
+mod passthrough;
+mod plan;
+mod precedence;
+mod provider;
+mod prune;
+#[cfg(feature = "python")]
+mod python;
+mod rc;
+mod rename;
+mod renderer;
+mod selective;
+mod semver;
+mod sensitive;
+mod session;
+mod stats;
+mod suggest;
+mod tags;
+mod timings;
 mod tmptoml;
+mod transaction;
+mod warnings;
+#[cfg(feature = "wasm")]
+mod wasm;
+pub use bake::{bake, read_baked, render_baked, BakeErr, Baked};
+pub use batch::{render_batch, Batch, BatchErr, BatchFailure, BatchOptions, BatchOutput};
+pub use bench::{bench_template, percentile, Bench};
+pub use bundle::{render_bundled_template, Bundle, BundleErr};
+pub use coercion::Coercion;
+pub use complete::{complete_group_ids, complete_group_path};
+pub use compression::{base64_encode, encode_output, Compression, OutputEncoding};
+pub use config_template::{config_template, group_template, TEMPLATE_KEY};
+pub use context::{
+    apply_override, merge_context_values, merge_context_values_with_precedence, parse_context,
+    parse_context_json, read_stdin_context, run_context_command, toml_to_json, ContextErr,
+    ContextFormat, ContextLimits, ContextValues,
+};
+pub use convert::{
+    convert_envsubst, convert_template, convert_template_file, Conversion, ConvertFrom,
+};
+pub use defaults::{
+    default_group_path, default_group_path_file, DEFAULTS_TABLE, GROUP_ENV_VAR, SECONDARY_ENV_VAR,
+};
+pub use eval::{eval_expression, expression_template};
+pub use exclude::TemplateFilter;
+pub use export::{export_group, export_group_with_coercions, ExportErr, ExportFormat, Exported};
+pub use flatten::flatten_config_file;
+pub use import::{import_file, import_helm_values, ImportErr, ImportFrom};
+pub use inspect::{inspect_file, inspect_template, InspectFormat, ItemKind, TemplateItem};
+pub use lint::{lint_config, lint_config_file, LintFinding, LintKind};
+pub use locale::{find_locale, Locale, LOCALES};
+pub use manifest::{
+    clean_manifest, outdated_outputs, Cleaned, FileHash, Manifest, ManifestEntry, ManifestErr,
+    Outdated,
+};
+pub use partial::Deferral;
+pub use passthrough::PassthroughMarkers;
+pub use plan::{plan_batch, unified_diff, Plan, PlannedAction, PlannedFile, UndefinedVariable};
+pub use precedence::Precedence;
+pub use provider::{ProviderErr, ValueProvider, ValueProviders};
+pub use prune::prune_output_dir;
+pub use rc::{find_rc_file, rc_arguments, RcErr, RC_FILE_NAMES};
+pub use rename::{
+    rename_config_source, rename_key, rename_template_source, Rename, RenameErr, RenamedFile,
+};
+pub use renderer::{LoadedConfig, Renderer};
+pub use selective::parse_toml_groups;
+pub use sensitive::{sensitive_keys, SENSITIVE_TABLE};
+pub use session::{Session, SessionErr, REDACTED};
+pub use stats::{config_stats, stats_file, template_stats};
+pub use suggest::UnknownName;
+pub use tags::{select_tagged_file, tagged_group_paths, TAGS_KEY};
+pub use timings::{format_timing, Timings};
 pub use tmptoml::{
-    parse_toml_to_config, render_template, Config, Group, ReadFileErr, TeraRenderErr, TmpTomlErr,
+    parse_group_path, parse_toml_str, parse_toml_to_config, render_str, render_template,
+    render_template_path, render_template_paths, render_template_with_options,
+    render_template_with_warnings, resolve_group, resolve_group_path, resolve_group_paths,
+    resolve_group_paths_with_precedence, secondary_group_ids, skipped_tables, Config, Group,
+    GroupPath, ReadFileErr, RenderOptions, Rendered, TeraRenderErr, TmpTomlErr,
 };
+pub use warnings::{DeprecatedKey, Notice, Warning, WarningKind};
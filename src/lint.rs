@@ -0,0 +1,263 @@
+// Structural checks of a config file, flagging the kind of mistakes that
+// creep in as configs grow: keys differing only by case, leftover sections,
+// copy-pasted sections and secrets committed in plain text.
+
+use crate::tmptoml::{parse_toml_to_config, read_file, Config, TmpTomlErr};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+const SECRET_KEY_WORDS: [&str; 8] = [
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "private_key",
+    "credential",
+];
+
+// Values starting with these point at a secret store or the environment
+// rather than containing the secret itself.
+const SECRET_REFERENCE_PREFIXES: [&str; 8] = [
+    "${", "{{", "vault:", "enc:", "ENC[", "arn:", "env:", "op://",
+];
+
+// Keys naming where a secret lives rather than holding it.
+const SECRET_KEY_SUFFIXES_IGNORED: [&str; 5] = ["_name", "_path", "_file", "_ref", "_env"];
+
+const SECRET_VALUE_PREFIXES: [&str; 4] = ["ghp_", "github_pat_", "xoxb-", "xoxp-"];
+
+// Sibling sections with at least this many keys sharing at least this share
+// of their values are reported as likely copy-paste.
+const SIMILAR_MIN_KEYS: usize = 3;
+const SIMILAR_MIN_RATIO: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintKind {
+    CaseDuplicate,
+    EmptySection,
+    UnreferencedSection,
+    SimilarSections,
+    PlaintextSecret,
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LintKind::CaseDuplicate => "case-duplicate",
+            LintKind::EmptySection => "empty-section",
+            LintKind::UnreferencedSection => "unreferenced-section",
+            LintKind::SimilarSections => "similar-sections",
+            LintKind::PlaintextSecret => "plaintext-secret",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintFinding {
+    pub path: String,
+    pub kind: LintKind,
+    pub message: String,
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+pub(crate) fn is_secret_key(key: &str) -> bool {
+    let lower = key.to_lowercase().replace('-', "_");
+    SECRET_KEY_WORDS.iter().any(|word| lower.contains(word))
+        && !SECRET_KEY_SUFFIXES_IGNORED
+            .iter()
+            .any(|suffix| lower.ends_with(suffix))
+}
+
+fn looks_like_aws_access_key(value: &str) -> bool {
+    value.len() == 20
+        && value.starts_with("AKIA")
+        && value
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+fn is_plaintext_secret(key: &str, value: &str) -> bool {
+    if value.starts_with("-----BEGIN") && value.contains("PRIVATE KEY") {
+        return true;
+    }
+    if looks_like_aws_access_key(value)
+        || SECRET_VALUE_PREFIXES
+            .iter()
+            .any(|prefix| value.starts_with(prefix))
+    {
+        return true;
+    }
+    is_secret_key(key)
+        && !value.trim().is_empty()
+        && !SECRET_REFERENCE_PREFIXES
+            .iter()
+            .any(|prefix| value.starts_with(prefix))
+}
+
+fn check_case_duplicates<'a>(
+    path: &str,
+    keys: impl Iterator<Item = &'a String>,
+    findings: &mut Vec<LintFinding>,
+) {
+    let mut by_lowercase: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for key in keys {
+        by_lowercase
+            .entry(key.to_lowercase())
+            .or_default()
+            .push(key);
+    }
+    for mut keys in by_lowercase.into_values().filter(|keys| keys.len() > 1) {
+        keys.sort();
+        let names: Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+        findings.push(LintFinding {
+            path: join_path(path, keys[0]),
+            kind: LintKind::CaseDuplicate,
+            message: format!("keys differ only by case: {}", names.join(", ")),
+        });
+    }
+}
+
+fn similarity(first: &toml::value::Table, second: &toml::value::Table) -> f64 {
+    let equal = first
+        .iter()
+        .filter(|(key, value)| second.get(*key) == Some(value))
+        .count();
+    let union = first.len()
+        + second
+            .keys()
+            .filter(|key| !first.contains_key(*key))
+            .count();
+    equal as f64 / union as f64
+}
+
+fn check_similar_sections(
+    path: &str,
+    tables: &[(&String, &toml::value::Table)],
+    findings: &mut Vec<LintFinding>,
+) {
+    for (index, (first_key, first)) in tables.iter().enumerate() {
+        for (second_key, second) in &tables[index + 1..] {
+            if first.len() < SIMILAR_MIN_KEYS || second.len() < SIMILAR_MIN_KEYS {
+                continue;
+            }
+            let ratio = similarity(first, second);
+            if ratio >= SIMILAR_MIN_RATIO {
+                findings.push(LintFinding {
+                    path: join_path(path, first_key),
+                    kind: LintKind::SimilarSections,
+                    message: format!(
+                        "{:.0}% of its values are identical to {}, likely copy-paste",
+                        ratio * 100.0,
+                        join_path(path, second_key)
+                    ),
+                });
+            }
+        }
+    }
+}
+
+fn lint_table(path: &str, table: &toml::value::Table, findings: &mut Vec<LintFinding>) {
+    check_case_duplicates(path, table.keys(), findings);
+
+    let mut tables: Vec<(&String, &toml::value::Table)> = Vec::new();
+    for (key, value) in table {
+        let value_path = join_path(path, key);
+        match value {
+            toml::Value::Table(nested) => {
+                if nested.is_empty() {
+                    findings.push(LintFinding {
+                        path: value_path.clone(),
+                        kind: LintKind::EmptySection,
+                        message: "section has no values".to_string(),
+                    });
+                }
+                tables.push((key, nested));
+                lint_table(&value_path, nested, findings);
+            }
+            toml::Value::String(string) if is_plaintext_secret(key, string) => {
+                findings.push(LintFinding {
+                    path: value_path,
+                    kind: LintKind::PlaintextSecret,
+                    message: "value looks like an unencrypted secret".to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+    tables.sort_by_key(|(key, _)| *key);
+    check_similar_sections(path, &tables, findings);
+}
+
+fn is_referenced(group_id: &str, references: &[String]) -> bool {
+    references.iter().any(|reference| {
+        reference
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .any(|word| word == group_id)
+    })
+}
+
+/// Lints the config, `references` being the content of the manifests and
+/// profiles selecting its groups. Groups are only checked for references
+/// when `references` isn't empty.
+pub fn lint_config(toml_config: &Config, references: &[String]) -> Vec<LintFinding> {
+    let table: toml::value::Table = toml_config
+        .iter()
+        .map(|(group_id, group)| {
+            let group_table = group
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect();
+            (group_id.to_string(), toml::Value::Table(group_table))
+        })
+        .collect();
+    let mut findings = Vec::new();
+    lint_table("", &table, &mut findings);
+
+    if !references.is_empty() {
+        for group_id in table.keys() {
+            if !is_referenced(group_id, references) {
+                findings.push(LintFinding {
+                    path: group_id.to_string(),
+                    kind: LintKind::UnreferencedSection,
+                    message: "section isn't referenced by any manifest or profile".to_string(),
+                });
+            }
+        }
+    }
+    findings.sort();
+    findings
+}
+
+/// Lints the config file and lists the findings, one per line.
+pub fn lint_config_file(
+    config_file_path: &Path,
+    reference_file_paths: &[PathBuf],
+) -> Result<String, TmpTomlErr> {
+    let toml_config = parse_toml_to_config(config_file_path.to_str())?;
+    let references = reference_file_paths
+        .iter()
+        .map(|reference_file_path| read_file(reference_file_path.to_str()))
+        .collect::<Result<Vec<String>, _>>()?;
+    let findings = lint_config(&toml_config, &references);
+    if findings.is_empty() {
+        return Ok("No issues found".to_string());
+    }
+    Ok(findings
+        .iter()
+        .map(|finding| format!("{}: {} [{}]", finding.path, finding.message, finding.kind))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
@@ -0,0 +1,312 @@
+// Locale data used by the opt-in formatting filters. Only the handful of
+// conventions the filters need is kept here: number separators, month and
+// weekday names and a default date format.
+
+#[derive(Debug)]
+pub struct Locale {
+    pub code: &'static str,
+    pub decimal_separator: &'static str,
+    pub group_separator: &'static str,
+    pub date_format: &'static str,
+    pub months: [&'static str; 12],
+    pub months_abbr: [&'static str; 12],
+    /// Weekday names, starting on Monday.
+    pub weekdays: [&'static str; 7],
+    pub weekdays_abbr: [&'static str; 7],
+}
+
+const EN_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const EN_MONTHS_ABBR: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const EN_WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const EN_WEEKDAYS_ABBR: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+pub const DEFAULT_LOCALE: &Locale = &LOCALES[0];
+
+pub const LOCALES: [Locale; 9] = [
+    Locale {
+        code: "en-US",
+        decimal_separator: ".",
+        group_separator: ",",
+        date_format: "%B %-d, %Y",
+        months: EN_MONTHS,
+        months_abbr: EN_MONTHS_ABBR,
+        weekdays: EN_WEEKDAYS,
+        weekdays_abbr: EN_WEEKDAYS_ABBR,
+    },
+    Locale {
+        code: "en-GB",
+        decimal_separator: ".",
+        group_separator: ",",
+        date_format: "%-d %B %Y",
+        months: EN_MONTHS,
+        months_abbr: EN_MONTHS_ABBR,
+        weekdays: EN_WEEKDAYS,
+        weekdays_abbr: EN_WEEKDAYS_ABBR,
+    },
+    Locale {
+        code: "de-DE",
+        decimal_separator: ",",
+        group_separator: ".",
+        date_format: "%-d. %B %Y",
+        months: [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+        months_abbr: [
+            "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sept.", "Okt.", "Nov.",
+            "Dez.",
+        ],
+        weekdays: [
+            "Montag",
+            "Dienstag",
+            "Mittwoch",
+            "Donnerstag",
+            "Freitag",
+            "Samstag",
+            "Sonntag",
+        ],
+        weekdays_abbr: ["Mo.", "Di.", "Mi.", "Do.", "Fr.", "Sa.", "So."],
+    },
+    Locale {
+        code: "fr-FR",
+        decimal_separator: ",",
+        group_separator: "\u{202f}",
+        date_format: "%-d %B %Y",
+        months: [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+        months_abbr: [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+        weekdays: [
+            "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+        ],
+        weekdays_abbr: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+    },
+    Locale {
+        code: "es-ES",
+        decimal_separator: ",",
+        group_separator: ".",
+        date_format: "%-d de %B de %Y",
+        months: [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+        months_abbr: [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+        ],
+        weekdays: [
+            "lunes",
+            "martes",
+            "miércoles",
+            "jueves",
+            "viernes",
+            "sábado",
+            "domingo",
+        ],
+        weekdays_abbr: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+    },
+    Locale {
+        code: "it-IT",
+        decimal_separator: ",",
+        group_separator: ".",
+        date_format: "%-d %B %Y",
+        months: [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+        months_abbr: [
+            "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+        ],
+        weekdays: [
+            "lunedì",
+            "martedì",
+            "mercoledì",
+            "giovedì",
+            "venerdì",
+            "sabato",
+            "domenica",
+        ],
+        weekdays_abbr: ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+    },
+    Locale {
+        code: "nl-NL",
+        decimal_separator: ",",
+        group_separator: ".",
+        date_format: "%-d %B %Y",
+        months: [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_abbr: [
+            "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+        ],
+        weekdays: [
+            "maandag",
+            "dinsdag",
+            "woensdag",
+            "donderdag",
+            "vrijdag",
+            "zaterdag",
+            "zondag",
+        ],
+        weekdays_abbr: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+    },
+    Locale {
+        code: "pt-BR",
+        decimal_separator: ",",
+        group_separator: ".",
+        date_format: "%-d de %B de %Y",
+        months: [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+        months_abbr: [
+            "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+        ],
+        weekdays: [
+            "segunda-feira",
+            "terça-feira",
+            "quarta-feira",
+            "quinta-feira",
+            "sexta-feira",
+            "sábado",
+            "domingo",
+        ],
+        weekdays_abbr: ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+    },
+    Locale {
+        code: "sv-SE",
+        decimal_separator: ",",
+        group_separator: "\u{a0}",
+        date_format: "%-d %B %Y",
+        months: [
+            "januari",
+            "februari",
+            "mars",
+            "april",
+            "maj",
+            "juni",
+            "juli",
+            "augusti",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_abbr: [
+            "jan.", "feb.", "mars", "apr.", "maj", "juni", "juli", "aug.", "sep.", "okt.", "nov.",
+            "dec.",
+        ],
+        weekdays: [
+            "måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag", "söndag",
+        ],
+        weekdays_abbr: ["mån", "tis", "ons", "tors", "fre", "lör", "sön"],
+    },
+];
+
+/// Looks up a locale by its code, e.g. `de-DE` or `de_DE`. A code only
+/// naming a language (`de`) or an unknown region of a known language
+/// (`de-AT`) falls back to the first locale of that language.
+pub fn find_locale(code: &str) -> Option<&'static Locale> {
+    let code = code.replace('_', "-");
+    LOCALES
+        .iter()
+        .find(|locale| locale.code.eq_ignore_ascii_case(&code))
+        .or_else(|| {
+            let language = code.split('-').next().unwrap_or_default();
+            LOCALES.iter().find(|locale| {
+                locale
+                    .code
+                    .split('-')
+                    .next()
+                    .map(|candidate| candidate.eq_ignore_ascii_case(language))
+                    .unwrap_or(false)
+            })
+        })
+}
@@ -0,0 +1,82 @@
+// The `config` template function, reading any value of the whole config for
+// the rare templates comparing environments, e.g. warning when production
+// and staging point at the same database. Templates only ever see their own
+// groups unless lookups are allowed.
+//
+// Tera functions are registered when the templates are compiled, while the
+// config is only known when rendering, so every render makes its config
+// available to the function of its own thread for the duration of the
+// render.
+
+use crate::{context::toml_to_json, tmptoml::Config};
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
+use tera::{Tera, Value};
+
+thread_local! {
+    static LOOKUP_CONFIG: RefCell<Option<Arc<Config>>> = const { RefCell::new(None) };
+}
+
+/// Runs `render` with `config` readable by the `config` function, when
+/// lookups are allowed.
+pub(crate) fn with_lookup_config<T>(config: Option<Arc<Config>>, render: impl FnOnce() -> T) -> T {
+    let previous = LOOKUP_CONFIG.with(|lookup| lookup.replace(config));
+    let rendered = render();
+    LOOKUP_CONFIG.with(|lookup| lookup.replace(previous));
+    rendered
+}
+
+pub(crate) fn register_lookup_function(tera: &mut Tera, allowed: bool) {
+    tera.register_function("config", move |args: &HashMap<String, Value>| {
+        config(allowed, args)
+    });
+}
+
+/// `config(path="prod.system1.host")` returns the value at a dotted path of
+/// the whole config, starting from a top-level group.
+fn config(allowed: bool, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let path = match args.get("path") {
+        Some(Value::String(path)) => path,
+        Some(other) => {
+            return Err(tera::Error::msg(format!(
+                "Function `config` expected the `path` argument to be a string but got {}",
+                other
+            )))
+        }
+        None => {
+            return Err(tera::Error::msg(
+                "Function `config` requires the `path` argument",
+            ))
+        }
+    };
+    let refused = || {
+        tera::Error::msg("Function `config` can only read the config with --allow-config-lookup")
+    };
+    if !allowed {
+        return Err(refused());
+    }
+    let config = LOOKUP_CONFIG
+        .with(|lookup| lookup.borrow().clone())
+        .ok_or_else(refused)?;
+    let not_found = || {
+        tera::Error::msg(format!(
+            "Function `config` found no value at {} in the config",
+            path
+        ))
+    };
+    let mut keys = path.split('.');
+    let group = keys
+        .next()
+        .and_then(|group_id| config.get(group_id))
+        .ok_or_else(not_found)?;
+    let mut value = match keys.next() {
+        Some(key) => group.get(key).ok_or_else(not_found)?,
+        None => {
+            let group: toml::value::Table = group.clone().into_iter().collect();
+            return Ok(toml_to_json(&toml::Value::Table(group)));
+        }
+    };
+    for key in keys {
+        value = value.get(key).ok_or_else(not_found)?;
+    }
+    Ok(toml_to_json(value))
+}
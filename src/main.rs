@@ -10,21 +10,823 @@
 // // So any code that fits the above responsibilities should live within this
 // // module.
 
-use std::path::PathBuf;
-use structopt::StructOpt;
-use tmptoml;
+use std::{collections::BTreeSet, ffi::OsString, path::PathBuf};
+use structopt::{clap, StructOpt};
 
 #[derive(StructOpt, Debug)]
-#[structopt(name = "tmptoml", about = "Parse toml files for templated solutions")]
+#[structopt(
+    name = "tmptoml",
+    about = "Parse toml files for templated solutions",
+    global_settings = &[clap::AppSettings::AllArgsOverrideSelf]
+)]
 pub struct ApplicationArguments {
+    #[structopt(subcommand)]
+    pub command: Command,
+}
+
+#[derive(StructOpt, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Command {
+    #[structopt(
+        name = "render",
+        about = "Render a template using a group of the config file (default)"
+    )]
+    Render(RenderArguments),
+    #[structopt(
+        name = "render-config",
+        about = "Render the template the selected group names with its _template key"
+    )]
+    RenderConfig(RenderConfigArguments),
+    #[structopt(
+        name = "eval",
+        about = "Print the value of a Tera expression evaluated against a group of the config file"
+    )]
+    Eval(EvalArguments),
+    #[structopt(
+        name = "batch",
+        about = "Render every template of a directory for several groups into an output directory"
+    )]
+    Batch(BatchArguments),
+    #[structopt(
+        name = "plan",
+        about = "Check and diff what batch would write with the same arguments, writing nothing"
+    )]
+    Plan(BatchArguments),
+    #[structopt(
+        name = "clean",
+        about = "Remove the generated files a batch manifest lists as no longer generated"
+    )]
+    Clean(ManifestArguments),
+    #[structopt(
+        name = "bundle",
+        about = "Bundle the templates of a directory into a single file render --bundle loads"
+    )]
+    Bundle(BundleArguments),
+    #[structopt(
+        name = "bake",
+        about = "Write an executable that renders a config's templates with no other files around"
+    )]
+    Bake(BakeArguments),
+    #[structopt(
+        name = "outdated",
+        about = "List the generated files of a batch manifest whose config or templates changed"
+    )]
+    Outdated(ManifestArguments),
+    #[structopt(
+        name = "convert",
+        about = "Convert a template from another template syntax into a Tera template"
+    )]
+    Convert(ConvertArguments),
+    #[structopt(
+        name = "import",
+        about = "Import values from another tool as a tmptoml config group"
+    )]
+    Import(ImportArguments),
+    #[structopt(
+        name = "export-group",
+        about = "Export the merged values of a group as a flat document"
+    )]
+    ExportGroup(ExportGroupArguments),
+    #[structopt(
+        name = "flatten",
+        about = "Print the effective values and their types for every group"
+    )]
+    Flatten(FlattenArguments),
+    #[structopt(
+        name = "lint-config",
+        about = "Flag structural smells and plain text secrets in the config file"
+    )]
+    LintConfig(LintConfigArguments),
+    #[structopt(
+        name = "inspect",
+        about = "List the variables, filters and statements of a template with their positions"
+    )]
+    Inspect(InspectArguments),
+    #[structopt(
+        name = "replay",
+        about = "Render a session recorded with render --record, checking it renders the recorded output"
+    )]
+    Replay(ReplayArguments),
+    #[structopt(
+        name = "bench",
+        about = "Compile and render a template repeatedly, reporting the timing percentiles"
+    )]
+    Bench(BenchArguments),
+    #[structopt(
+        name = "rename-key",
+        about = "Rename a key in the config and every template reading it"
+    )]
+    RenameKey(RenameKeyArguments),
+    #[structopt(
+        name = "stats",
+        about = "Print counts of the groups, keys and value types of the config and its templates"
+    )]
+    Stats(StatsArguments),
+    #[structopt(
+        name = "completions",
+        about = "Print the completion script of a shell, completing group IDs for bash"
+    )]
+    Completions(CompletionsArguments),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct RenderArguments {
     #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
     pub config: PathBuf,
+    #[structopt(
+        name = "template",
+        about = "Path to the template file, or its name with --bundle",
+        parse(from_os_str)
+    )]
+    pub template: PathBuf,
+    #[structopt(flatten)]
+    pub render: RenderOptionArguments,
+}
+
+// The expression is the last positional argument, after the optional group
+// IDs, so it is taken from whichever of them was given last.
+#[derive(StructOpt, Debug)]
+pub struct EvalArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(flatten)]
+    pub render: RenderOptionArguments,
+    #[structopt(
+        name = "expression",
+        about = "Tera expression to evaluate, e.g. '{{ port + 100 }}' or 'port + 100'"
+    )]
+    pub expression: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct BenchArguments {
     #[structopt(
         name = "template",
         about = "Path to the template file",
         parse(from_os_str)
     )]
     pub template: PathBuf,
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(
+        long = "iterations",
+        about = "Number of times the template is compiled and rendered",
+        default_value = "100"
+    )]
+    pub iterations: std::num::NonZeroUsize,
+    #[structopt(flatten)]
+    pub render: RenderOptionArguments,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct RenameKeyArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(
+        name = "template_dir",
+        about = "Directory of the templates reading the key",
+        parse(from_os_str)
+    )]
+    pub template_dir: PathBuf,
+    #[structopt(name = "old_key", about = "Key to rename")]
+    pub old_key: String,
+    #[structopt(name = "new_key", about = "New name of the key")]
+    pub new_key: String,
+    #[structopt(
+        long = "dry-run",
+        about = "Print the diff of the rename without writing any file"
+    )]
+    pub dry_run: bool,
+    #[structopt(
+        long = "exclude",
+        about = "Glob of template files to leave out, relative to the template directory",
+        number_of_values = 1
+    )]
+    pub exclude: Vec<String>,
+    #[structopt(
+        long = "gitignore",
+        about = "Leave out the template files .gitignore files ignore"
+    )]
+    pub gitignore: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct RenderConfigArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(flatten)]
+    pub render: RenderOptionArguments,
+}
+
+// The group selection and flags `render` and `render-config` share.
+#[derive(StructOpt, Debug)]
+pub struct RenderOptionArguments {
+    #[structopt(
+        name = "group_id",
+        about = "ID of the toml group to use, defaulting to $TMPTOML_GROUP or the config's [_defaults]"
+    )]
+    pub group_id: Option<String>,
+    #[structopt(
+        name = "secondary_group_id",
+        about = "ID of the toml secondary group to use, defaulting to $TMPTOML_SECONDARY or the config's [_defaults]"
+    )]
+    pub secondary_group_id: Option<String>,
+    #[structopt(
+        short = "g",
+        long = "group",
+        name = "group",
+        about = "ID of a toml group to descend into, repeat to select nested groups",
+        number_of_values = 1,
+        conflicts_with_all = &["group_id", "secondary_group_id"]
+    )]
+    pub groups: Vec<String>,
+    #[structopt(
+        short = "s",
+        long = "select",
+        name = "select",
+        about = "Dotted path of an additional group to merge in, e.g. shared.observability. Later selections take precedence",
+        number_of_values = 1
+    )]
+    pub selections: Vec<String>,
+    #[structopt(
+        long = "context-json",
+        about = "JSON object merged into the context with the highest precedence",
+        number_of_values = 1
+    )]
+    pub context_json: Vec<String>,
+    #[structopt(
+        long = "stdin-context",
+        about = "Merge a document piped to stdin into the context",
+        possible_values = &["json", "toml"]
+    )]
+    pub stdin_context: Option<tmptoml::ContextFormat>,
+    #[structopt(
+        long = "context-cmd",
+        about = "Merge the JSON or TOML a shell command prints into the context",
+        number_of_values = 1
+    )]
+    pub context_cmd: Vec<String>,
+    #[structopt(
+        long = "set",
+        about = "Override a value with key=value, the value being parsed as TOML",
+        number_of_values = 1
+    )]
+    pub set: Vec<String>,
+    #[structopt(
+        long = "set-string",
+        about = "Override a value with key=value, the value always being a string",
+        number_of_values = 1
+    )]
+    pub set_string: Vec<String>,
+    #[structopt(
+        long = "precedence",
+        about = "Whether earlier or later group levels, selections and context values win when merged",
+        default_value = "last-wins",
+        possible_values = &["first-wins", "last-wins"]
+    )]
+    pub precedence: tmptoml::Precedence,
+    #[structopt(
+        long = "include-siblings",
+        about = "Expose the tables next to the selected groups under their own keys instead of leaving them out"
+    )]
+    pub include_siblings: bool,
+    #[structopt(
+        long = "explain",
+        about = "List every table the selected groups leave out of the context on STDERR"
+    )]
+    pub explain: bool,
+    #[structopt(
+        long = "macros",
+        about = "Directory of *.tera macro files templates can import",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub macros: Vec<PathBuf>,
+    #[structopt(
+        long = "restrict-root",
+        about = "Directory the file() and glob() template functions can read below",
+        parse(from_os_str)
+    )]
+    pub restrict_root: Option<PathBuf>,
+    #[structopt(
+        long = "reproducible",
+        about = "Pin now() to the source date and refuse get_env() and get_random(), for byte-identical output"
+    )]
+    pub reproducible: bool,
+    #[structopt(
+        long = "source-date-epoch",
+        about = "Seconds since the Unix epoch now() returns with --reproducible",
+        env = "SOURCE_DATE_EPOCH"
+    )]
+    pub source_date_epoch: Option<i64>,
+    #[structopt(
+        long = "allow-config-lookup",
+        about = "Let templates read any value of the config with the config() function"
+    )]
+    pub allow_config_lookup: bool,
+    #[structopt(
+        long = "max-context-keys",
+        about = "Fail when the context has more keys, those of nested tables included"
+    )]
+    pub max_context_keys: Option<usize>,
+    #[structopt(
+        long = "max-context-depth",
+        about = "Fail when the context is nested more levels deep"
+    )]
+    pub max_context_depth: Option<usize>,
+    #[structopt(
+        long = "max-context-bytes",
+        about = "Fail when the context is larger as JSON, in bytes"
+    )]
+    pub max_context_bytes: Option<usize>,
+    #[structopt(
+        long = "bundle",
+        about = "Render the named template of a bundle written by `tmptoml bundle`",
+        parse(from_os_str)
+    )]
+    pub bundle: Option<PathBuf>,
+    #[structopt(
+        long = "record",
+        about = "Record the template, resolved context and output to replay with `tmptoml replay`, redacting secrets",
+        parse(from_os_str),
+        conflicts_with = "bundle"
+    )]
+    pub record: Option<PathBuf>,
+    #[structopt(
+        long = "locale",
+        about = "Locale of the format_number and format_date filters, e.g. de-DE"
+    )]
+    pub locale: Option<String>,
+    #[structopt(
+        long = "only",
+        about = "Substitute only these variables, re-emitting every other expression untouched",
+        use_delimiter = true,
+        conflicts_with = "defer-unknown"
+    )]
+    pub only: Option<Vec<String>>,
+    #[structopt(
+        long = "defer-unknown",
+        about = "Re-emit expressions using variables missing from the context untouched"
+    )]
+    pub defer_unknown: bool,
+    #[structopt(
+        long = "passthrough-markers",
+        about = "Begin and end markers of regions emitted byte-for-byte, e.g. '# tmptoml:off' '# tmptoml:on'",
+        number_of_values = 2,
+        value_names = &["begin", "end"]
+    )]
+    pub passthrough_markers: Vec<String>,
+    #[structopt(
+        long = "deprecated-key",
+        about = "Warn when the template reads this key, written key or key=replacement",
+        number_of_values = 1
+    )]
+    pub deprecated_keys: Vec<tmptoml::DeprecatedKey>,
+    #[structopt(
+        long = "warn-unused-keys",
+        about = "Warn about the values of the context the template doesn't read"
+    )]
+    pub warn_unused_keys: bool,
+    #[structopt(
+        long = "secret-output",
+        about = "The output is a secret, e.g. a Kubernetes Secret, so sensitive values can be written to it"
+    )]
+    pub secret_output: bool,
+    #[structopt(
+        long = "deny-warnings",
+        about = "Fail instead of rendering when there are warnings"
+    )]
+    pub deny_warnings: bool,
+    #[structopt(
+        long = "report-coercions",
+        about = "Report every value coerced to another type on STDERR"
+    )]
+    pub report_coercions: bool,
+    #[structopt(
+        long = "deny-coercions",
+        about = "Fail when any value is coerced to another type"
+    )]
+    pub deny_coercions: bool,
+    #[structopt(
+        long = "timings",
+        about = "Report the time spent in each phase of the render on STDERR"
+    )]
+    pub timings: bool,
+    #[structopt(
+        long = "output-compress",
+        about = "Compress the rendered output written to STDOUT",
+        possible_values = &["gzip", "zstd"]
+    )]
+    pub output_compress: Option<tmptoml::Compression>,
+    #[structopt(
+        long = "output-encoding",
+        about = "Encode the rendered output, base64 after compressing it and UTF-16 before",
+        possible_values = &["base64", "utf16-le-bom"]
+    )]
+    pub output_encoding: Option<tmptoml::OutputEncoding>,
+}
+
+impl RenderOptionArguments {
+    pub fn group_path(&self) -> Vec<String> {
+        if !self.groups.is_empty() {
+            return self.groups.clone();
+        }
+        self.group_id
+            .iter()
+            .chain(self.secondary_group_id.iter())
+            .cloned()
+            .collect()
+    }
+
+    // The positional or -g selection, or else the default group, comes first,
+    // followed by every -s selection in the order given.
+    pub fn group_paths(
+        &self,
+        config: &std::path::Path,
+    ) -> Result<Vec<tmptoml::GroupPath>, tmptoml::TmpTomlErr> {
+        let mut group_path = self.group_path();
+        if group_path.is_empty() {
+            group_path = tmptoml::default_group_path_file(config)?;
+        }
+        if group_path.is_empty() && self.selections.is_empty() {
+            clap::Error::with_description(
+                "No group given: pass the group IDs, set TMPTOML_GROUP or add a [_defaults] table with a group to the config",
+                clap::ErrorKind::MissingRequiredArgument,
+            )
+            .exit();
+        }
+        let selections = self
+            .selections
+            .iter()
+            .map(|selection| tmptoml::parse_group_path(selection));
+        Ok(std::iter::once(group_path)
+            .filter(|group_path| !group_path.is_empty())
+            .chain(selections)
+            .collect())
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct BatchArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(
+        name = "template_dir",
+        about = "Directory of the templates to render",
+        parse(from_os_str)
+    )]
+    pub template_dir: PathBuf,
+    #[structopt(
+        short = "s",
+        long = "select",
+        about = "Dotted path of a group to render the templates for, e.g. qa.system1",
+        number_of_values = 1,
+        required_unless = "tags"
+    )]
+    pub selections: Vec<String>,
+    #[structopt(
+        long = "tags",
+        name = "tags",
+        about = "Render the sections whose _tags have any of these tags, within the selections if given",
+        use_delimiter = true
+    )]
+    pub tags: Vec<String>,
+    #[structopt(
+        short = "o",
+        long = "output-dir",
+        about = "Directory the rendered files are written to, one subdirectory per group",
+        parse(from_os_str)
+    )]
+    pub output_dir: PathBuf,
+    #[structopt(
+        long = "exclude",
+        about = "Glob of template files to leave out, relative to the template directory",
+        number_of_values = 1
+    )]
+    pub exclude: Vec<String>,
+    #[structopt(
+        long = "gitignore",
+        about = "Leave out the template files .gitignore files ignore"
+    )]
+    pub gitignore: bool,
+    #[structopt(
+        long = "context-json",
+        about = "JSON object merged into the context with the highest precedence",
+        number_of_values = 1
+    )]
+    pub context_json: Vec<String>,
+    #[structopt(
+        long = "stdin-context",
+        about = "Merge a document piped to stdin into the context",
+        possible_values = &["json", "toml"]
+    )]
+    pub stdin_context: Option<tmptoml::ContextFormat>,
+    #[structopt(
+        long = "context-cmd",
+        about = "Merge the JSON or TOML a shell command prints into the context",
+        number_of_values = 1
+    )]
+    pub context_cmd: Vec<String>,
+    #[structopt(
+        long = "set",
+        about = "Override a value with key=value, the value being parsed as TOML",
+        number_of_values = 1
+    )]
+    pub set: Vec<String>,
+    #[structopt(
+        long = "set-string",
+        about = "Override a value with key=value, the value always being a string",
+        number_of_values = 1
+    )]
+    pub set_string: Vec<String>,
+    #[structopt(
+        long = "precedence",
+        about = "Whether earlier or later group levels, selections and context values win when merged",
+        default_value = "last-wins",
+        possible_values = &["first-wins", "last-wins"]
+    )]
+    pub precedence: tmptoml::Precedence,
+    #[structopt(
+        long = "include-siblings",
+        about = "Expose the tables next to the selected groups under their own keys instead of leaving them out"
+    )]
+    pub include_siblings: bool,
+    #[structopt(
+        long = "explain",
+        about = "List every table the selected groups leave out of the context on STDERR"
+    )]
+    pub explain: bool,
+    #[structopt(
+        long = "macros",
+        about = "Directory of *.tera macro files templates can import",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub macros: Vec<PathBuf>,
+    #[structopt(
+        long = "restrict-root",
+        about = "Directory the file() and glob() template functions can read below",
+        parse(from_os_str)
+    )]
+    pub restrict_root: Option<PathBuf>,
+    #[structopt(
+        long = "allow-config-lookup",
+        about = "Let templates read any value of the config with the config() function"
+    )]
+    pub allow_config_lookup: bool,
+    #[structopt(
+        long = "max-context-keys",
+        about = "Fail when the context has more keys, those of nested tables included"
+    )]
+    pub max_context_keys: Option<usize>,
+    #[structopt(
+        long = "max-context-depth",
+        about = "Fail when the context is nested more levels deep"
+    )]
+    pub max_context_depth: Option<usize>,
+    #[structopt(
+        long = "max-context-bytes",
+        about = "Fail when the context is larger as JSON, in bytes"
+    )]
+    pub max_context_bytes: Option<usize>,
+    #[structopt(
+        long = "reproducible",
+        about = "Pin now() to the source date and refuse get_env() and get_random(), for byte-identical output"
+    )]
+    pub reproducible: bool,
+    #[structopt(
+        long = "source-date-epoch",
+        about = "Seconds since the Unix epoch now() returns with --reproducible",
+        env = "SOURCE_DATE_EPOCH"
+    )]
+    pub source_date_epoch: Option<i64>,
+    #[structopt(
+        long = "locale",
+        about = "Locale of the format_number and format_date filters, e.g. de-DE"
+    )]
+    pub locale: Option<String>,
+    #[structopt(
+        long = "timings",
+        about = "Report the time spent compiling and rendering each file on STDERR"
+    )]
+    pub timings: bool,
+    #[structopt(
+        long = "continue-on-error",
+        about = "Write the files that render and report the others instead of aborting"
+    )]
+    pub continue_on_error: bool,
+    #[structopt(
+        long = "timeout-per-file",
+        about = "Seconds a single file may take to render before it is reported as timed out",
+        parse(try_from_str = parse_timeout)
+    )]
+    pub timeout_per_file: Option<std::time::Duration>,
+    #[structopt(
+        short = "j",
+        long = "jobs",
+        about = "Number of files to render at once",
+        default_value = "1"
+    )]
+    pub jobs: usize,
+    #[structopt(
+        long = "ordered",
+        about = "List the files in rendering order rather than the order they finish in"
+    )]
+    pub ordered: bool,
+    #[structopt(
+        long = "output-compress",
+        about = "Compress the written files, adding .gz or .zst to their names",
+        possible_values = &["gzip", "zstd"]
+    )]
+    pub output_compress: Option<tmptoml::Compression>,
+    #[structopt(
+        long = "output-encoding",
+        about = "Encode the written files, base64 after compressing them and UTF-16 before",
+        possible_values = &["base64", "utf16-le-bom"]
+    )]
+    pub output_encoding: Option<tmptoml::OutputEncoding>,
+    #[structopt(
+        long = "manifest",
+        about = "Record the files written and their hashes in this JSON manifest",
+        parse(from_os_str)
+    )]
+    pub manifest: Option<PathBuf>,
+    #[structopt(
+        long = "report",
+        about = "Write a JSON report of the files written and failed to this path",
+        parse(from_os_str)
+    )]
+    pub report: Option<PathBuf>,
+    #[structopt(
+        long = "prune",
+        about = "Remove the files of the output directory the previous --manifest lists and the batch no longer writes",
+        requires = "manifest"
+    )]
+    pub prune: bool,
+    #[structopt(
+        long = "prune-allow",
+        about = "Glob of files to keep when pruning, relative to the output directory",
+        number_of_values = 1,
+        requires = "prune"
+    )]
+    pub prune_allow: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct BundleArguments {
+    #[structopt(
+        name = "template_dir",
+        about = "Directory of the templates to bundle",
+        parse(from_os_str)
+    )]
+    pub template_dir: PathBuf,
+    #[structopt(
+        short = "o",
+        long = "output",
+        about = "Path of the bundle to write",
+        parse(from_os_str)
+    )]
+    pub output: PathBuf,
+    #[structopt(
+        long = "exclude",
+        about = "Glob of template files to leave out, relative to the template directory",
+        number_of_values = 1
+    )]
+    pub exclude: Vec<String>,
+    #[structopt(
+        long = "gitignore",
+        about = "Leave out the template files .gitignore files ignore"
+    )]
+    pub gitignore: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct BakeArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(
+        name = "template_dir",
+        about = "Directory of the templates to bake",
+        parse(from_os_str)
+    )]
+    pub template_dir: PathBuf,
+    #[structopt(
+        short = "s",
+        long = "select",
+        about = "Dotted path of a group the baked executable renders by default, e.g. qa.system1",
+        number_of_values = 1,
+        required = true
+    )]
+    pub selections: Vec<String>,
+    #[structopt(
+        short = "o",
+        long = "output",
+        about = "Path of the executable to write",
+        parse(from_os_str)
+    )]
+    pub output: PathBuf,
+    #[structopt(
+        long = "exclude",
+        about = "Glob of template files to leave out, relative to the template directory",
+        number_of_values = 1
+    )]
+    pub exclude: Vec<String>,
+    #[structopt(
+        long = "gitignore",
+        about = "Leave out the template files .gitignore files ignore"
+    )]
+    pub gitignore: bool,
+}
+
+/// Arguments of a baked executable.
+#[derive(StructOpt, Debug)]
+#[structopt(about = "Renders the templates baked into this executable")]
+pub struct BakedArguments {
+    #[structopt(
+        short = "o",
+        long = "output-dir",
+        about = "Directory the rendered files are written to, one subdirectory per group",
+        default_value = ".",
+        parse(from_os_str)
+    )]
+    pub output_dir: PathBuf,
+    #[structopt(
+        short = "s",
+        long = "select",
+        about = "Dotted path of a group to render instead of the baked ones",
+        number_of_values = 1
+    )]
+    pub selections: Vec<String>,
+    #[structopt(
+        long = "set",
+        about = "Override a value with key=value, the value being parsed as TOML",
+        number_of_values = 1
+    )]
+    pub set: Vec<String>,
+    #[structopt(
+        long = "set-string",
+        about = "Override a value with key=value, the value always being a string",
+        number_of_values = 1
+    )]
+    pub set_string: Vec<String>,
+    #[structopt(
+        long = "continue-on-error",
+        about = "Write the files that render and report the others instead of aborting"
+    )]
+    pub continue_on_error: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ManifestArguments {
+    #[structopt(
+        long = "manifest",
+        about = "Manifest written by `batch --manifest`",
+        parse(from_os_str)
+    )]
+    pub manifest: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ConvertArguments {
+    #[structopt(
+        long = "from",
+        about = "Template syntax to convert from",
+        possible_values = &["envsubst"]
+    )]
+    pub from: tmptoml::ConvertFrom,
+    #[structopt(
+        name = "template",
+        about = "Path to the template file to convert",
+        parse(from_os_str)
+    )]
+    pub template: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ImportArguments {
+    #[structopt(
+        long = "from",
+        about = "Format of the values file to import",
+        possible_values = &["helm"]
+    )]
+    pub from: tmptoml::ImportFrom,
+    #[structopt(
+        long = "group",
+        about = "ID of the toml group to import the values into"
+    )]
+    pub group_id: String,
+    #[structopt(
+        name = "values",
+        about = "Path to the values file to import",
+        parse(from_os_str)
+    )]
+    pub values: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ExportGroupArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
     #[structopt(name = "group_id", about = "ID of the toml group to use")]
     pub group_id: String,
     #[structopt(
@@ -32,42 +834,1103 @@ pub struct ApplicationArguments {
         about = "ID of the toml secondary group to use"
     )]
     pub secondary_group_id: String,
+    #[structopt(
+        long = "format",
+        about = "Format of the exported document",
+        default_value = "toml",
+        possible_values = &["toml", "shell"]
+    )]
+    pub format: tmptoml::ExportFormat,
+    #[structopt(
+        long = "include-sensitive",
+        about = "Export the values marked sensitive, which are left out otherwise"
+    )]
+    pub include_sensitive: bool,
+    #[structopt(
+        long = "report-coercions",
+        about = "Report every value coerced to another type on STDERR"
+    )]
+    pub report_coercions: bool,
+    #[structopt(
+        long = "deny-coercions",
+        about = "Fail when any value is coerced to another type"
+    )]
+    pub deny_coercions: bool,
+    #[structopt(
+        long = "verify-roundtrip",
+        about = "Read the exported document back and fail when any value is lost or changed"
+    )]
+    pub verify_roundtrip: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct FlattenArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct LintConfigArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(
+        long = "referenced-in",
+        about = "Manifest or profile selecting groups, groups no such file mentions are flagged",
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    pub referenced_in: Vec<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct InspectArguments {
+    #[structopt(
+        name = "template",
+        about = "Path to the template file",
+        parse(from_os_str)
+    )]
+    pub template: PathBuf,
+    #[structopt(
+        long = "config",
+        about = "Config file whose selected groups variables are checked against",
+        parse(from_os_str),
+        requires = "select"
+    )]
+    pub config: Option<PathBuf>,
+    #[structopt(
+        short = "s",
+        long = "select",
+        name = "select",
+        about = "Dotted path of a group whose values the template reads, e.g. qa.system1",
+        number_of_values = 1,
+        requires = "config"
+    )]
+    pub selections: Vec<String>,
+    #[structopt(
+        long = "format",
+        about = "Format of the listing",
+        default_value = "text",
+        possible_values = &["text", "json"]
+    )]
+    pub format: tmptoml::InspectFormat,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ReplayArguments {
+    #[structopt(
+        name = "session",
+        about = "Path to the session file",
+        parse(from_os_str)
+    )]
+    pub session: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct StatsArguments {
+    #[structopt(name = "config", about = "Path to the config file", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(
+        name = "template_dir",
+        about = "Directory of the templates to count",
+        parse(from_os_str)
+    )]
+    pub template_dir: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CompletionsArguments {
+    #[structopt(
+        name = "shell",
+        about = "Shell to print the completion script of",
+        possible_values = &clap::Shell::variants()
+    )]
+    pub shell: clap::Shell,
 }
 
-fn run() -> Result<String, tmptoml::TmpTomlErr> {
-    let opt: ApplicationArguments = ApplicationArguments::from_args();
-    let config_file_path: PathBuf = opt.config.clone();
-    let template_file_path: PathBuf = opt.template.clone();
-    let group_id: String = opt.group_id.clone();
-    let sec_group_id: String = opt.secondary_group_id.clone();
+// Arguments of `tmptoml __complete`, called by the completion scripts with the
+// words typed so far. It isn't a subcommand, which would show up in the
+// completions themselves and trip clap's bash generator over its name.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "__complete")]
+pub struct CompleteArguments {
+    #[structopt(long = "dotted", about = "Complete a dotted group path such as qa.sys")]
+    pub dotted: bool,
+    #[structopt(name = "config", parse(from_os_str))]
+    pub config: PathBuf,
+    #[structopt(
+        name = "words",
+        about = "Group IDs typed so far followed by the partial ID"
+    )]
+    pub words: Vec<String>,
+}
 
-    return tmptoml::render_template(
-        &config_file_path,
-        &template_file_path,
-        group_id,
-        sec_group_id,
+// Completes the group arguments of `render` and `render-config`, which the
+// script clap generates can't know, by asking `tmptoml __complete` for the
+// groups of the config file typed so far. Everything else is completed by the
+// generated script. The options whose values are skipped are filled in from
+// the definitions of `render` and `render-config`, the other subcommands from
+// those of `Command`.
+const BASH_GROUP_COMPLETION: &str = r#"
+_tmptoml_groups() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local positional=() groups=() word i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word="${COMP_WORDS[i]}"
+        case "$word" in
+            -g|--group)
+                if ((i + 1 < COMP_CWORD)); then groups+=("${COMP_WORDS[i+1]}"); fi
+                i=$((i + 1)) ;;
+            @PAIR_OPTIONS@) i=$((i + 2)) ;;
+            @VALUE_OPTIONS@)
+                i=$((i + 1)) ;;
+            -*) ;;
+            *) positional+=("$word") ;;
+        esac
+    done
+    local skip=2
+    case "${positional[0]}" in
+        render) positional=("${positional[@]:1}") ;;
+        render-config) positional=("${positional[@]:1}"); skip=1 ;;
+        @OTHER_SUBCOMMANDS@) _tmptoml; return ;;
+    esac
+    local config="${positional[0]}" candidates=""
+    if [[ -n "$config" ]]; then
+        case "$prev" in
+            -s|--select) candidates="$(tmptoml __complete --dotted "$config" "$cur" 2>/dev/null)" ;;
+            -g|--group) candidates="$(tmptoml __complete "$config" "${groups[@]}" "$cur" 2>/dev/null)" ;;
+            *)
+                if [[ "$cur" != -* ]] && ((${#groups[@]} == 0)) && ((${#positional[@]} == skip || ${#positional[@]} == skip + 1)); then
+                    candidates="$(tmptoml __complete "$config" "${positional[@]:skip}" "$cur" 2>/dev/null)"
+                fi ;;
+        esac
+    fi
+    if [[ -n "$candidates" ]]; then
+        COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+    else
+        _tmptoml
+    fi
+}
+complete -F _tmptoml_groups -o bashdefault -o default tmptoml
+"#;
+
+fn parse_timeout(seconds: &str) -> Result<std::time::Duration, String> {
+    seconds
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(std::time::Duration::from_secs_f64)
+        .ok_or_else(|| format!("`{}` is not a positive number of seconds", seconds))
+}
+
+// The original interface had no subcommands (`tmptoml config template group
+// secondary_group`), so anything that isn't a known subcommand is treated as
+// an invocation of `render`.
+fn parse_arguments() -> Result<ApplicationArguments, tmptoml::TmpTomlErr> {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    let rc_file = std::env::current_dir()
+        .ok()
+        .and_then(|dir| tmptoml::find_rc_file(&dir));
+    let starts_with_positional = args
+        .get(1)
+        .map(|arg| !arg.to_string_lossy().starts_with('-'))
+        .unwrap_or(false);
+    match parse_with_defaults(&args, rc_file.as_deref())? {
+        Ok(opt) => Ok(opt),
+        Err(err)
+            if starts_with_positional
+                && matches!(
+                    err.kind,
+                    clap::ErrorKind::UnknownArgument | clap::ErrorKind::UnrecognizedSubcommand
+                ) =>
+        {
+            args.insert(1, OsString::from("render"));
+            Ok(parse_with_defaults(&args, rc_file.as_deref())?.unwrap_or_else(|err| err.exit()))
+        }
+        Err(err) => err.exit(),
+    }
+}
+
+// `--no-<flag>` turns off a boolean flag of the subcommand that the defaults
+// file turns on, and is accepted when none does too. Returns the arguments
+// without the negations, along with the boolean flags the command line turns
+// on or off, whose defaults don't apply.
+fn given_boolean_flags(args: &[OsString]) -> (Vec<OsString>, BTreeSet<String>) {
+    let app = ApplicationArguments::clap();
+    let flags: BTreeSet<&str> = args
+        .get(1)
+        .and_then(|arg| arg.to_str())
+        .and_then(|name| {
+            app.p
+                .subcommands
+                .iter()
+                .find(|subcommand| subcommand.get_name() == name)
+        })
+        .map(|subcommand| {
+            subcommand
+                .p
+                .flags
+                .iter()
+                .filter_map(|flag| flag.s.long)
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut given = BTreeSet::new();
+    let mut remaining = Vec::new();
+    let mut after_separator = false;
+    for (index, arg) in args.iter().enumerate() {
+        let long = arg
+            .to_str()
+            .filter(|_| index >= 2 && !after_separator)
+            .and_then(|arg| arg.strip_prefix("--"));
+        after_separator |= arg == "--";
+        if let Some(flag) = long.filter(|flag| flags.contains(flag)) {
+            given.insert(flag.to_string());
+        } else if let Some(flag) = long
+            .and_then(|long| long.strip_prefix("no-"))
+            .filter(|flag| flags.contains(flag))
+        {
+            given.insert(flag.to_string());
+            continue;
+        }
+        remaining.push(arg.clone());
+    }
+    (remaining, given)
+}
+
+// The defaults file's flags for the subcommand go before those of the command
+// line, which override them. An error only the defaults cause is reported as
+// an error of the defaults file.
+fn parse_with_defaults(
+    args: &[OsString],
+    rc_file: Option<&std::path::Path>,
+) -> Result<Result<ApplicationArguments, clap::Error>, tmptoml::TmpTomlErr> {
+    let (args, given) = given_boolean_flags(args);
+    let args = &args[..];
+    let (rc_file, subcommand) = match (rc_file, args.get(1).and_then(|arg| arg.to_str())) {
+        (Some(rc_file), Some(subcommand)) => (rc_file, subcommand),
+        _ => return Ok(ApplicationArguments::from_iter_safe(args)),
+    };
+    let defaults: Vec<String> = tmptoml::rc_arguments(rc_file, subcommand)?
+        .into_iter()
+        .filter(|default| {
+            !default
+                .strip_prefix("--")
+                .is_some_and(|flag| given.contains(flag))
+        })
+        .collect();
+    if defaults.is_empty() {
+        return Ok(ApplicationArguments::from_iter_safe(args));
+    }
+    let with_defaults: Vec<OsString> = args[..2]
+        .iter()
+        .cloned()
+        .chain(defaults.into_iter().map(OsString::from))
+        .chain(args[2..].iter().cloned())
+        .collect();
+    match ApplicationArguments::from_iter_safe(&with_defaults) {
+        Ok(opt) => Ok(Ok(opt)),
+        Err(err) if ApplicationArguments::from_iter_safe(args).is_ok() => {
+            Err(tmptoml::RcErr::Invalid(format!("{}: {}", rc_file.display(), err.message)).into())
+        }
+        Err(err) => Ok(Err(err)),
+    }
+}
+
+// What a command prints. Compressed and UTF-16 output is binary, written as
+// is without the newline printed after text.
+enum Output {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+fn write_output(output: Output) -> Result<(), tmptoml::TmpTomlErr> {
+    match output {
+        Output::Text(output) => println!("{}", output),
+        Output::Binary(output) => {
+            use std::io::Write;
+            let mut stdout = std::io::stdout();
+            stdout
+                .write_all(&output)
+                .and_then(|_| stdout.flush())
+                .map_err(|err| tmptoml::TmpTomlErr::Output(err.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+fn render(opt: RenderArguments) -> Result<Output, tmptoml::TmpTomlErr> {
+    render_with(&opt.config, opt.template, &opt.render)
+}
+
+// The template of the selected group is relative to the config file, or a
+// template name with --bundle.
+fn render_config(opt: RenderConfigArguments) -> Result<Output, tmptoml::TmpTomlErr> {
+    let group_paths = opt.render.group_paths(&opt.config)?;
+    let template = tmptoml::config_template(&opt.config, &group_paths)?;
+    let template = match &opt.render.bundle {
+        Some(_) => PathBuf::from(template),
+        None => opt
+            .config
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new(""))
+            .join(template),
+    };
+    render_with(&opt.config, template, &opt.render)
+}
+
+fn render_options(
+    config: &std::path::Path,
+    opt: &RenderOptionArguments,
+) -> Result<tmptoml::RenderOptions, tmptoml::TmpTomlErr> {
+    // Overrides apply in increasing precedence: --stdin-context,
+    // --context-cmd, --set, --set-string and finally --context-json.
+    let mut context_values = Vec::new();
+    if let Some(format) = opt.stdin_context {
+        context_values.push(tmptoml::read_stdin_context(format)?);
+    }
+    for command in &opt.context_cmd {
+        context_values.push(tmptoml::run_context_command(command)?);
+    }
+    let mut overrides = tmptoml::ContextValues::new();
+    for assignment in &opt.set {
+        tmptoml::apply_override(&mut overrides, assignment, true)?;
+    }
+    for assignment in &opt.set_string {
+        tmptoml::apply_override(&mut overrides, assignment, false)?;
+    }
+    context_values.push(overrides);
+    for json in &opt.context_json {
+        context_values.push(tmptoml::parse_context_json(json)?);
+    }
+    Ok(tmptoml::RenderOptions {
+        group_paths: opt.group_paths(config)?,
+        context_values,
+        precedence: opt.precedence,
+        include_siblings: opt.include_siblings,
+        macro_dirs: opt.macros.clone(),
+        locale: opt.locale.clone(),
+        deferral: match (&opt.only, opt.defer_unknown) {
+            (Some(keys), _) => Some(tmptoml::Deferral::Only(keys.clone())),
+            (None, true) => Some(tmptoml::Deferral::Unknown),
+            (None, false) => None,
+        },
+        passthrough_markers: opt
+            .passthrough_markers
+            .chunks(2)
+            .map(|markers| tmptoml::PassthroughMarkers {
+                begin: markers[0].clone(),
+                end: markers[1].clone(),
+            })
+            .collect(),
+        deprecated_keys: opt.deprecated_keys.clone(),
+        warn_unused_keys: opt.warn_unused_keys,
+        restrict_root: opt.restrict_root.clone(),
+        reproducible: opt.reproducible,
+        source_date_epoch: opt.source_date_epoch,
+        config_lookup: opt.allow_config_lookup,
+        context_limits: tmptoml::ContextLimits {
+            max_keys: opt.max_context_keys,
+            max_depth: opt.max_context_depth,
+            max_bytes: opt.max_context_bytes,
+        },
+        secret_output: opt.secret_output,
+        ..tmptoml::RenderOptions::default()
+    })
+}
+
+fn render_with(
+    config: &std::path::Path,
+    template: PathBuf,
+    opt: &RenderOptionArguments,
+) -> Result<Output, tmptoml::TmpTomlErr> {
+    let options = render_options(config, opt)?;
+    let rendered = match &opt.bundle {
+        Some(bundle_path) => tmptoml::render_bundled_template(
+            config,
+            &tmptoml::Bundle::read(bundle_path)?,
+            &template.to_string_lossy(),
+            &options,
+        ),
+        None => tmptoml::render_template_with_warnings(config, &template, &options),
+    };
+    if let Some(session_path) = &opt.record {
+        let result = match &rendered {
+            Ok(rendered) => Ok(rendered.output.as_str()),
+            Err(err) => Err(format!("{:?}", err)),
+        };
+        // A render failing before the context could be resolved has nothing
+        // to replay, its error is reported as is.
+        match tmptoml::Session::record(config, &template, &options, result) {
+            Ok(session) => session.write(session_path)?,
+            Err(record_err) if rendered.is_ok() => return Err(record_err),
+            Err(_) => {}
+        }
+    }
+    let rendered = rendered?;
+    print_notices(&rendered.notices, opt.explain);
+    for warning in &rendered.warnings {
+        eprintln!("{}", warning);
+    }
+    if opt.timings {
+        eprintln!("{}", rendered.timings);
+    }
+    if opt.deny_warnings && !rendered.warnings.is_empty() {
+        return Err(tmptoml::TmpTomlErr::DeniedWarnings(rendered.warnings));
+    }
+    report_coercions(rendered.coercions, opt.report_coercions, opt.deny_coercions)?;
+    if opt.output_compress.is_none() && opt.output_encoding.is_none() {
+        return Ok(Output::Text(rendered.output));
+    }
+    let output = tmptoml::encode_output(
+        rendered.output.into_bytes(),
+        opt.output_compress,
+        opt.output_encoding,
+    )?;
+    Ok(match String::from_utf8(output) {
+        Ok(output) => Output::Text(output),
+        Err(err) => Output::Binary(err.into_bytes()),
+    })
+}
+
+// The groups, batch options and render options a batch or plan renders
+// with.
+fn batch_setup(
+    opt: &BatchArguments,
+) -> Result<
+    (
+        Vec<tmptoml::GroupPath>,
+        tmptoml::BatchOptions,
+        tmptoml::RenderOptions,
+    ),
+    tmptoml::TmpTomlErr,
+> {
+    let mut context_values = Vec::new();
+    if let Some(format) = opt.stdin_context {
+        context_values.push(tmptoml::read_stdin_context(format)?);
+    }
+    for command in &opt.context_cmd {
+        context_values.push(tmptoml::run_context_command(command)?);
+    }
+    let mut overrides = tmptoml::ContextValues::new();
+    for assignment in &opt.set {
+        tmptoml::apply_override(&mut overrides, assignment, true)?;
+    }
+    for assignment in &opt.set_string {
+        tmptoml::apply_override(&mut overrides, assignment, false)?;
+    }
+    context_values.push(overrides);
+    for json in &opt.context_json {
+        context_values.push(tmptoml::parse_context_json(json)?);
+    }
+    let options = tmptoml::RenderOptions {
+        context_values,
+        precedence: opt.precedence,
+        include_siblings: opt.include_siblings,
+        macro_dirs: opt.macros.clone(),
+        locale: opt.locale.clone(),
+        restrict_root: opt.restrict_root.clone(),
+        reproducible: opt.reproducible,
+        source_date_epoch: opt.source_date_epoch,
+        config_lookup: opt.allow_config_lookup,
+        context_limits: tmptoml::ContextLimits {
+            max_keys: opt.max_context_keys,
+            max_depth: opt.max_context_depth,
+            max_bytes: opt.max_context_bytes,
+        },
+        ..tmptoml::RenderOptions::default()
+    };
+    let mut group_paths: Vec<tmptoml::GroupPath> = opt
+        .selections
+        .iter()
+        .map(|selection| tmptoml::parse_group_path(selection))
+        .collect();
+    if !opt.tags.is_empty() {
+        group_paths = tmptoml::select_tagged_file(&opt.config, &opt.tags, &group_paths)?;
+    }
+    let batch_options = tmptoml::BatchOptions {
+        filter: tmptoml::TemplateFilter {
+            exclude: opt.exclude.clone(),
+            gitignore: opt.gitignore,
+        },
+        continue_on_error: opt.continue_on_error,
+        timeout_per_file: opt.timeout_per_file,
+        jobs: opt.jobs,
+        ordered: opt.ordered,
+        compression: opt.output_compress,
+        encoding: opt.output_encoding,
+    };
+    Ok((group_paths, batch_options, options))
+}
+
+// A config of thousands of groups leaves thousands of tables out of every
+// render, so they are only listed one by one with --explain.
+fn print_notices(notices: &[tmptoml::Notice], explain: bool) {
+    if explain {
+        for notice in notices {
+            eprintln!("{}", notice);
+        }
+    } else if !notices.is_empty() {
+        eprintln!(
+            "NOTICE: {} table(s) are left out of the context, see --explain",
+            notices.len()
+        );
+    }
+}
+
+fn batch(opt: BatchArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let (group_paths, batch_options, options) = batch_setup(&opt)?;
+    let batch = tmptoml::render_batch(
+        &opt.config,
+        &opt.template_dir,
+        &opt.output_dir,
+        &group_paths,
+        &batch_options,
+        options,
+    )?;
+    print_notices(&batch.notices, opt.explain);
+    if let Some(report_path) = &opt.report {
+        let report = serde_json::to_string_pretty(&batch.report()).unwrap_or_default();
+        std::fs::write(report_path, report).map_err(|err| {
+            tmptoml::BatchErr::Write(format!("{}: {}", report_path.display(), err))
+        })?;
+    }
+    if opt.timings {
+        eprintln!(
+            "{}",
+            tmptoml::format_timing("config read/parse", batch.timings.config)
+        );
+        eprintln!(
+            "{}",
+            tmptoml::format_timing("template compilation", batch.timings.compile)
+        );
+        for output in &batch.outputs {
+            eprintln!(
+                "{}",
+                tmptoml::format_timing(
+                    &format!("rendering {}", output.path.display()),
+                    output.timings.render
+                )
+            );
+        }
+    }
+    if let Some(manifest_path) = &opt.manifest {
+        let previous = tmptoml::Manifest::read(manifest_path)?;
+        let current = tmptoml::Manifest::from_batch(&batch, &previous)?;
+        current.write(manifest_path)?;
+        if opt.prune {
+            for path in
+                tmptoml::prune_output_dir(&opt.output_dir, &previous, &current, &opt.prune_allow)?
+            {
+                println!("Pruned {}", path.display());
+            }
+        }
+    }
+    written_files(batch)
+}
+
+// Files that would fail to render are listed in the plan, which then exits
+// with status 1.
+fn plan(opt: BatchArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let written = [
+        ("--manifest", opt.manifest.is_some()),
+        ("--report", opt.report.is_some()),
+        ("--prune", opt.prune),
+    ];
+    if let Some((flag, _)) = written.iter().find(|(_, given)| *given) {
+        return Err(tmptoml::BatchErr::NotPlanned(format!(
+            "{} writes or removes files, which plan doesn't",
+            flag
+        ))
+        .into());
+    }
+    let (group_paths, batch_options, options) = batch_setup(&opt)?;
+    let plan = tmptoml::plan_batch(
+        &opt.config,
+        &opt.template_dir,
+        &opt.output_dir,
+        &group_paths,
+        &batch_options,
+        options,
+    )?;
+    print_notices(&plan.notices, opt.explain);
+    if plan.failures.is_empty() {
+        return Ok(plan.to_string());
+    }
+    println!("{}", plan);
+    std::process::exit(1);
+}
+
+// Lists the files a batch wrote, failing after listing them if any file
+// failed to render.
+fn written_files(batch: tmptoml::Batch) -> Result<String, tmptoml::TmpTomlErr> {
+    let written = batch
+        .outputs
+        .iter()
+        .map(|output| format!("Wrote {}", output.path.display()))
+        .collect::<Vec<String>>()
+        .join("\n");
+    if !batch.failures.is_empty() {
+        if !written.is_empty() {
+            println!("{}", written);
+        }
+        return Err(tmptoml::BatchErr::Failed(batch.failures).into());
+    }
+    Ok(written)
+}
+
+fn bake(opt: BakeArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let executable = std::env::current_exe()
+        .map_err(|err| tmptoml::BakeErr::Read(format!("tmptoml executable: {}", err)))?;
+    let baked = tmptoml::Baked {
+        config: std::fs::read_to_string(&opt.config)
+            .map_err(|err| tmptoml::BakeErr::Read(format!("{}: {}", opt.config.display(), err)))?,
+        bundle: tmptoml::Bundle::from_dir(
+            &opt.template_dir,
+            &tmptoml::TemplateFilter {
+                exclude: opt.exclude.clone(),
+                gitignore: opt.gitignore,
+            },
+        )?,
+        group_paths: opt
+            .selections
+            .iter()
+            .map(|selection| tmptoml::parse_group_path(selection))
+            .collect(),
+    };
+    tmptoml::bake(&executable, &baked, &opt.output)?;
+    Ok(format!(
+        "Baked {} template(s) into {}",
+        baked.bundle.templates.len(),
+        opt.output.display()
+    ))
+}
+
+fn baked(baked: tmptoml::Baked) -> Result<String, tmptoml::TmpTomlErr> {
+    let opt = BakedArguments::from_args();
+    let mut overrides = tmptoml::ContextValues::new();
+    for assignment in &opt.set {
+        tmptoml::apply_override(&mut overrides, assignment, true)?;
+    }
+    for assignment in &opt.set_string {
+        tmptoml::apply_override(&mut overrides, assignment, false)?;
+    }
+    let group_paths: Vec<tmptoml::GroupPath> = opt
+        .selections
+        .iter()
+        .map(|selection| tmptoml::parse_group_path(selection))
+        .collect();
+    let batch = tmptoml::render_baked(
+        &baked,
+        &opt.output_dir,
+        &group_paths,
+        &tmptoml::BatchOptions {
+            continue_on_error: opt.continue_on_error,
+            ..tmptoml::BatchOptions::default()
+        },
+        tmptoml::RenderOptions {
+            context_values: vec![overrides],
+            ..tmptoml::RenderOptions::default()
+        },
+    )?;
+    written_files(batch)
+}
+
+fn clean(opt: ManifestArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    Ok(tmptoml::clean_manifest(&opt.manifest)?
+        .iter()
+        .map(|cleaned| match cleaned {
+            tmptoml::Cleaned::Removed(path) => format!("Removed {}", path),
+            tmptoml::Cleaned::Modified(path) => {
+                format!("Kept {}, it changed since it was generated", path)
+            }
+            tmptoml::Cleaned::Missing(path) => format!("Already removed {}", path),
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+fn outdated(opt: ManifestArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    Ok(tmptoml::outdated_outputs(&opt.manifest)?
+        .iter()
+        .map(|outdated| match outdated {
+            tmptoml::Outdated::OutputMissing(path) => format!("{} (removed)", path),
+            tmptoml::Outdated::InputChanged { output, input } => {
+                format!("{} ({} changed)", output, input)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+fn bundle(opt: BundleArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let bundle = tmptoml::Bundle::from_dir(
+        &opt.template_dir,
+        &tmptoml::TemplateFilter {
+            exclude: opt.exclude.clone(),
+            gitignore: opt.gitignore,
+        },
+    )?;
+    bundle.write(&opt.output)?;
+    Ok(format!(
+        "Bundled {} template(s) into {}",
+        bundle.templates.len(),
+        opt.output.display()
+    ))
+}
+
+fn convert(opt: ConvertArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let conversion = tmptoml::convert_template_file(opt.from, &opt.template)?;
+    eprintln!(
+        "Found {} variable(s): {}",
+        conversion.variables.len(),
+        conversion.variables.join(", ")
     );
+    Ok(conversion.template)
+}
+
+fn import(opt: ImportArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    tmptoml::import_file(opt.from, &opt.values, &opt.group_id)
+}
+
+// Denied coercions are always reported, so that the error can be acted on.
+fn report_coercions(
+    coercions: Vec<tmptoml::Coercion>,
+    report: bool,
+    deny: bool,
+) -> Result<(), tmptoml::TmpTomlErr> {
+    if report || deny {
+        for coercion in &coercions {
+            eprintln!("{}", coercion);
+        }
+    }
+    if deny && !coercions.is_empty() {
+        return Err(tmptoml::TmpTomlErr::DeniedCoercions(coercions));
+    }
+    Ok(())
+}
+
+fn export_group(opt: ExportGroupArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let exported = tmptoml::export_group_with_coercions(
+        &opt.config,
+        &opt.group_id,
+        &opt.secondary_group_id,
+        opt.format,
+        opt.include_sensitive,
+    )?;
+    if opt.verify_roundtrip {
+        exported.verify_roundtrip()?;
+    }
+    report_coercions(exported.coercions, opt.report_coercions, opt.deny_coercions)?;
+    Ok(exported.output)
+}
+
+fn flatten(opt: FlattenArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    tmptoml::flatten_config_file(&opt.config)
+}
+
+fn lint_config(opt: LintConfigArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    tmptoml::lint_config_file(&opt.config, &opt.referenced_in)
+}
+
+fn inspect(opt: InspectArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let group_paths: Vec<tmptoml::GroupPath> = opt
+        .selections
+        .iter()
+        .map(|selection| tmptoml::parse_group_path(selection))
+        .collect();
+    tmptoml::inspect_file(
+        &opt.template,
+        opt.config
+            .as_deref()
+            .map(|config| (config, group_paths.as_slice())),
+        opt.format,
+    )
+}
+
+fn replay(opt: ReplayArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    tmptoml::Session::read(&opt.session)?.replay()
+}
+
+fn eval(mut opt: EvalArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let expression = match opt.expression.take() {
+        Some(expression) => expression,
+        None => match opt.render.secondary_group_id.take() {
+            Some(expression) => expression,
+            None => opt.render.group_id.take().unwrap_or_else(|| {
+                clap::Error::with_description(
+                    "No expression given: pass it after the group IDs, e.g. '{{ port + 100 }}'",
+                    clap::ErrorKind::MissingRequiredArgument,
+                )
+                .exit()
+            }),
+        },
+    };
+    let options = render_options(&opt.config, &opt.render)?;
+    tmptoml::eval_expression(&opt.config, &expression, &options)
+}
+
+fn bench(opt: BenchArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let options = render_options(&opt.config, &opt.render)?;
+    let bench = tmptoml::bench_template(&opt.config, &opt.template, &options, opt.iterations)?;
+    Ok(bench.to_string())
+}
+
+// The diff is printed either way, the files are only written without
+// --dry-run.
+fn rename_key(opt: RenameKeyArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let filter = tmptoml::TemplateFilter {
+        exclude: opt.exclude,
+        gitignore: opt.gitignore,
+    };
+    let rename = tmptoml::rename_key(
+        &opt.config,
+        &opt.template_dir,
+        &opt.old_key,
+        &opt.new_key,
+        &filter,
+    )?;
+    if !opt.dry_run {
+        rename.write()?;
+    }
+    Ok(rename.diff())
+}
+
+fn stats(opt: StatsArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    tmptoml::stats_file(&opt.config, opt.template_dir.as_deref())
+}
+
+// The bash patterns of the options of `render` and `render-config` taking one
+// value and of those taking two, `-g|--group` left out as the group
+// completion reads its values.
+fn value_option_patterns(app: &clap::App) -> (String, String) {
+    let mut single = BTreeSet::new();
+    let mut pairs = BTreeSet::new();
+    let render_apps = app
+        .p
+        .subcommands
+        .iter()
+        .filter(|subcommand| matches!(subcommand.get_name(), "render" | "render-config"));
+    for option in render_apps.flat_map(|subcommand| subcommand.p.opts.iter()) {
+        if option.s.long == Some("group") {
+            continue;
+        }
+        let names = option
+            .s
+            .short
+            .map(|short| format!("-{}", short))
+            .into_iter()
+            .chain(option.s.long.map(|long| format!("--{}", long)));
+        match option.v.num_vals {
+            Some(2) => pairs.extend(names),
+            _ => single.extend(names),
+        }
+    }
+    let join = |names: BTreeSet<String>| names.into_iter().collect::<Vec<String>>().join("|");
+    (join(single), join(pairs))
+}
+
+// The bash pattern of the subcommands other than `render` and
+// `render-config`, which take no groups.
+fn other_subcommand_pattern(app: &clap::App) -> String {
+    let mut subcommands: BTreeSet<&str> = app
+        .p
+        .subcommands
+        .iter()
+        .map(|subcommand| subcommand.get_name())
+        .filter(|name| !matches!(*name, "render" | "render-config"))
+        .collect();
+    subcommands.insert("help");
+    subcommands.into_iter().collect::<Vec<&str>>().join("|")
+}
+
+fn completions(opt: CompletionsArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let mut app = ApplicationArguments::clap();
+    let mut script = Vec::new();
+    app.gen_completions_to("tmptoml", opt.shell, &mut script);
+    let mut script = String::from_utf8_lossy(&script).into_owned();
+    if let clap::Shell::Bash = opt.shell {
+        let (single, pairs) = value_option_patterns(&app);
+        script.push_str(
+            &BASH_GROUP_COMPLETION
+                .replace("@VALUE_OPTIONS@", &single)
+                .replace("@PAIR_OPTIONS@", &pairs)
+                .replace("@OTHER_SUBCOMMANDS@", &other_subcommand_pattern(&app)),
+        );
+    }
+    Ok(script)
+}
+
+// Completion must never print errors into the user's prompt, so a config
+// that can't be read completes nothing.
+fn complete(opt: CompleteArguments) -> Result<String, tmptoml::TmpTomlErr> {
+    let toml_config = match tmptoml::parse_toml_to_config(opt.config.to_str()) {
+        Ok(toml_config) => toml_config,
+        Err(_) => return Ok(String::new()),
+    };
+    let mut words = opt.words;
+    let partial = words.pop().unwrap_or_default();
+    let ids = if opt.dotted {
+        tmptoml::complete_group_path(&toml_config, &partial)
+    } else {
+        tmptoml::complete_group_ids(&toml_config, &words, &partial)
+    };
+    Ok(ids.join("\n"))
+}
+
+fn run() -> Result<Output, tmptoml::TmpTomlErr> {
+    if std::env::args_os()
+        .nth(1)
+        .is_some_and(|arg| arg == "__complete")
+    {
+        return complete(CompleteArguments::from_iter(std::env::args_os().skip(1)))
+            .map(Output::Text);
+    }
+    // A baked executable only regenerates the files it was baked with.
+    if let Ok(executable) = std::env::current_exe() {
+        match tmptoml::read_baked(&executable) {
+            Ok(Some(baked_files)) => return baked(baked_files).map(Output::Text),
+            Err(tmptoml::BakeErr::Invalid(reason)) => {
+                return Err(tmptoml::BakeErr::Invalid(reason).into())
+            }
+            _ => {}
+        }
+    }
+    let opt: ApplicationArguments = parse_arguments()?;
+    let output = match opt.command {
+        Command::Render(render_opt) => return render(render_opt),
+        Command::RenderConfig(render_opt) => return render_config(render_opt),
+        Command::Eval(eval_opt) => eval(eval_opt),
+        Command::Batch(batch_opt) => batch(batch_opt),
+        Command::Plan(plan_opt) => plan(plan_opt),
+        Command::Clean(clean_opt) => clean(clean_opt),
+        Command::Outdated(outdated_opt) => outdated(outdated_opt),
+        Command::Bundle(bundle_opt) => bundle(bundle_opt),
+        Command::Bake(bake_opt) => bake(bake_opt),
+        Command::Convert(convert_opt) => convert(convert_opt),
+        Command::Import(import_opt) => import(import_opt),
+        Command::ExportGroup(export_opt) => export_group(export_opt),
+        Command::Flatten(flatten_opt) => flatten(flatten_opt),
+        Command::LintConfig(lint_opt) => lint_config(lint_opt),
+        Command::Inspect(inspect_opt) => inspect(inspect_opt),
+        Command::Replay(replay_opt) => replay(replay_opt),
+        Command::Bench(bench_opt) => bench(bench_opt),
+        Command::RenameKey(rename_opt) => rename_key(rename_opt),
+        Command::Stats(stats_opt) => stats(stats_opt),
+        Command::Completions(completions_opt) => completions(completions_opt),
+    };
+    output.map(Output::Text)
 }
+
 fn main() {
-    match run() {
-        Ok(output) => println!("{}", output),
+    match run().and_then(write_output) {
+        Ok(()) => {}
         Err(err) => match err {
             tmptoml::TmpTomlErr::File(file_error) => println!(
                 "ERROR: There was an issue reading the config or template file. Reason: {:?}",
                 file_error
             ),
-            tmptoml::TmpTomlErr::GroupNotFound(key_id) => println!(
-                "ERROR: Specified group_id or secondary_group_id ({:?}) could not be found in the config file.",
-                key_id
+            tmptoml::TmpTomlErr::GroupNotFound(unknown) => println!(
+                "ERROR: Specified group_id or secondary_group_id could not be found in the config file: {}",
+                unknown
             ),
             tmptoml::TmpTomlErr::Config(config_error) => println!(
                 "ERROR: The specified config file could not be parsed. Reason: {:?}",
                 config_error
             ),
+            tmptoml::TmpTomlErr::Render(tmptoml::TeraRenderErr::VariableNotFound(unknown)) => {
+                println!(
+                    "ERROR: Variable could not be found in the template context: {}",
+                    unknown
+                )
+            }
             tmptoml::TmpTomlErr::Render(render_error) => println!(
                 "ERROR: Unable to render the specified template. Reason: {:?}",
                 render_error
             ),
+            tmptoml::TmpTomlErr::Import(import_error) => println!(
+                "ERROR: Unable to import the specified values file. Reason: {:?}",
+                import_error
+            ),
+            tmptoml::TmpTomlErr::Export(tmptoml::ExportErr::LossyRoundtrip(losses)) => {
+                for loss in &losses {
+                    println!("ERROR: {}", loss);
+                }
+                println!(
+                    "ERROR: The export doesn't read back as the exported values, {} value(s) were lost or changed.",
+                    losses.len()
+                );
+                std::process::exit(1);
+            }
+            tmptoml::TmpTomlErr::Export(export_error) => println!(
+                "ERROR: Unable to export the specified group. Reason: {:?}",
+                export_error
+            ),
+            tmptoml::TmpTomlErr::Context(context_error) => println!(
+                "ERROR: Unable to build the template context. Reason: {:?}",
+                context_error
+            ),
+            tmptoml::TmpTomlErr::Batch(tmptoml::BatchErr::Failed(failures)) => {
+                for failure in &failures {
+                    println!(
+                        "ERROR: Unable to render {} for {}. Reason: {:?}",
+                        failure.template,
+                        failure.group_path.join("."),
+                        failure.error
+                    );
+                }
+                println!("ERROR: {} file(s) could not be rendered.", failures.len());
+                std::process::exit(1);
+            }
+            tmptoml::TmpTomlErr::Batch(batch_error) => println!(
+                "ERROR: Unable to render the template directory. Reason: {:?}",
+                batch_error
+            ),
+            tmptoml::TmpTomlErr::Manifest(manifest_error) => println!(
+                "ERROR: Unable to read or write the manifest. Reason: {:?}",
+                manifest_error
+            ),
+            tmptoml::TmpTomlErr::Bundle(bundle_error) => println!(
+                "ERROR: Unable to read or write the template bundle. Reason: {:?}",
+                bundle_error
+            ),
+            tmptoml::TmpTomlErr::Bake(bake_error) => println!(
+                "ERROR: Unable to bake or run the baked executable. Reason: {:?}",
+                bake_error
+            ),
+            tmptoml::TmpTomlErr::Session(session_error) => println!(
+                "ERROR: Unable to record or replay the render session. Reason: {:?}",
+                session_error
+            ),
+            tmptoml::TmpTomlErr::Rc(rc_error) => println!(
+                "ERROR: Unable to apply the flag defaults file. Reason: {:?}",
+                rc_error
+            ),
+            tmptoml::TmpTomlErr::Rename(rename_error) => println!(
+                "ERROR: Unable to rename the key. Reason: {:?}",
+                rename_error
+            ),
+            tmptoml::TmpTomlErr::Provider(provider_error) => println!(
+                "ERROR: Unable to resolve a provided value. Reason: {:?}",
+                provider_error
+            ),
+            tmptoml::TmpTomlErr::Output(reason) => println!(
+                "ERROR: Unable to compress or write the rendered output. Reason: {:?}",
+                reason
+            ),
+            tmptoml::TmpTomlErr::DeniedWarnings(warnings) => {
+                println!(
+                    "ERROR: Rendering produced {} warning(s) while --deny-warnings is set.",
+                    warnings.len()
+                );
+                std::process::exit(1);
+            }
+            tmptoml::TmpTomlErr::DeniedCoercions(coercions) => {
+                println!(
+                    "ERROR: {} value(s) were coerced to another type while --deny-coercions is set.",
+                    coercions.len()
+                );
+                std::process::exit(1);
+            }
         },
     };
 }
@@ -0,0 +1,271 @@
+// Manifests of the files a batch generated, so that the files a later batch
+// no longer generates can be cleaned up instead of lingering on hosts, and
+// the files whose config or templates changed since can be found.
+
+use crate::{batch::Batch, tmptoml::TmpTomlErr};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum ManifestErr {
+    Read(String),
+    Invalid(String),
+    Write(String),
+}
+
+impl From<ManifestErr> for TmpTomlErr {
+    fn from(err: ManifestErr) -> Self {
+        TmpTomlErr::Manifest(err)
+    }
+}
+
+/// A file and the SHA-256 of its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHash {
+    pub path: String,
+    pub hash: String,
+}
+
+/// A generated file and the SHA-256 of the content it was generated with,
+/// along with the hashes of the config and template files it was rendered
+/// from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub hash: String,
+    pub inputs: Vec<FileHash>,
+}
+
+/// The files the last batch generated, along with the files earlier batches
+/// generated that it no longer does.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub files: Vec<ManifestEntry>,
+    pub stale: Vec<ManifestEntry>,
+}
+
+/// What `clean_manifest` did with a stale file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cleaned {
+    Removed(String),
+    /// The file changed since it was generated, so it was kept.
+    Modified(String),
+    /// The file had already been removed.
+    Missing(String),
+}
+
+/// A generated file that needs to be rendered again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outdated {
+    /// The file was removed since it was generated.
+    OutputMissing(String),
+    /// A config or template file it was rendered from changed or was
+    /// removed.
+    InputChanged { output: String, input: String },
+}
+
+pub(crate) fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+fn file_hash_to_json(file: &FileHash) -> Value {
+    json!({ "path": file.path, "hash": file.hash })
+}
+
+fn entries_to_json(entries: &[ManifestEntry]) -> Value {
+    entries
+        .iter()
+        .map(|entry| {
+            let mut value = file_hash_to_json(&FileHash {
+                path: entry.path.clone(),
+                hash: entry.hash.clone(),
+            });
+            value["inputs"] = entry.inputs.iter().map(file_hash_to_json).collect();
+            value
+        })
+        .collect()
+}
+
+fn list_from_json<'a>(value: &'a Value, key: &str) -> Result<&'a [Value], ManifestErr> {
+    match value.get(key) {
+        Some(Value::Array(entries)) => Ok(entries),
+        None => Ok(&[]),
+        Some(_) => Err(ManifestErr::Invalid(format!("`{}` is not a list", key))),
+    }
+}
+
+fn file_hash_from_json(value: &Value, key: &str) -> Result<FileHash, ManifestErr> {
+    match (value.get("path"), value.get("hash")) {
+        (Some(Value::String(path)), Some(Value::String(hash))) => Ok(FileHash {
+            path: path.clone(),
+            hash: hash.clone(),
+        }),
+        _ => Err(ManifestErr::Invalid(format!(
+            "`{}` entries need a path and a hash",
+            key
+        ))),
+    }
+}
+
+fn entries_from_json(value: &Value, key: &str) -> Result<Vec<ManifestEntry>, ManifestErr> {
+    list_from_json(value, key)?
+        .iter()
+        .map(|entry| {
+            let file = file_hash_from_json(entry, key)?;
+            let inputs = list_from_json(entry, "inputs")?
+                .iter()
+                .map(|input| file_hash_from_json(input, "inputs"))
+                .collect::<Result<_, _>>()?;
+            Ok(ManifestEntry {
+                path: file.path,
+                hash: file.hash,
+                inputs,
+            })
+        })
+        .collect()
+}
+
+fn hash_file(path: &Path) -> Result<String, ManifestErr> {
+    let content =
+        fs::read(path).map_err(|err| ManifestErr::Read(format!("{}: {}", path.display(), err)))?;
+    Ok(content_hash(&content))
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "files": entries_to_json(&self.files),
+            "stale": entries_to_json(&self.stale),
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Manifest, ManifestErr> {
+        Ok(Manifest {
+            files: entries_from_json(value, "files")?,
+            stale: entries_from_json(value, "stale")?,
+        })
+    }
+
+    /// Reads a manifest, a missing file being an empty manifest.
+    pub fn read(path: &Path) -> Result<Manifest, ManifestErr> {
+        if !path.exists() {
+            return Ok(Manifest::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|err| ManifestErr::Read(format!("{}: {}", path.display(), err)))?;
+        let value: Value = serde_json::from_str(&content)
+            .map_err(|err| ManifestErr::Invalid(format!("{}: {}", path.display(), err)))?;
+        Manifest::from_json(&value)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ManifestErr> {
+        let content = serde_json::to_string_pretty(&self.to_json())
+            .map_err(|err| ManifestErr::Write(err.to_string()))?;
+        fs::write(path, content)
+            .map_err(|err| ManifestErr::Write(format!("{}: {}", path.display(), err)))
+    }
+
+    /// Builds the manifest of the files written by `batch`, the files of
+    /// `previous` it no longer generates becoming stale. Files that failed
+    /// to render keep their previous entry.
+    pub fn from_batch(batch: &Batch, previous: &Manifest) -> Result<Manifest, ManifestErr> {
+        let mut input_hashes: BTreeMap<&PathBuf, String> = BTreeMap::new();
+        let mut files = Vec::new();
+        for output in &batch.outputs {
+            let mut inputs = Vec::new();
+            for input in &output.inputs {
+                if !input_hashes.contains_key(input) {
+                    input_hashes.insert(input, hash_file(input)?);
+                }
+                inputs.push(FileHash {
+                    path: input.display().to_string(),
+                    hash: input_hashes[input].clone(),
+                });
+            }
+            files.push(ManifestEntry {
+                path: output.path.display().to_string(),
+                hash: hash_file(&output.path)?,
+                inputs,
+            });
+        }
+        files.extend(
+            previous
+                .files
+                .iter()
+                .filter(|entry| {
+                    batch
+                        .failures
+                        .iter()
+                        .any(|failure| failure.path.display().to_string() == entry.path)
+                })
+                .cloned(),
+        );
+        let stale = previous
+            .files
+            .iter()
+            .chain(&previous.stale)
+            .filter(|entry| !files.iter().any(|file| file.path == entry.path))
+            .cloned()
+            .collect();
+        Ok(Manifest { files, stale })
+    }
+}
+
+/// Removes the stale files of the manifest at `path`, relative paths being
+/// relative to the current directory. Files changed since they were
+/// generated are kept and stay listed as stale, the others are dropped from
+/// the manifest.
+pub fn clean_manifest(path: &Path) -> Result<Vec<Cleaned>, TmpTomlErr> {
+    let mut manifest = Manifest::read(path)?;
+    let mut cleaned = Vec::new();
+    let mut kept = Vec::new();
+    for entry in manifest.stale {
+        let content = match fs::read(&entry.path) {
+            Ok(content) => content,
+            Err(_) => {
+                cleaned.push(Cleaned::Missing(entry.path));
+                continue;
+            }
+        };
+        if content_hash(&content) != entry.hash {
+            cleaned.push(Cleaned::Modified(entry.path.clone()));
+            kept.push(entry);
+            continue;
+        }
+        fs::remove_file(&entry.path)
+            .map_err(|err| ManifestErr::Write(format!("{}: {}", entry.path, err)))?;
+        cleaned.push(Cleaned::Removed(entry.path));
+    }
+    manifest.stale = kept;
+    manifest.write(path)?;
+    Ok(cleaned)
+}
+
+/// Lists the generated files of the manifest at `path` that are missing or
+/// whose config or template files changed since they were generated, in the
+/// order of the manifest.
+pub fn outdated_outputs(path: &Path) -> Result<Vec<Outdated>, TmpTomlErr> {
+    let manifest = Manifest::read(path)?;
+    let mut outdated = Vec::new();
+    for entry in manifest.files {
+        if !Path::new(&entry.path).exists() {
+            outdated.push(Outdated::OutputMissing(entry.path));
+            continue;
+        }
+        let changed = entry.inputs.into_iter().find(|input| {
+            hash_file(Path::new(&input.path)).map_or(true, |hash| hash != input.hash)
+        });
+        if let Some(input) = changed {
+            outdated.push(Outdated::InputChanged {
+                output: entry.path,
+                input: input.path,
+            });
+        }
+    }
+    Ok(outdated)
+}
@@ -0,0 +1,161 @@
+// Tera functions deriving addresses from IP networks, so that network
+// configuration templates (dnsmasq, WireGuard, HAProxy) can compute their
+// addresses from a single CIDR value in the config. IPv4 and IPv6 are both
+// supported.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+use tera::{Tera, Value};
+
+pub(crate) fn register_network_functions(tera: &mut Tera) {
+    tera.register_function("cidr_host", cidr_host);
+    tera.register_function("cidr_netmask", cidr_netmask);
+    tera.register_function("ip_add", ip_add);
+}
+
+fn string_arg<'a>(
+    function: &str,
+    args: &'a HashMap<String, Value>,
+    name: &str,
+) -> tera::Result<&'a str> {
+    match args.get(name) {
+        Some(Value::String(value)) => Ok(value),
+        Some(other) => Err(tera::Error::msg(format!(
+            "Function `{}` expected the `{}` argument to be a string but got {}",
+            function, name, other
+        ))),
+        None => Err(tera::Error::msg(format!(
+            "Function `{}` requires the `{}` argument",
+            function, name
+        ))),
+    }
+}
+
+fn integer_arg(function: &str, args: &HashMap<String, Value>, name: &str) -> tera::Result<i128> {
+    match args.get(name) {
+        Some(Value::Number(number)) if number.is_i64() || number.is_u64() => Ok(number
+            .as_i64()
+            .map(i128::from)
+            .unwrap_or_else(|| number.as_u64().map(i128::from).unwrap_or_default())),
+        Some(other) => Err(tera::Error::msg(format!(
+            "Function `{}` expected the `{}` argument to be an integer but got {}",
+            function, name, other
+        ))),
+        None => Err(tera::Error::msg(format!(
+            "Function `{}` requires the `{}` argument",
+            function, name
+        ))),
+    }
+}
+
+// An address as a number along with its width in bits, so that IPv4 and IPv6
+// share the arithmetic.
+fn to_number(ip: IpAddr) -> (u128, u32) {
+    match ip {
+        IpAddr::V4(ip) => (u128::from(u32::from(ip)), 32),
+        IpAddr::V6(ip) => (u128::from(ip), 128),
+    }
+}
+
+fn from_number(number: u128, bits: u32) -> IpAddr {
+    match bits {
+        32 => IpAddr::V4(Ipv4Addr::from(number as u32)),
+        _ => IpAddr::V6(Ipv6Addr::from(number)),
+    }
+}
+
+fn parse_ip(function: &str, ip: &str) -> tera::Result<IpAddr> {
+    ip.trim().parse().map_err(|_| {
+        tera::Error::msg(format!(
+            "Function `{}` received an invalid IP address: {}",
+            function, ip
+        ))
+    })
+}
+
+/// Parses `10.0.0.0/24` into its first address as a number, its width in
+/// bits and its prefix length. Host bits set in the address are ignored.
+fn parse_cidr(function: &str, net: &str) -> tera::Result<(u128, u32, u32)> {
+    let invalid = || {
+        tera::Error::msg(format!(
+            "Function `{}` received an invalid CIDR network: {}",
+            function, net
+        ))
+    };
+    let (ip, prefix) = net.trim().split_once('/').ok_or_else(invalid)?;
+    let (number, bits) = to_number(ip.parse().map_err(|_| invalid())?);
+    let prefix: u32 = prefix.parse().map_err(|_| invalid())?;
+    if prefix > bits {
+        return Err(invalid());
+    }
+    Ok((number & !host_mask(bits, prefix), bits, prefix))
+}
+
+fn host_mask(bits: u32, prefix: u32) -> u128 {
+    match bits - prefix {
+        128 => u128::MAX,
+        host_bits => (1u128 << host_bits) - 1,
+    }
+}
+
+/// `cidr_host(net="10.0.0.0/24", n=5)` returns `10.0.0.5`, the n-th address
+/// of the network. Negative numbers count back from the end of the network,
+/// `-1` being its last address.
+fn cidr_host(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let net = string_arg("cidr_host", args, "net")?;
+    let n = integer_arg("cidr_host", args, "n")?;
+    let (network, bits, prefix) = parse_cidr("cidr_host", net)?;
+    let size = host_mask(bits, prefix);
+    let offset = if n < 0 {
+        size.checked_sub(n.unsigned_abs() - 1)
+    } else {
+        Some(n as u128).filter(|offset| *offset <= size)
+    };
+    let offset = offset.ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Function `cidr_host` received host number {} outside of {}",
+            n, net
+        ))
+    })?;
+    Ok(Value::String(
+        from_number(network + offset, bits).to_string(),
+    ))
+}
+
+/// `cidr_netmask(net="10.0.0.0/20")` returns `255.255.240.0`. IPv6 networks
+/// have no netmask notation and are refused.
+fn cidr_netmask(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let net = string_arg("cidr_netmask", args, "net")?;
+    let (_, bits, prefix) = parse_cidr("cidr_netmask", net)?;
+    if bits != 32 {
+        return Err(tera::Error::msg(format!(
+            "Function `cidr_netmask` only supports IPv4 networks but got {}",
+            net
+        )));
+    }
+    let mask = u32::MAX as u128 & !host_mask(bits, prefix);
+    Ok(Value::String(from_number(mask, bits).to_string()))
+}
+
+/// `ip_add(ip="10.0.0.1", n=10)` returns `10.0.0.11`. `n` can be negative,
+/// and addresses past the end or the start of the address space are refused.
+fn ip_add(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let ip = string_arg("ip_add", args, "ip")?;
+    let n = integer_arg("ip_add", args, "n")?;
+    let (number, bits) = to_number(parse_ip("ip_add", ip)?);
+    let added = if n < 0 {
+        number.checked_sub(n.unsigned_abs())
+    } else {
+        number.checked_add(n as u128)
+    }
+    .filter(|added| bits == 128 || *added <= u32::MAX as u128)
+    .ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Function `ip_add` went outside of the address space adding {} to {}",
+            n, ip
+        ))
+    })?;
+    Ok(Value::String(from_number(added, bits).to_string()))
+}
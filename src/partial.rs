@@ -0,0 +1,170 @@
+// Partial rendering: only some variables are substituted while every other
+// `{{ }}` expression is re-emitted untouched for a later rendering pass.
+
+use crate::{analysis::expression_variables, tmptoml::TeraRenderErr};
+use std::collections::BTreeSet;
+use tera::{ast::Node, Template};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deferral {
+    /// Substitute only expressions whose variables are all listed.
+    Only(Vec<String>),
+    /// Substitute only expressions whose variables are all in the context.
+    Unknown,
+}
+
+// Finds the end of a tag opened at `start`, skipping quoted strings so a
+// closing delimiter inside a string literal doesn't end the tag.
+pub(crate) fn find_tag_end(source: &str, start: usize, closing: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut i = start + 2;
+    while i < bytes.len() {
+        match quote {
+            Some(q) if bytes[i] == q => quote = None,
+            Some(_) => {}
+            None if matches!(bytes[i], b'"' | b'\'' | b'`') => quote = Some(bytes[i]),
+            None if source[i..].starts_with(closing) => return Some(i + closing.len()),
+            None => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+pub(crate) fn tag_content(tag: &str) -> &str {
+    tag[2..tag.len() - 2]
+        .trim_start_matches('-')
+        .trim_end_matches('-')
+        .trim()
+}
+
+fn identifiers(list: &str) -> impl Iterator<Item = String> + '_ {
+    list.split(',')
+        .map(|name| {
+            name.split('=')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+        .filter(|name| !name.is_empty())
+}
+
+fn invalid_template(err: impl std::fmt::Display) -> TeraRenderErr {
+    TeraRenderErr::InvalidTemplate(format!("Failed to parse template file with error: {}", err))
+}
+
+/// Rewrites the template so that every `{{ }}` expression reading a variable
+/// for which `is_substituted` returns false is wrapped in a raw block, making
+/// Tera emit it verbatim. Variables bound by the template itself (loop
+/// variables, `set` and macro arguments) are always substituted. Statements
+/// (`{% %}`) are always evaluated.
+pub(crate) fn defer_expressions(
+    source: &str,
+    is_substituted: &dyn Fn(&str) -> bool,
+) -> Result<String, TeraRenderErr> {
+    let mut output = String::with_capacity(source.len());
+    let mut scopes: Vec<BTreeSet<String>> = vec![BTreeSet::new()];
+    let mut position = 0;
+
+    while let Some(offset) = source[position..].find('{') {
+        let start = position + offset;
+        let closing = match source.as_bytes().get(start + 1) {
+            Some(b'{') => "}}",
+            Some(b'%') => "%}",
+            Some(b'#') => "#}",
+            _ => {
+                output.push_str(&source[position..start + 1]);
+                position = start + 1;
+                continue;
+            }
+        };
+        output.push_str(&source[position..start]);
+        let end = find_tag_end(source, start, closing)
+            .ok_or_else(|| invalid_template(format!("unclosed tag at byte {}", start)))?;
+        let tag = &source[start..end];
+        position = end;
+
+        match closing {
+            "}}" => {
+                let template = Template::new("expression", None, tag).map_err(invalid_template)?;
+                let mut variables = BTreeSet::new();
+                if let Some(Node::VariableBlock(_, expr)) = template.ast.first() {
+                    expression_variables(expr, &mut variables);
+                }
+                let deferred = variables.iter().any(|variable| {
+                    !scopes.iter().any(|scope| scope.contains(variable))
+                        && !is_substituted(variable)
+                });
+                if deferred {
+                    output.push_str("{% raw %}");
+                    output.push_str(tag);
+                    output.push_str("{% endraw %}");
+                } else {
+                    output.push_str(tag);
+                }
+            }
+            "%}" => {
+                let content = tag_content(tag);
+                let mut words = content.split_whitespace();
+                match words.next() {
+                    Some("raw") => {
+                        // Copy the raw block verbatim up to and including its
+                        // endraw tag.
+                        let mut raw_end = None;
+                        let mut search = end;
+                        while let Some(offset) = source[search..].find("{%") {
+                            let tag_start = search + offset;
+                            let tag_end = find_tag_end(source, tag_start, "%}")
+                                .ok_or_else(|| invalid_template("unclosed raw block"))?;
+                            if tag_content(&source[tag_start..tag_end]) == "endraw" {
+                                raw_end = Some(tag_end);
+                                break;
+                            }
+                            search = tag_end;
+                        }
+                        let raw_end =
+                            raw_end.ok_or_else(|| invalid_template("unclosed raw block"))?;
+                        output.push_str(&source[start..raw_end]);
+                        position = raw_end;
+                        continue;
+                    }
+                    Some("for") => {
+                        let names = content["for".len()..]
+                            .split(" in ")
+                            .next()
+                            .unwrap_or_default();
+                        let mut scope: BTreeSet<String> = identifiers(names).collect();
+                        scope.insert("loop".to_string());
+                        scopes.push(scope);
+                    }
+                    Some("macro") => {
+                        let args = content
+                            .split_once('(')
+                            .and_then(|(_, rest)| rest.rsplit_once(')'))
+                            .map(|(args, _)| args)
+                            .unwrap_or_default();
+                        scopes.push(identifiers(args).collect());
+                    }
+                    Some("endfor") | Some("endmacro") if scopes.len() > 1 => {
+                        scopes.pop();
+                    }
+                    Some("set") | Some("set_global") => {
+                        if let Some(name) = words.next() {
+                            let name = name.split('=').next().unwrap_or_default().to_string();
+                            if let Some(scope) = scopes.last_mut() {
+                                scope.insert(name);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                output.push_str(tag);
+            }
+            _ => output.push_str(tag),
+        }
+    }
+    output.push_str(&source[position..]);
+    Ok(output)
+}
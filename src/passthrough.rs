@@ -0,0 +1,82 @@
+// Marker regions are cut out of the template before Tera sees it and put
+// back after rendering, so their content is emitted byte-for-byte whatever
+// template syntax it contains.
+
+use crate::tmptoml::TeraRenderErr;
+
+/// A pair of markers delimiting a region that is emitted verbatim, markers
+/// included, e.g. `# tmptoml:off` and `# tmptoml:on`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassthroughMarkers {
+    pub begin: String,
+    pub end: String,
+}
+
+fn placeholder(index: usize) -> String {
+    format!("\u{1}tmptoml-passthrough-{}\u{1}", index)
+}
+
+/// Replaces every marker region of the template with a placeholder, returning
+/// the template and the regions in placeholder order.
+pub(crate) fn extract_regions(
+    source: &str,
+    markers: &[PassthroughMarkers],
+) -> Result<(String, Vec<String>), TeraRenderErr> {
+    let mut regions = Vec::new();
+    let output = extract_regions_into(source, markers, &mut regions)?;
+    Ok((output, regions))
+}
+
+/// Like `extract_regions`, appending to `regions` so that the placeholders of
+/// several templates don't collide.
+pub(crate) fn extract_regions_into(
+    source: &str,
+    markers: &[PassthroughMarkers],
+    regions: &mut Vec<String>,
+) -> Result<String, TeraRenderErr> {
+    let mut output = String::with_capacity(source.len());
+    let mut position = 0;
+
+    loop {
+        let next_region = markers
+            .iter()
+            .filter(|marker| !marker.begin.is_empty())
+            .filter_map(|marker| {
+                source[position..]
+                    .find(&marker.begin)
+                    .map(|offset| (position + offset, marker))
+            })
+            .min_by_key(|(start, _)| *start);
+        let (start, marker) = match next_region {
+            Some(next_region) => next_region,
+            None => break,
+        };
+        let content_start = start + marker.begin.len();
+        let end = source[content_start..]
+            .find(&marker.end)
+            .map(|offset| content_start + offset + marker.end.len())
+            .ok_or_else(|| {
+                TeraRenderErr::InvalidTemplate(format!(
+                    "Passthrough region opened by {:?} is never closed by {:?}",
+                    marker.begin, marker.end
+                ))
+            })?;
+        output.push_str(&source[position..start]);
+        output.push_str(&placeholder(regions.len()));
+        regions.push(source[start..end].to_string());
+        position = end;
+    }
+    output.push_str(&source[position..]);
+    Ok(output)
+}
+
+/// Puts the regions taken out by `extract_regions` back into the rendered
+/// template.
+pub(crate) fn restore_regions(rendered: String, regions: &[String]) -> String {
+    regions
+        .iter()
+        .enumerate()
+        .fold(rendered, |rendered, (index, region)| {
+            rendered.replace(&placeholder(index), region)
+        })
+}
@@ -0,0 +1,317 @@
+// Planning of a batch: parsing and linting the config, compiling the
+// templates, checking their variables against every selected group and
+// diffing what each file would be rendered as against the output directory,
+// all in one pass and without writing anything, so that operators get a
+// single gate before running the batch.
+
+use crate::{
+    batch::{output_path, render_file, BatchFailure, BatchOptions},
+    compression::encode_output,
+    inspect::{inspect_template, ItemKind},
+    lint::{lint_config, LintFinding},
+    renderer::{LoadedConfig, Renderer},
+    tmptoml::{
+        build_template_values, parse_toml_to_config, resolve_group_paths_with_options, GroupPath,
+        RenderOptions, TmpTomlErr,
+    },
+    warnings::{skipped_table_notices, Notice},
+};
+use similar::{ChangeTag, TextDiff};
+use std::{
+    collections::BTreeSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+// Lines of context around the changed lines of a diff.
+const DIFF_CONTEXT: usize = 3;
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedAction {
+    /// The file doesn't exist yet.
+    Add,
+    /// The file exists with other content.
+    Change,
+    Unchanged,
+}
+
+/// A file the batch would write and what writing it would do. `diff` is a
+/// unified diff of the change, empty unless the file changes or when either
+/// content isn't text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedFile {
+    pub group_path: GroupPath,
+    pub template: String,
+    pub path: PathBuf,
+    pub action: PlannedAction,
+    pub diff: String,
+}
+
+/// A variable a template reads that the context of a group doesn't define.
+/// Variables only tested with `is defined` are listed too, so these don't
+/// fail a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndefinedVariable {
+    pub group_path: GroupPath,
+    pub template: String,
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub struct Plan {
+    pub files: Vec<PlannedFile>,
+    /// The files that would fail to render.
+    pub failures: Vec<BatchFailure>,
+    pub undefined: Vec<UndefinedVariable>,
+    pub findings: Vec<LintFinding>,
+    pub notices: Vec<Notice>,
+}
+
+/// Plans rendering every template of `template_dir` that the filter doesn't
+/// exclude for every group path into `output_dir`, like `render_batch`.
+/// Invalid configs and templates fail the plan, files that fail to render are
+/// recorded as failures. Nothing is written.
+pub fn plan_batch(
+    config_file_path: &Path,
+    template_dir: &Path,
+    output_dir: &Path,
+    group_paths: &[GroupPath],
+    batch_options: &BatchOptions,
+    options: RenderOptions,
+) -> Result<Plan, TmpTomlErr> {
+    let toml_config = parse_toml_to_config(config_file_path.to_str())?;
+    let findings = lint_config(&toml_config, &[]);
+    let config = Arc::new(LoadedConfig::new(toml_config));
+    let renderer = Arc::new(Renderer::from_dir(
+        template_dir,
+        &batch_options.filter,
+        options,
+    )?);
+    let notices = skipped_table_notices(
+        config.config(),
+        group_paths,
+        renderer.options().include_siblings,
+    );
+
+    let mut plan = Plan {
+        files: Vec::new(),
+        failures: Vec::new(),
+        undefined: Vec::new(),
+        findings,
+        notices,
+    };
+    for group_path in group_paths {
+        // A group whose context can't be built fails every render, which
+        // reports why.
+        let defined = resolve_group_paths_with_options(
+            config.config(),
+            std::slice::from_ref(group_path),
+            renderer.options(),
+        )
+        .and_then(|group_values| build_template_values(&group_values, renderer.options()))
+        .map(|template_values| {
+            template_values
+                .keys()
+                .cloned()
+                .collect::<BTreeSet<String>>()
+        });
+
+        for template in renderer.dependency_order() {
+            if let (Ok(defined), Some(source)) = (&defined, renderer.source(template)) {
+                plan.undefined
+                    .extend(undefined_variables(source, defined)?.into_iter().map(
+                        |(name, line, column)| UndefinedVariable {
+                            group_path: group_path.clone(),
+                            template: template.clone(),
+                            name,
+                            line,
+                            column,
+                        },
+                    ));
+            }
+
+            let path = output_path(output_dir, group_path, template, batch_options.compression);
+            let rendered = render_file(
+                &renderer,
+                &config,
+                template,
+                group_path,
+                batch_options.timeout_per_file,
+            )
+            .and_then(|rendered| {
+                encode_output(
+                    rendered.into_bytes(),
+                    batch_options.compression,
+                    batch_options.encoding,
+                )
+            });
+            match rendered {
+                Ok(rendered) => {
+                    let (action, diff) = planned_change(&path, &rendered);
+                    plan.files.push(PlannedFile {
+                        group_path: group_path.clone(),
+                        template: template.clone(),
+                        path,
+                        action,
+                        diff,
+                    })
+                }
+                Err(error) => plan.failures.push(BatchFailure {
+                    group_path: group_path.clone(),
+                    template: template.clone(),
+                    path,
+                    error,
+                }),
+            }
+        }
+    }
+    Ok(plan)
+}
+
+// The first use of every variable the template reads but neither binds nor
+// finds in the context, with its line and column.
+fn undefined_variables(
+    source: &str,
+    defined: &BTreeSet<String>,
+) -> Result<Vec<(String, usize, usize)>, TmpTomlErr> {
+    let mut seen = BTreeSet::new();
+    Ok(inspect_template(source)?
+        .into_iter()
+        .filter(|item| item.kind == ItemKind::Variable && !item.local)
+        .filter_map(|item| {
+            let root = item.name.split('.').next().unwrap_or_default().to_string();
+            (!defined.contains(&root) && seen.insert(root.clone())).then_some((
+                root,
+                item.line,
+                item.column,
+            ))
+        })
+        .collect())
+}
+
+// Compressed or encoded files aren't text, so their changes aren't diffed.
+fn planned_change(path: &Path, rendered: &[u8]) -> (PlannedAction, String) {
+    let existing = match fs::read(path) {
+        Ok(existing) if existing == rendered => return (PlannedAction::Unchanged, String::new()),
+        Ok(existing) => existing,
+        Err(_) => return (PlannedAction::Add, String::new()),
+    };
+    let diff = match (
+        std::str::from_utf8(&existing),
+        std::str::from_utf8(rendered),
+    ) {
+        (Ok(existing), Ok(rendered)) => unified_diff(path, existing, rendered),
+        _ => String::new(),
+    };
+    (PlannedAction::Change, diff)
+}
+
+/// A unified diff of two texts with three lines of context. The changed
+/// lines are found with Myers' algorithm in linear space, approximated when
+/// a diff takes longer than `DIFF_TIMEOUT`.
+pub fn unified_diff(path: &Path, original: &str, changed: &str) -> String {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(original, changed);
+    let mut lines = vec![
+        format!("--- {}", path.display()),
+        format!("+++ {}", path.display()),
+    ];
+    for hunk in diff.grouped_ops(DIFF_CONTEXT) {
+        let (first, last) = match (hunk.first(), hunk.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+        let old = first.old_range().start..last.old_range().end;
+        let new = first.new_range().start..last.new_range().end;
+        // An empty range starts at the line before it.
+        let position = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            position(old.start, old.len()),
+            old.len(),
+            position(new.start, new.len()),
+            new.len()
+        ));
+        for change in hunk.iter().flat_map(|op| diff.iter_changes(op)) {
+            let marker = match change.tag() {
+                ChangeTag::Equal => ' ',
+                ChangeTag::Delete => '-',
+                ChangeTag::Insert => '+',
+            };
+            let line = change.value();
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            lines.push(format!("{}{}", marker, line));
+        }
+    }
+    lines.join("\n")
+}
+
+impl Plan {
+    pub fn count(&self, action: PlannedAction) -> usize {
+        self.files
+            .iter()
+            .filter(|file| file.action == action)
+            .count()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = Vec::new();
+        if !self.findings.is_empty() {
+            lines.push("Config findings:".to_string());
+            for finding in &self.findings {
+                lines.push(format!(
+                    "  {}: {} [{}]",
+                    finding.path, finding.message, finding.kind
+                ));
+            }
+        }
+        if !self.undefined.is_empty() {
+            lines.push("Undefined variables:".to_string());
+            for variable in &self.undefined {
+                lines.push(format!(
+                    "  {} for {}:{}:{}: {}",
+                    variable.template,
+                    variable.group_path.join("."),
+                    variable.line,
+                    variable.column,
+                    variable.name
+                ));
+            }
+        }
+        for file in &self.files {
+            match file.action {
+                PlannedAction::Add => lines.push(format!("+ {}", file.path.display())),
+                PlannedAction::Change => {
+                    lines.push(format!("~ {}", file.path.display()));
+                    if !file.diff.is_empty() {
+                        lines.push(file.diff.clone());
+                    }
+                }
+                PlannedAction::Unchanged => {}
+            }
+        }
+        for failure in &self.failures {
+            lines.push(format!("! {}: {:?}", failure.path.display(), failure.error));
+        }
+        let mut summary = format!(
+            "Plan: {} to add, {} to change, {} unchanged",
+            self.count(PlannedAction::Add),
+            self.count(PlannedAction::Change),
+            self.count(PlannedAction::Unchanged)
+        );
+        if !self.failures.is_empty() {
+            summary.push_str(&format!(", {} failing", self.failures.len()));
+        }
+        lines.push(format!("{}.", summary));
+        write!(f, "{}", lines.join("\n"))
+    }
+}
@@ -0,0 +1,34 @@
+// Which layer wins when merging puts two values at the same key: the levels
+// of a group path, the group paths selected with `-s` and the context values
+// merged over them. Tools disagree on it, so teams migrating to tmptoml can
+// keep the order they are used to.
+
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precedence {
+    /// The value of the earliest layer is kept, later layers only add keys.
+    FirstWins,
+    /// The value of the latest layer replaces the earlier ones.
+    #[default]
+    LastWins,
+}
+
+impl FromStr for Precedence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-wins" => Ok(Precedence::FirstWins),
+            "last-wins" => Ok(Precedence::LastWins),
+            other => Err(format!("unsupported precedence: {}", other)),
+        }
+    }
+}
+
+impl Precedence {
+    /// Whether a value already merged is kept over a later one.
+    pub(crate) fn keeps_existing(self) -> bool {
+        self == Precedence::FirstWins
+    }
+}
@@ -0,0 +1,113 @@
+// Values provided by the embedder rather than written in the config, e.g.
+// secrets read from a secret store. A config value like
+// `"vault://secret/db#password"` is replaced, once the group values are
+// resolved, by what the provider registered for `vault://` resolves it to.
+
+use crate::tmptoml::{Group, TmpTomlErr};
+use std::{fmt, sync::Arc};
+
+/// Resolves the config values starting with its prefix.
+pub trait ValueProvider: Send + Sync {
+    /// The prefix of the values the provider resolves, e.g. `vault://`.
+    fn prefix(&self) -> &str;
+
+    /// Resolves a reference, the value without the provider's prefix, e.g.
+    /// `secret/db#password`.
+    fn resolve(&self, reference: &str) -> Result<toml::Value, String>;
+}
+
+#[derive(Debug)]
+pub struct ProviderErr {
+    /// The value that failed to resolve, prefix included.
+    pub reference: String,
+    pub reason: String,
+}
+
+impl From<ProviderErr> for TmpTomlErr {
+    fn from(err: ProviderErr) -> Self {
+        TmpTomlErr::Provider(err)
+    }
+}
+
+/// The providers consulted for the values of the groups a template is
+/// rendered with. The first provider whose prefix a value starts with
+/// resolves it, values no provider's prefix matches are kept as written.
+#[derive(Clone, Default)]
+pub struct ValueProviders {
+    providers: Vec<Arc<dyn ValueProvider>>,
+}
+
+impl fmt::Debug for ValueProviders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.providers.iter().map(|provider| provider.prefix()))
+            .finish()
+    }
+}
+
+impl ValueProviders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, provider: impl ValueProvider + 'static) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    fn provide_value(
+        &self,
+        key: &str,
+        value: &mut toml::Value,
+        provided: &mut Vec<String>,
+    ) -> Result<(), ProviderErr> {
+        match value {
+            toml::Value::String(string) => {
+                let matched = self.providers.iter().find_map(|provider| {
+                    string
+                        .strip_prefix(provider.prefix())
+                        .map(|reference| (provider, reference))
+                });
+                if let Some((provider, reference)) = matched {
+                    let resolved = provider.resolve(reference).map_err(|reason| ProviderErr {
+                        reference: string.clone(),
+                        reason,
+                    })?;
+                    *value = resolved;
+                    provided.push(key.to_string());
+                }
+            }
+            toml::Value::Array(values) => {
+                for (index, value) in values.iter_mut().enumerate() {
+                    self.provide_value(&format!("{}[{}]", key, index), value, provided)?;
+                }
+            }
+            toml::Value::Table(table) => {
+                for (sub_key, value) in table.iter_mut() {
+                    self.provide_value(&format!("{}.{}", key, sub_key), value, provided)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Resolves the provided values of resolved group values in place,
+    /// returning the keys of the values replaced. Resolved values aren't
+    /// resolved again.
+    pub(crate) fn provide(&self, group_values: &mut Group) -> Result<Vec<String>, TmpTomlErr> {
+        let mut provided = Vec::new();
+        if self.is_empty() {
+            return Ok(provided);
+        }
+        for (key, value) in group_values.iter_mut() {
+            self.provide_value(key, value, &mut provided)?;
+        }
+        provided.sort();
+        Ok(provided)
+    }
+}
@@ -0,0 +1,88 @@
+// Pruning of the files an earlier batch generated that the current batch no
+// longer does, like `kubectl apply --prune` deletes the objects no longer in
+// the applied set. Only files the previous manifest lists are candidates, so
+// files kept by hand next to the generated ones are never touched.
+
+use crate::{batch::BatchErr, manifest::Manifest, tmptoml::TmpTomlErr};
+use ignore::overrides::OverrideBuilder;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn is_hidden(relative_path: &Path) -> bool {
+    relative_path.components().any(|component| match component {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+/// Removes the files `previous` lists that `current` doesn't, along with the
+/// directories left empty, returning the removed files sorted. Only files
+/// below `output_dir` are removed, never those in a hidden directory or
+/// matching an `allow` glob, relative to the output directory and in the
+/// `.gitignore` syntax.
+pub fn prune_output_dir(
+    output_dir: &Path,
+    previous: &Manifest,
+    current: &Manifest,
+    allow: &[String],
+) -> Result<Vec<PathBuf>, TmpTomlErr> {
+    if !output_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let invalid_pattern = |pattern: &str, err: ignore::Error| {
+        BatchErr::InvalidPattern(format!("{}: {}", pattern, err))
+    };
+    let mut allowed = OverrideBuilder::new(output_dir);
+    for pattern in allow {
+        allowed
+            .add(pattern)
+            .map_err(|err| invalid_pattern(pattern, err))?;
+    }
+    let allowed = allowed
+        .build()
+        .map_err(|err| invalid_pattern(&allow.join(", "), err))?;
+
+    let output_dir = canonical(output_dir);
+    let generated: BTreeSet<PathBuf> = current
+        .files
+        .iter()
+        .map(|entry| canonical(Path::new(&entry.path)))
+        .collect();
+    let mut removed = Vec::new();
+    for entry in &previous.files {
+        let path = Path::new(&entry.path);
+        if !path.is_file() {
+            continue;
+        }
+        let canonical_path = canonical(path);
+        let relative_path = match canonical_path.strip_prefix(&output_dir) {
+            Ok(relative_path) => relative_path,
+            Err(_) => continue,
+        };
+        if generated.contains(&canonical_path)
+            || is_hidden(relative_path)
+            || allowed.matched(&canonical_path, false).is_whitelist()
+        {
+            continue;
+        }
+        fs::remove_file(path)
+            .map_err(|err| BatchErr::Write(format!("{}: {}", path.display(), err)))?;
+        // Directories that aren't empty can't be removed, which stops the
+        // climb.
+        for dir in canonical_path.ancestors().skip(1) {
+            if dir == output_dir || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+        removed.push(path.to_path_buf());
+    }
+    removed.sort();
+    Ok(removed)
+}
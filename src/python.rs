@@ -0,0 +1,93 @@
+// Python bindings exposing the same rendering entry points as the CLI, so
+// Python tooling renders exactly what tmptoml renders.
+//
+//     import tmptoml
+//     tmptoml.render_template("config.toml", "template.yaml", ["qa", "system1"])
+//     tmptoml.render_str(config, template, ["qa", "system1"], context={"replicas": 3})
+
+use crate::{
+    context::{parse_context_json, ContextValues},
+    tmptoml::{self, RenderOptions, TmpTomlErr},
+};
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+use std::path::PathBuf;
+
+create_exception!(tmptoml, TmpTomlError, PyException);
+
+fn to_py_err(err: TmpTomlErr) -> PyErr {
+    TmpTomlError::new_err(format!("{:?}", err))
+}
+
+// Python values are passed through `json.dumps` so they are merged exactly
+// like `--context-json` values.
+fn context_values(
+    py: Python<'_>,
+    context: Option<&Bound<'_, PyAny>>,
+) -> PyResult<Vec<ContextValues>> {
+    match context {
+        Some(context) => {
+            let json: String = py
+                .import("json")?
+                .call_method1("dumps", (context,))?
+                .extract()?;
+            let values = parse_context_json(&json).map_err(|err| to_py_err(err.into()))?;
+            Ok(vec![values])
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+fn render_options(
+    py: Python<'_>,
+    groups: Vec<String>,
+    context: Option<&Bound<'_, PyAny>>,
+    locale: Option<String>,
+) -> PyResult<RenderOptions> {
+    Ok(RenderOptions {
+        group_paths: vec![groups],
+        context_values: context_values(py, context)?,
+        locale,
+        ..RenderOptions::default()
+    })
+}
+
+/// Renders the template file with the values of the group path (e.g.
+/// `["qa", "system1"]`) of the config file.
+#[pyfunction]
+#[pyo3(signature = (config, template, groups, context=None, locale=None))]
+fn render_template(
+    py: Python<'_>,
+    config: PathBuf,
+    template: PathBuf,
+    groups: Vec<String>,
+    context: Option<&Bound<'_, PyAny>>,
+    locale: Option<String>,
+) -> PyResult<String> {
+    let options = render_options(py, groups, context, locale)?;
+    tmptoml::render_template_with_options(&config, &template, &options).map_err(to_py_err)
+}
+
+/// Renders a template string with the values of the group path of a config
+/// string.
+#[pyfunction]
+#[pyo3(signature = (config, template, groups, context=None, locale=None))]
+fn render_str(
+    py: Python<'_>,
+    config: &str,
+    template: &str,
+    groups: Vec<String>,
+    context: Option<&Bound<'_, PyAny>>,
+    locale: Option<String>,
+) -> PyResult<String> {
+    let options = render_options(py, groups, context, locale)?;
+    tmptoml::render_str(config, template, &options).map_err(to_py_err)
+}
+
+#[pymodule]
+#[pyo3(name = "tmptoml")]
+fn tmptoml_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("TmpTomlError", m.py().get_type::<TmpTomlError>())?;
+    m.add_function(wrap_pyfunction!(render_template, m)?)?;
+    m.add_function(wrap_pyfunction!(render_str, m)?)?;
+    Ok(())
+}
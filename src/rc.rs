@@ -0,0 +1,100 @@
+// Defaults of the command line flags, read from a `.tmptomlrc` or
+// `tmptoml.rc.toml` file so that teams can standardize how tmptoml runs
+// without wrapping it in scripts. Flags are set in a table per subcommand,
+// named like their long flags:
+//
+//     [batch]
+//     output-dir = "out"
+//     exclude = ["*.md"]
+//     deny-warnings = true
+
+use crate::tmptoml::{read_file, TmpTomlErr};
+use std::path::{Path, PathBuf};
+
+/// The names of the defaults file, in order of preference.
+pub const RC_FILE_NAMES: [&str; 2] = [".tmptomlrc", "tmptoml.rc.toml"];
+
+#[derive(Debug)]
+pub enum RcErr {
+    /// The file isn't TOML tables of flag values, or the subcommand doesn't
+    /// take a flag it sets.
+    Invalid(String),
+}
+
+impl From<RcErr> for TmpTomlErr {
+    fn from(err: RcErr) -> Self {
+        TmpTomlErr::Rc(err)
+    }
+}
+
+/// Finds the defaults file of `dir`, or else of its nearest ancestor having
+/// one.
+pub fn find_rc_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        RC_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+fn flag_values(value: &toml::Value) -> Option<Vec<String>> {
+    match value {
+        toml::Value::String(string) => Some(vec![string.clone()]),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Datetime(_) => {
+            Some(vec![value.to_string()])
+        }
+        toml::Value::Array(values) => values
+            .iter()
+            .map(|value| match value {
+                toml::Value::Array(_) | toml::Value::Table(_) => None,
+                value => flag_values(value),
+            })
+            .collect::<Option<Vec<Vec<String>>>>()
+            .map(|values| values.concat()),
+        toml::Value::Boolean(_) | toml::Value::Table(_) => None,
+    }
+}
+
+/// Lists the flags the defaults file sets for `subcommand` as command line
+/// arguments, e.g. `["--locale=de-DE", "--deny-warnings"]`. Flags set to
+/// `true` are passed alone, those set to `false` not at all and arrays repeat
+/// the flag for each of their values.
+pub fn rc_arguments(rc_file_path: &Path, subcommand: &str) -> Result<Vec<String>, TmpTomlErr> {
+    let invalid =
+        |reason: String| RcErr::Invalid(format!("{}: {}", rc_file_path.display(), reason));
+    let rc: toml::value::Table = toml::from_str(&read_file(rc_file_path.to_str())?)
+        .map_err(|err| invalid(err.to_string()))?;
+    if let Some((key, _)) = rc.iter().find(|(_, value)| !value.is_table()) {
+        return Err(invalid(format!(
+            "{} isn't in the table of a subcommand, e.g. [render]",
+            key
+        ))
+        .into());
+    }
+    let flags = match rc.get(subcommand).and_then(|flags| flags.as_table()) {
+        Some(flags) => flags,
+        None => return Ok(Vec::new()),
+    };
+    let mut arguments = Vec::new();
+    for (flag, value) in flags {
+        let name = format!("--{}", flag);
+        match (value, flag_values(value)) {
+            (toml::Value::Boolean(true), _) => arguments.push(name),
+            (toml::Value::Boolean(false), _) => {}
+            (_, Some(values)) => {
+                for value in values {
+                    arguments.push(format!("{}={}", name, value));
+                }
+            }
+            (_, None) => {
+                return Err(invalid(format!(
+                    "{}.{} must be a string, number, boolean or array of them",
+                    subcommand, flag
+                ))
+                .into())
+            }
+        }
+    }
+    Ok(arguments)
+}
@@ -0,0 +1,670 @@
+// Renaming of a key across a config and the templates reading it. The config
+// is rewritten in place, keeping its formatting and comments, and checked to
+// parse as the same config with the key renamed. Templates are rewritten at
+// the positions `inspect` finds, so that variables the templates bind
+// themselves are left alone while paths through them are renamed.
+
+use crate::{
+    exclude::{list_template_files, TemplateFilter},
+    inspect::{inspect_template, string_literals, ItemKind, TemplateItem},
+    sensitive::{is_marked, SENSITIVE_TABLE},
+    tmptoml::{read_file, Config, TeraRenderErr, TmpTomlErr},
+    transaction::Transaction,
+};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum RenameErr {
+    /// A key isn't a name templates can read, e.g. contains a dot.
+    InvalidKey(String),
+    /// The new key is already in use where the old one is.
+    Conflict(String),
+    /// The config couldn't be rewritten into the same config with the key
+    /// renamed, or a template might read the key in a way that can't be
+    /// rewritten, so it has to be renamed by hand.
+    Unsupported(String),
+    Write(String),
+}
+
+impl From<RenameErr> for TmpTomlErr {
+    fn from(err: RenameErr) -> Self {
+        TmpTomlErr::Rename(err)
+    }
+}
+
+/// A file whose content changes with the rename.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamedFile {
+    pub path: PathBuf,
+    pub original: String,
+    pub renamed: String,
+}
+
+/// The files a rename changes, the config first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub files: Vec<RenamedFile>,
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Renames `old_key` to `new_key` in the config and the templates of
+/// `template_dir`, without writing anything. Keys of every table below the
+/// groups are renamed, as are the paths of `[_sensitive]` and the template
+/// variables reading them, directly or through a loop or `set` variable.
+pub fn rename_key(
+    config_file_path: &Path,
+    template_dir: &Path,
+    old_key: &str,
+    new_key: &str,
+    filter: &TemplateFilter,
+) -> Result<Rename, TmpTomlErr> {
+    for key in [old_key, new_key] {
+        if !is_identifier(key) {
+            return Err(RenameErr::InvalidKey(key.to_string()).into());
+        }
+    }
+    let mut files = Vec::new();
+    let config_source = read_file(config_file_path.to_str())?;
+    let renamed = rename_config_source(&config_source, old_key, new_key)?;
+    if renamed != config_source {
+        files.push(RenamedFile {
+            path: config_file_path.to_path_buf(),
+            original: config_source,
+            renamed,
+        });
+    }
+    for template_file_path in list_template_files(template_dir, filter)? {
+        let source = read_file(template_file_path.to_str()).map_err(TeraRenderErr::from)?;
+        let renamed =
+            rename_template_source(&source, old_key, new_key).map_err(|err| match err {
+                TmpTomlErr::Rename(RenameErr::Conflict(reason)) => {
+                    RenameErr::Conflict(format!("{}: {}", template_file_path.display(), reason))
+                        .into()
+                }
+                TmpTomlErr::Rename(RenameErr::Unsupported(reason)) => {
+                    RenameErr::Unsupported(format!("{}: {}", template_file_path.display(), reason))
+                        .into()
+                }
+                err => err,
+            })?;
+        if renamed != source {
+            files.push(RenamedFile {
+                path: template_file_path,
+                original: source,
+                renamed,
+            });
+        }
+    }
+    Ok(Rename { files })
+}
+
+impl Rename {
+    /// A unified diff of the changes, each changed line being a hunk of its
+    /// own as renames never add or remove lines.
+    pub fn diff(&self) -> String {
+        let mut lines = Vec::new();
+        for file in &self.files {
+            lines.push(format!("--- {}", file.path.display()));
+            lines.push(format!("+++ {}", file.path.display()));
+            for (index, (original, renamed)) in
+                file.original.lines().zip(file.renamed.lines()).enumerate()
+            {
+                if original != renamed {
+                    lines.push(format!("@@ -{} +{} @@", index + 1, index + 1));
+                    lines.push(format!("-{}", original));
+                    lines.push(format!("+{}", renamed));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Writes every renamed file, all of them or none.
+    pub fn write(&self) -> Result<(), TmpTomlErr> {
+        let mut transaction = Transaction::default();
+        for file in &self.files {
+            if let Err(err) = transaction.stage(&file.path, file.renamed.as_bytes()) {
+                transaction.rollback();
+                return Err(RenameErr::Write(format!("{:?}", err)).into());
+            }
+        }
+        transaction
+            .commit()
+            .map_err(|err| RenameErr::Write(format!("{:?}", err)).into())
+    }
+}
+
+// The end of the subscript opening at `start`, past its `]`.
+fn subscript_end(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0;
+    let mut position = start;
+    while let Some(&byte) = bytes.get(position) {
+        match byte {
+            b'"' | b'\'' | b'`' => {
+                let offset = source[position + 1..].find(byte as char)?;
+                position += offset + 1;
+            }
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position + 1);
+                }
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+    None
+}
+
+// The keys of the attributes and subscripts following a variable's path at
+// `position`, e.g. `host` of `servers[0].host` and of `x["host"]`, as the
+// span of the key and whether it's a string literal. Other subscripts are
+// indexes or expressions and read no key by name.
+fn accesses(source: &str, mut position: usize) -> Vec<(usize, usize, bool)> {
+    let bytes = source.as_bytes();
+    let mut keys = Vec::new();
+    loop {
+        match bytes.get(position) {
+            Some(b'.') => {
+                let start = position + 1;
+                let end = start
+                    + source[start..]
+                        .bytes()
+                        .take_while(|byte| byte.is_ascii_alphanumeric() || *byte == b'_')
+                        .count();
+                if end == start {
+                    break;
+                }
+                keys.push((start, end, false));
+                position = end;
+            }
+            Some(b'[') => {
+                let end = match subscript_end(source, position) {
+                    Some(end) => end,
+                    None => break,
+                };
+                let content = &source[position + 1..end - 1];
+                let start = position + 1 + content.len() - content.trim_start().len();
+                let subscript = content.trim();
+                if let Some(&quote @ (b'"' | b'\'' | b'`')) = subscript.as_bytes().first() {
+                    let key = &subscript[1..];
+                    if key.len() > 1 && key.find(quote as char) == Some(key.len() - 1) {
+                        keys.push((start + 1, start + key.len(), true));
+                    }
+                }
+                position = end;
+            }
+            _ => break,
+        }
+    }
+    keys
+}
+
+fn line_column(source: &str, position: usize) -> String {
+    let line_start = source[..position]
+        .rfind('\n')
+        .map_or(0, |offset| offset + 1);
+    format!(
+        "{}:{}",
+        source[..position].matches('\n').count() + 1,
+        source[line_start..position].chars().count() + 1
+    )
+}
+
+/// Renames the variables of a template reading `old_key`, leaving those the
+/// template binds itself. Attributes and string subscripts of any variable
+/// are renamed too, while other strings naming the key might be read as a
+/// key, e.g. by a subscript with a variable, and fail the rename.
+pub fn rename_template_source(
+    source: &str,
+    old_key: &str,
+    new_key: &str,
+) -> Result<String, TmpTomlErr> {
+    let items = inspect_template(source)?;
+    let mut replacements = Vec::new();
+    let mut renamed_roots = 0;
+    // The positions of the string subscripts, at their opening quote.
+    let mut subscripts = BTreeSet::new();
+    for item in items.iter().filter(|item| item.kind == ItemKind::Variable) {
+        let mut offset = item.start;
+        for (index, segment) in item.name.split('.').enumerate() {
+            if segment == old_key && !(index == 0 && item.local) {
+                replacements.push((offset, offset + segment.len(), new_key.to_string()));
+                renamed_roots += usize::from(index == 0);
+            }
+            offset += segment.len() + 1;
+        }
+        for (start, end, quoted) in accesses(source, item.end) {
+            if quoted {
+                subscripts.insert(start - 1);
+            }
+            if &source[start..end] == old_key {
+                replacements.push((start, end, new_key.to_string()));
+            }
+        }
+    }
+    let unrenamed: Vec<String> = string_literals(source)?
+        .into_iter()
+        .filter(|(start, end)| {
+            &source[start + 1..end - 1] == old_key && !subscripts.contains(start)
+        })
+        .map(|(start, _)| line_column(source, start))
+        .collect();
+    if !unrenamed.is_empty() {
+        return Err(RenameErr::Unsupported(format!(
+            "\"{}\" might be read as a key at {}, which has to be renamed by hand",
+            old_key,
+            unrenamed.join(", ")
+        ))
+        .into());
+    }
+    let renamed = replace_ranges(source, replacements);
+
+    // A variable the template binds with the new name would capture the
+    // renamed references.
+    let context_roots = |items: &[TemplateItem]| {
+        items
+            .iter()
+            .filter(|item| item.kind == ItemKind::Variable && !item.local)
+            .filter(|item| item.name.split('.').next() == Some(new_key))
+            .count()
+    };
+    let renamed_items = inspect_template(&renamed)?;
+    if context_roots(&renamed_items) != context_roots(&items) + renamed_roots {
+        return Err(RenameErr::Conflict(format!(
+            "the template binds a variable named {}",
+            new_key
+        ))
+        .into());
+    }
+    Ok(renamed)
+}
+
+fn replace_ranges(source: &str, mut replacements: Vec<(usize, usize, String)>) -> String {
+    replacements.sort();
+    let mut output = String::with_capacity(source.len());
+    let mut position = 0;
+    for (start, end, replacement) in replacements {
+        output.push_str(&source[position..start]);
+        output.push_str(&replacement);
+        position = end;
+    }
+    output.push_str(&source[position..]);
+    output
+}
+
+// Tables and arrays of tables are the groups a group path may select, so
+// their keys are never renamed. Tables marked sensitive inline are values.
+fn is_group_table(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::Table(_) => !is_marked(value),
+        toml::Value::Array(values) => values.iter().any(toml::Value::is_table),
+        _ => false,
+    }
+}
+
+// Renames the keys of the values of every table, arrays of tables included.
+fn rename_value(value: &toml::Value, old_key: &str, new_key: &str) -> toml::Value {
+    match value {
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key == old_key && !is_group_table(value) {
+                        true => new_key,
+                        false => key,
+                    };
+                    (key.to_string(), rename_value(value, old_key, new_key))
+                })
+                .collect(),
+        ),
+        toml::Value::Array(values) => toml::Value::Array(
+            values
+                .iter()
+                .map(|value| rename_value(value, old_key, new_key))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+// Every segment of a key path but the last names a table.
+fn rename_path(path: &str, old_key: &str, new_key: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((tables, key)) if key == old_key => format!("{}.{}", tables, new_key),
+        None if path == old_key => new_key.to_string(),
+        _ => path.to_string(),
+    }
+}
+
+fn renamed_config(toml_config: &Config, old_key: &str, new_key: &str) -> toml::value::Table {
+    toml_config
+        .iter()
+        .map(|(group_id, group)| {
+            let group: toml::value::Table = group.clone().into_iter().collect();
+            let mut renamed = rename_value(&toml::Value::Table(group), old_key, new_key);
+            if group_id == SENSITIVE_TABLE {
+                if let Some(toml::Value::Array(keys)) = renamed.get_mut("keys") {
+                    for key in keys.iter_mut() {
+                        if let toml::Value::String(path) = key {
+                            *path = rename_path(path, old_key, new_key);
+                        }
+                    }
+                }
+            }
+            (group_id.clone(), renamed)
+        })
+        .collect()
+}
+
+fn find_conflict(path: &str, value: &toml::Value, old_key: &str, new_key: &str) -> Option<String> {
+    match value {
+        toml::Value::Table(table) => {
+            if table.contains_key(old_key) && table.contains_key(new_key) {
+                return Some(format!("{} has both {} and {}", path, old_key, new_key));
+            }
+            table.iter().find_map(|(key, value)| {
+                find_conflict(&format!("{}.{}", path, key), value, old_key, new_key)
+            })
+        }
+        toml::Value::Array(values) => values
+            .iter()
+            .find_map(|value| find_conflict(path, value, old_key, new_key)),
+        _ => None,
+    }
+}
+
+/// Renames the keys of a config, keeping everything else of its source as
+/// written.
+pub fn rename_config_source(
+    source: &str,
+    old_key: &str,
+    new_key: &str,
+) -> Result<String, TmpTomlErr> {
+    let toml_config: Config = toml::from_str(source)?;
+    let mut group_ids: Vec<&String> = toml_config.keys().collect();
+    group_ids.sort();
+    for group_id in group_ids {
+        let group: toml::value::Table = toml_config[group_id].clone().into_iter().collect();
+        if let Some(conflict) =
+            find_conflict(group_id, &toml::Value::Table(group), old_key, new_key)
+        {
+            return Err(RenameErr::Conflict(conflict).into());
+        }
+    }
+
+    let document = toml::Value::Table(
+        toml_config
+            .iter()
+            .map(|(group_id, group)| {
+                let group = group.clone().into_iter().collect();
+                (group_id.clone(), toml::Value::Table(group))
+            })
+            .collect(),
+    );
+    let mut scanner = TomlScanner {
+        document: &document,
+        source,
+        bytes: source.as_bytes(),
+        position: 0,
+        old_key,
+        new_key,
+        replacements: Vec::new(),
+    };
+    scanner.document();
+    let renamed = replace_ranges(source, scanner.replacements);
+
+    let expected = renamed_config(&toml_config, old_key, new_key);
+    let rewritten: Config = toml::from_str(&renamed)
+        .map_err(|err| RenameErr::Unsupported(format!("the renamed config is invalid: {}", err)))?;
+    let rewritten: toml::value::Table = rewritten
+        .into_iter()
+        .map(|(group_id, group)| (group_id, toml::Value::Table(group.into_iter().collect())))
+        .collect();
+    let same = toml::to_string(&toml::Value::Table(expected)).ok()
+        == toml::to_string(&toml::Value::Table(rewritten)).ok();
+    if !same {
+        return Err(RenameErr::Unsupported(format!(
+            "not every {} of the config could be renamed",
+            old_key
+        ))
+        .into());
+    }
+    Ok(renamed)
+}
+
+// Finds the keys of a TOML document named like the renamed key and the key
+// path strings of the `[_sensitive]` table. The document is valid TOML, it
+// parsed before, as `document`.
+struct TomlScanner<'a> {
+    document: &'a toml::Value,
+    source: &'a str,
+    bytes: &'a [u8],
+    position: usize,
+    old_key: &'a str,
+    new_key: &'a str,
+    replacements: Vec<(usize, usize, String)>,
+}
+
+impl<'a> TomlScanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_blank(&mut self, newlines: bool) {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b' ' | b'\t' | b'\r' => self.position += 1,
+                b'\n' if newlines => self.position += 1,
+                b'#' => {
+                    while self.peek().is_some_and(|byte| byte != b'\n') {
+                        self.position += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    // Whether a key path names a group table, in any entry of the arrays of
+    // tables along it.
+    fn names_group_table(&self, path: &[String]) -> bool {
+        let mut values = vec![self.document];
+        for segment in path {
+            values = values
+                .into_iter()
+                .flat_map(|value| match value {
+                    toml::Value::Array(entries) => entries.iter().collect(),
+                    value => vec![value],
+                })
+                .filter_map(|value| value.get(segment.as_str()))
+                .collect();
+        }
+        values.into_iter().any(is_group_table)
+    }
+
+    fn document(&mut self) {
+        let mut table = Vec::new();
+        let mut in_sensitive = false;
+        loop {
+            self.skip_blank(true);
+            match self.peek() {
+                None => break,
+                Some(b'[') => {
+                    let array = self.bytes.get(self.position + 1) == Some(&b'[');
+                    self.position += if array { 2 } else { 1 };
+                    table = self.key(&[], b']');
+                    in_sensitive = table == [SENSITIVE_TABLE];
+                    while self.peek() == Some(b']') {
+                        self.position += 1;
+                    }
+                }
+                Some(_) => {
+                    let segments = self.key(&table, b'=');
+                    self.position += 1;
+                    self.skip_blank(false);
+                    let is_key_paths = in_sensitive && segments == ["keys"];
+                    self.value(&[table.clone(), segments].concat(), is_key_paths);
+                }
+            }
+        }
+    }
+
+    // Reads a dotted key of `table` up to `terminator`, recording the
+    // segments named like the renamed key. Keys of the document's root are
+    // group IDs and keep their names, as do group tables.
+    fn key(&mut self, table: &[String], terminator: u8) -> Vec<String> {
+        let mut segments = Vec::new();
+        loop {
+            self.skip_blank(false);
+            let (start, end, segment) = match self.peek() {
+                Some(quote @ (b'"' | b'\'')) => {
+                    let start = self.position;
+                    self.string_end(quote);
+                    (
+                        start + 1,
+                        self.position - 1,
+                        self.source[start + 1..self.position - 1].to_string(),
+                    )
+                }
+                _ => {
+                    let start = self.position;
+                    while self.peek().is_some_and(|byte| {
+                        byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-'
+                    }) {
+                        self.position += 1;
+                    }
+                    (
+                        start,
+                        self.position,
+                        self.source[start..self.position].to_string(),
+                    )
+                }
+            };
+            segments.push(segment);
+            let path = [table, &segments].concat();
+            if segments.last().map(String::as_str) == Some(self.old_key)
+                && path.len() > 1
+                && !self.names_group_table(&path)
+            {
+                self.replacements
+                    .push((start, end, self.new_key.to_string()));
+            }
+            self.skip_blank(false);
+            match self.peek() {
+                Some(b'.') => self.position += 1,
+                Some(byte) if byte == terminator => break,
+                _ => break,
+            }
+        }
+        segments
+    }
+
+    // Moves past the string starting at the current position, multi-line
+    // strings included.
+    fn string_end(&mut self, quote: u8) {
+        let triple = [quote; 3];
+        if self.bytes[self.position..].starts_with(&triple) {
+            self.position += 3;
+            while self.position < self.bytes.len() {
+                if self.bytes[self.position..].starts_with(&triple) {
+                    self.position += 3;
+                    // Up to two more quotes belong to the content.
+                    while self.peek() == Some(quote) {
+                        self.position += 1;
+                    }
+                    return;
+                }
+                self.position += if quote == b'"' && self.peek() == Some(b'\\') {
+                    2
+                } else {
+                    1
+                };
+            }
+            return;
+        }
+        self.position += 1;
+        while let Some(byte) = self.peek() {
+            self.position += 1;
+            if byte == quote {
+                return;
+            }
+            if byte == b'\\' && quote == b'"' {
+                self.position += 1;
+            }
+        }
+    }
+
+    fn value(&mut self, path: &[String], is_key_paths: bool) {
+        match self.peek() {
+            Some(quote @ (b'"' | b'\'')) => {
+                let start = self.position;
+                self.string_end(quote);
+                let is_single_line = self.position - start >= 2
+                    && !self.source[start..self.position].starts_with("\"\"\"")
+                    && !self.source[start..self.position].starts_with("'''");
+                if is_key_paths && is_single_line {
+                    let (start, end) = (start + 1, self.position - 1);
+                    let path = rename_path(&self.source[start..end], self.old_key, self.new_key);
+                    self.replacements.push((start, end, path));
+                }
+            }
+            Some(b'[') => {
+                self.position += 1;
+                loop {
+                    self.skip_blank(true);
+                    match self.peek() {
+                        None => break,
+                        Some(b']') => {
+                            self.position += 1;
+                            break;
+                        }
+                        Some(b',') => self.position += 1,
+                        Some(_) => self.value(path, is_key_paths),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.position += 1;
+                loop {
+                    self.skip_blank(false);
+                    match self.peek() {
+                        None => break,
+                        Some(b'}') => {
+                            self.position += 1;
+                            break;
+                        }
+                        Some(b',') => self.position += 1,
+                        Some(_) => {
+                            let segments = self.key(path, b'=');
+                            self.position += 1;
+                            self.skip_blank(false);
+                            self.value(&[path, &segments].concat(), false);
+                        }
+                    }
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|byte| !matches!(byte, b',' | b']' | b'}' | b'\n' | b'#'))
+                {
+                    self.position += 1;
+                }
+            }
+        }
+    }
+}
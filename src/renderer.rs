@@ -0,0 +1,257 @@
+// Rendering of a set of templates against many group selections, e.g. every
+// template of a directory for every environment. The templates are compiled
+// once and the compiled templates are shared by every render.
+//
+// Neither `Renderer` nor `LoadedConfig` change once built, so both are
+// `Send + Sync` and can be shared between threads, e.g. behind an `Arc`.
+
+use crate::{
+    analysis::template_dependencies,
+    exclude::{list_template_files, TemplateFilter},
+    lookup::with_lookup_config,
+    partial::{defer_expressions, Deferral},
+    passthrough::{extract_regions_into, restore_regions},
+    selective::parse_toml_groups_file,
+    tmptoml::{
+        build_template_values, build_tera, build_tera_context, parse_toml_str, read_file,
+        render_error, render_tera_template, resolve_group_paths_with_options, Config, GroupPath,
+        RenderOptions, TemplateSource, TeraRenderErr, TmpTomlErr,
+    },
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::Arc,
+};
+use tera::{Template, Tera};
+
+/// A parsed config, shared by every render of a `Renderer`.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    config: Arc<Config>,
+}
+
+impl LoadedConfig {
+    pub fn new(config: Config) -> LoadedConfig {
+        LoadedConfig {
+            config: Arc::new(config),
+        }
+    }
+
+    /// Parses a config held in memory.
+    pub fn parse(content: &str) -> Result<LoadedConfig, TmpTomlErr> {
+        Ok(LoadedConfig::new(parse_toml_str(content)?))
+    }
+
+    /// Reads and parses a config file, keeping only the groups the paths
+    /// start from.
+    pub fn from_file(path: &Path, group_paths: &[GroupPath]) -> Result<LoadedConfig, TmpTomlErr> {
+        Ok(LoadedConfig::new(parse_toml_groups_file(
+            path.to_str(),
+            group_paths,
+        )?))
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub(crate) fn shared(&self) -> Arc<Config> {
+        Arc::clone(&self.config)
+    }
+}
+
+/// Reads every file below `template_dir` that `filter` doesn't exclude,
+/// named by its path relative to the directory.
+pub(crate) fn read_template_dir(
+    template_dir: &Path,
+    filter: &TemplateFilter,
+) -> Result<Vec<(String, String)>, TmpTomlErr> {
+    let files = list_template_files(template_dir, filter)?;
+    let mut templates = Vec::new();
+    for file in files {
+        let name = file
+            .strip_prefix(template_dir)
+            .unwrap_or(&file)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let source = read_file(file.to_str()).map_err(TeraRenderErr::from)?;
+        templates.push((name, source));
+    }
+    Ok(templates)
+}
+
+/// A set of compiled templates, rendered by name. Rendering only reads the
+/// compiled templates, so one renderer can render from several threads at
+/// once.
+pub struct Renderer {
+    options: RenderOptions,
+    tera: Tera,
+    sources: BTreeMap<String, String>,
+    regions: Vec<String>,
+}
+
+impl Renderer {
+    /// Compiles the templates, given as pairs of name and source. Templates
+    /// can include, import or extend each other by name. `options.group_paths`
+    /// is ignored, the groups being selected for each render.
+    pub fn new(
+        templates: Vec<(String, String)>,
+        options: RenderOptions,
+    ) -> Result<Renderer, TmpTomlErr> {
+        let mut tera = build_tera(&options)?;
+        let mut regions = Vec::new();
+        let mut compiled_sources = Vec::new();
+        for (name, source) in &templates {
+            let mut source =
+                extract_regions_into(source, &options.passthrough_markers, &mut regions)?;
+            if let Some(Deferral::Only(keys)) = &options.deferral {
+                source = defer_expressions(&source, &|variable: &str| {
+                    keys.iter().any(|key| key == variable)
+                })?;
+            }
+            compiled_sources.push((name.clone(), source));
+        }
+        // Which expressions --defer-unknown defers depends on the context,
+        // so those templates are compiled for every render instead.
+        if options.deferral != Some(Deferral::Unknown) {
+            tera.add_raw_templates(compiled_sources).map_err(|err| {
+                TeraRenderErr::InvalidTemplate(format!(
+                    "Failed to parse template file with error: {}",
+                    &err
+                ))
+            })?;
+        }
+        Ok(Renderer {
+            options,
+            tera,
+            sources: templates.into_iter().collect(),
+            regions,
+        })
+    }
+
+    /// Compiles every file below `template_dir` that `filter` doesn't
+    /// exclude, named by its path relative to the directory, e.g.
+    /// `nginx/site.conf`.
+    pub fn from_dir(
+        template_dir: &Path,
+        filter: &TemplateFilter,
+        options: RenderOptions,
+    ) -> Result<Renderer, TmpTomlErr> {
+        Renderer::new(read_template_dir(template_dir, filter)?, options)
+    }
+
+    pub(crate) fn options(&self) -> &RenderOptions {
+        &self.options
+    }
+
+    pub(crate) fn source(&self, name: &str) -> Option<&str> {
+        self.sources.get(name).map(String::as_str)
+    }
+
+    /// Lists the names of the templates, sorted.
+    pub fn template_names(&self) -> Vec<&String> {
+        self.sources.keys().collect()
+    }
+
+    // The templates each template includes, imports or extends by name.
+    fn dependency_map(&self) -> BTreeMap<&String, BTreeSet<String>> {
+        self.sources
+            .iter()
+            .map(|(name, source)| {
+                let dependencies = match self.tera.get_template(name) {
+                    Ok(template) => template_dependencies(template),
+                    Err(_) => Template::new(name, None, source)
+                        .map(|template| template_dependencies(&template))
+                        .unwrap_or_default(),
+                };
+                (name, dependencies)
+            })
+            .collect()
+    }
+
+    /// Lists the names of the templates, every template coming after the
+    /// templates it includes, imports or extends and otherwise sorted.
+    pub fn dependency_order(&self) -> Vec<&String> {
+        let dependencies = self.dependency_map();
+        let mut visited = BTreeSet::new();
+        let mut order = Vec::new();
+        for name in dependencies.keys() {
+            visit_dependencies(name, &dependencies, &mut visited, &mut order);
+        }
+        order
+    }
+
+    /// Lists the names of the templates the named template includes,
+    /// imports or extends, directly or through other templates.
+    pub fn dependencies(&self, name: &str) -> Vec<&String> {
+        let dependencies = self.dependency_map();
+        let mut visited = BTreeSet::new();
+        let mut order = Vec::new();
+        if let Some((name, _)) = dependencies.get_key_value(&name.to_string()) {
+            visit_dependencies(name, &dependencies, &mut visited, &mut order);
+            order.pop();
+        }
+        order
+    }
+
+    /// Renders the named template with the values of `group_paths`, merged
+    /// with the context values of the options.
+    pub fn render(
+        &self,
+        name: &str,
+        config: &LoadedConfig,
+        group_paths: &[GroupPath],
+    ) -> Result<String, TmpTomlErr> {
+        let source = self
+            .sources
+            .get(name)
+            .ok_or_else(|| TeraRenderErr::TemplateNotFound(name.to_string()))?;
+        let mut group_values =
+            resolve_group_paths_with_options(config.config(), group_paths, &self.options)?;
+        self.options.value_providers.provide(&mut group_values)?;
+        let template_values = build_template_values(&group_values, &self.options)?;
+        let lookup_config = self.options.config_lookup.then(|| config.shared());
+        with_lookup_config(lookup_config, || {
+            if self.options.deferral == Some(Deferral::Unknown) {
+                return Ok(render_tera_template(
+                    TemplateSource::Str(source),
+                    template_values,
+                    &self.options,
+                )?);
+            }
+            let context = build_tera_context(&template_values);
+            let rendered = self
+                .tera
+                .render(name, &context)
+                .map_err(|err| render_error(err, &template_values))?;
+            Ok(restore_regions(rendered, &self.regions))
+        })
+    }
+}
+
+// Appends `name` to `order` after the templates it depends on, skipping
+// templates already visited so that cycles end.
+fn visit_dependencies<'a>(
+    name: &'a String,
+    dependencies: &BTreeMap<&'a String, BTreeSet<String>>,
+    visited: &mut BTreeSet<&'a String>,
+    order: &mut Vec<&'a String>,
+) {
+    if !visited.insert(name) {
+        return;
+    }
+    for dependency in dependencies.get(name).into_iter().flatten() {
+        if let Some((dependency, _)) = dependencies.get_key_value(dependency) {
+            visit_dependencies(dependency, dependencies, visited, order);
+        }
+    }
+    order.push(name);
+}
+
+// Fails to compile if either type stops being shareable between threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Renderer>();
+    assert_send_sync::<LoadedConfig>();
+};
@@ -0,0 +1,98 @@
+// Parsing of only the groups a render selects. Generated configs can hold
+// thousands of groups, so the other groups are skipped while parsing instead
+// of being deserialized into values that are dropped right away.
+
+use crate::{
+    sensitive::SENSITIVE_TABLE,
+    tmptoml::{read_file, Config, Group, GroupPath, TmpTomlErr},
+};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use std::fmt;
+
+// A group that isn't selected: checked to be a table like any group, its
+// values skipped.
+struct SkippedGroup;
+
+impl<'de> DeserializeSeed<'de> for SkippedGroup {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SkippedGroup {
+    type Value = ();
+
+    // Matches the error of deserializing the group as a `Group`.
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+}
+
+struct SelectedGroups<'a> {
+    group_ids: &'a [&'a str],
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for SelectedGroups<'a> {
+    type Value = Config;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Config, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for SelectedGroups<'a> {
+    type Value = Config;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a table of groups")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Config, A::Error> {
+        let mut config = Config::new();
+        while let Some(group_id) = map.next_key::<String>()? {
+            if self.group_ids.contains(&group_id.as_str()) || group_id == SENSITIVE_TABLE {
+                let group: Group = map.next_value()?;
+                config.insert(group_id, group);
+            } else {
+                map.next_value_seed(SkippedGroup)?;
+                config.insert(group_id, Group::new());
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Parses the config like `parse_toml_str`, keeping only the values of the
+/// groups the paths start from and the sensitive keys. The other groups are
+/// kept empty so that their IDs can still be suggested for a mistyped group.
+/// They are still checked to be tables and the whole document to be valid
+/// TOML, so a config is rejected like `parse_toml_str` would.
+pub fn parse_toml_groups(content: &str, group_paths: &[GroupPath]) -> Result<Config, TmpTomlErr> {
+    let group_ids: Vec<&str> = group_paths
+        .iter()
+        .filter_map(|group_path| group_path.first())
+        .map(|group_id| group_id.as_str())
+        .collect();
+    let mut deserializer = toml::Deserializer::new(content);
+    let config = SelectedGroups {
+        group_ids: &group_ids,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(config)
+}
+
+pub(crate) fn parse_toml_groups_file(
+    path: Option<&str>,
+    group_paths: &[GroupPath],
+) -> Result<Config, TmpTomlErr> {
+    let file_content = read_file(path)?;
+    parse_toml_groups(&file_content, group_paths)
+}
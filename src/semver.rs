@@ -0,0 +1,430 @@
+// Tera filters reasoning about semantic versions, so that templates emitting
+// dependency manifests or image tags can bump, compare and match the
+// versions of the config rather than slicing strings. Versions follow
+// semver.org, optionally prefixed with `v`, and requirements follow Cargo's
+// syntax.
+
+use std::{cmp::Ordering, collections::HashMap, fmt};
+use tera::{Tera, Value};
+
+pub(crate) fn register_semver_filters(tera: &mut Tera) {
+    tera.register_filter("semver_bump", semver_bump);
+    tera.register_filter("semver_match", semver_match);
+    tera.register_filter(
+        "semver_gt",
+        |value: &Value, args: &HashMap<String, Value>| {
+            semver_compare("semver_gt", value, args, |ordering| ordering.is_gt())
+        },
+    );
+    tera.register_filter(
+        "semver_gte",
+        |value: &Value, args: &HashMap<String, Value>| {
+            semver_compare("semver_gte", value, args, |ordering| ordering.is_ge())
+        },
+    );
+    tera.register_filter(
+        "semver_lt",
+        |value: &Value, args: &HashMap<String, Value>| {
+            semver_compare("semver_lt", value, args, |ordering| ordering.is_lt())
+        },
+    );
+    tera.register_filter(
+        "semver_lte",
+        |value: &Value, args: &HashMap<String, Value>| {
+            semver_compare("semver_lte", value, args, |ordering| ordering.is_le())
+        },
+    );
+    tera.register_filter(
+        "semver_eq",
+        |value: &Value, args: &HashMap<String, Value>| {
+            semver_compare("semver_eq", value, args, |ordering| ordering.is_eq())
+        },
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl Identifier {
+    fn parse(identifier: &str) -> Option<Identifier> {
+        if identifier.is_empty()
+            || !identifier
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return None;
+        }
+        match identifier.parse() {
+            Ok(number) => Some(Identifier::Numeric(number)),
+            Err(_) => Some(Identifier::Alphanumeric(identifier.to_string())),
+        }
+    }
+}
+
+// Numeric identifiers sort before alphanumeric ones.
+impl Ord for Identifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alphanumeric(_)) => Ordering::Less,
+            (Identifier::Alphanumeric(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::Alphanumeric(a), Identifier::Alphanumeric(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Numeric(number) => write!(f, "{}", number),
+            Identifier::Alphanumeric(identifier) => write!(f, "{}", identifier),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Version {
+    prefix: &'static str,
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<Identifier>,
+    build: Option<String>,
+}
+
+fn parse_identifiers(identifiers: &str) -> Option<Vec<Identifier>> {
+    identifiers.split('.').map(Identifier::parse).collect()
+}
+
+impl Version {
+    fn parse(version: &str) -> Option<Version> {
+        let version = version.trim();
+        let (prefix, version) = match version.strip_prefix('v') {
+            Some(version) => ("v", version),
+            None => ("", version),
+        };
+        let (version, build) = match version.split_once('+') {
+            Some((version, build)) => (version, Some(build.to_string())),
+            None => (version, None),
+        };
+        let (version, pre) = match version.split_once('-') {
+            Some((version, pre)) => (version, parse_identifiers(pre)?),
+            None => (version, Vec::new()),
+        };
+        let mut numbers = version.split('.').map(|number| number.parse::<u64>().ok());
+        let version = Version {
+            prefix,
+            major: numbers.next()??,
+            minor: numbers.next()??,
+            patch: numbers.next()??,
+            pre,
+            build,
+        };
+        match numbers.next() {
+            Some(_) => None,
+            None => Some(version),
+        }
+    }
+
+    /// Precedence as defined by semver.org, ignoring build metadata. A
+    /// pre-release sorts before its release.
+    fn precedence(&self, other: &Version) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}.{}.{}",
+            self.prefix, self.major, self.minor, self.patch
+        )?;
+        if !self.pre.is_empty() {
+            let pre: Vec<String> = self.pre.iter().map(Identifier::to_string).collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        if let Some(build) = &self.build {
+            write!(f, "+{}", build)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+    Wildcard,
+}
+
+/// A comparator of a requirement, e.g. `>=1.2`. Missing minor and patch
+/// numbers are wildcards.
+#[derive(Debug, Clone)]
+struct Comparator {
+    op: Op,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<Identifier>,
+}
+
+impl Comparator {
+    fn parse(comparator: &str) -> Option<Comparator> {
+        let comparator = comparator.trim();
+        let (op, version) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .iter()
+        .find_map(|(symbol, op)| comparator.strip_prefix(symbol).map(|rest| (*op, rest)))
+        .unwrap_or((Op::Caret, comparator));
+        let version = version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        let (version, pre) = match version.split_once('-') {
+            Some((version, pre)) => (version, parse_identifiers(pre)?),
+            None => (version, Vec::new()),
+        };
+        let mut parts = version.split('.');
+        let mut number = |wildcard: &mut bool| -> Option<Option<u64>> {
+            match parts.next() {
+                None => Some(None),
+                Some("*" | "x" | "X") => {
+                    *wildcard = true;
+                    Some(None)
+                }
+                Some(_) if *wildcard => None,
+                Some(number) => number.parse().ok().map(Some),
+            }
+        };
+        let mut wildcard = false;
+        let major = number(&mut wildcard)?;
+        let minor = number(&mut wildcard)?;
+        let patch = number(&mut wildcard)?;
+        if parts.next().is_some() || ((minor.is_none() || patch.is_none()) && !pre.is_empty()) {
+            return None;
+        }
+        let op = match (major, wildcard) {
+            (None, _) => return Some(Comparator::any()),
+            (Some(_), true) => Op::Wildcard,
+            (Some(_), false) => op,
+        };
+        Some(Comparator {
+            op,
+            major: major?,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    fn any() -> Comparator {
+        Comparator {
+            op: Op::GreaterEq,
+            major: 0,
+            minor: None,
+            patch: None,
+            pre: Vec::new(),
+        }
+    }
+
+    fn lower(&self) -> Version {
+        Version {
+            prefix: "",
+            major: self.major,
+            minor: self.minor.unwrap_or(0),
+            patch: self.patch.unwrap_or(0),
+            pre: self.pre.clone(),
+            build: None,
+        }
+    }
+
+    // The first version past the range of a partial version, e.g. 1.3.0 for
+    // 1.2, 2.0.0 for 1.
+    fn upper_of_partial(&self) -> (u64, u64, u64) {
+        match (self.minor, self.patch) {
+            (None, _) => (self.major + 1, 0, 0),
+            (Some(minor), None) => (self.major, minor + 1, 0),
+            (Some(minor), Some(patch)) => (self.major, minor, patch + 1),
+        }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let lower = self.lower();
+        let numbers = (version.major, version.minor, version.patch);
+        let below_upper = |upper: (u64, u64, u64)| numbers < upper;
+        match self.op {
+            Op::Exact | Op::Wildcard => {
+                version.precedence(&lower).is_ge()
+                    && below_upper(self.upper_of_partial())
+                    && (self.patch.is_none() || version.pre == self.pre)
+            }
+            Op::Greater => match (self.minor, self.patch) {
+                (Some(_), Some(_)) => version.precedence(&lower).is_gt(),
+                _ => numbers >= self.upper_of_partial(),
+            },
+            Op::GreaterEq => version.precedence(&lower).is_ge(),
+            Op::Less => version.precedence(&lower).is_lt(),
+            Op::LessEq => match (self.minor, self.patch) {
+                (Some(_), Some(_)) => version.precedence(&lower).is_le(),
+                _ => below_upper(self.upper_of_partial()),
+            },
+            Op::Tilde => {
+                let upper = match self.minor {
+                    Some(minor) => (self.major, minor + 1, 0),
+                    None => (self.major + 1, 0, 0),
+                };
+                version.precedence(&lower).is_ge() && below_upper(upper)
+            }
+            Op::Caret => {
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => (0, 0, patch + 1),
+                    (0, Some(minor), _) => (0, minor + 1, 0),
+                    (major, _, _) => (major + 1, 0, 0),
+                };
+                version.precedence(&lower).is_ge() && below_upper(upper)
+            }
+        }
+    }
+}
+
+/// A Cargo style requirement, e.g. `>=1.2, <2` or `^1.4`, every comparator
+/// having to match. A pre-release only matches when a comparator names a
+/// pre-release of the same version, so that `>=1.0` doesn't match
+/// `2.0.0-rc.1`.
+fn requirement_matches(requirement: &[Comparator], version: &Version) -> bool {
+    let pre_release_allowed = version.pre.is_empty()
+        || requirement.iter().any(|comparator| {
+            !comparator.pre.is_empty()
+                && (comparator.major, comparator.minor, comparator.patch)
+                    == (version.major, Some(version.minor), Some(version.patch))
+        });
+    pre_release_allowed
+        && requirement
+            .iter()
+            .all(|comparator| comparator.matches(version))
+}
+
+fn parse_requirement(requirement: &str) -> Option<Vec<Comparator>> {
+    requirement.split(',').map(Comparator::parse).collect()
+}
+
+fn version_value(filter: &str, value: &Value) -> tera::Result<Version> {
+    value.as_str().and_then(Version::parse).ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `{}` was used on a value that isn't a semantic version: {}",
+            filter, value
+        ))
+    })
+}
+
+fn string_arg<'a>(
+    filter: &str,
+    name: &str,
+    args: &'a HashMap<String, Value>,
+) -> tera::Result<&'a str> {
+    match args.get(name) {
+        Some(Value::String(value)) => Ok(value),
+        Some(other) => Err(tera::Error::msg(format!(
+            "Filter `{}` expected the `{}` argument to be a string but got {}",
+            filter, name, other
+        ))),
+        None => Err(tera::Error::msg(format!(
+            "Filter `{}` expected a `{}` argument",
+            filter, name
+        ))),
+    }
+}
+
+/// Bumps the `major`, `minor` or `patch` number of a version, e.g.
+/// `"v1.4.2" | semver_bump(part="minor")` renders `v1.5.0`. Build metadata
+/// is dropped, and so is a pre-release, which bumps to its own release when
+/// that is the bumped version: `1.5.0-rc.1` bumps to `1.5.0` for `minor`.
+fn semver_bump(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let mut version = version_value("semver_bump", value)?;
+    let releases = !version.pre.is_empty();
+    match string_arg("semver_bump", "part", args)? {
+        "major" if releases && version.minor == 0 && version.patch == 0 => {}
+        "major" => {
+            version.major += 1;
+            version.minor = 0;
+            version.patch = 0;
+        }
+        "minor" if releases && version.patch == 0 => {}
+        "minor" => {
+            version.minor += 1;
+            version.patch = 0;
+        }
+        "patch" if releases => {}
+        "patch" => version.patch += 1,
+        other => {
+            return Err(tera::Error::msg(format!(
+                "Filter `semver_bump` expected the `part` argument to be major, minor or patch but got {}",
+                other
+            )))
+        }
+    }
+    version.pre.clear();
+    version.build = None;
+    Ok(Value::String(version.to_string()))
+}
+
+/// Whether a version matches a requirement, e.g.
+/// `{% if tag | semver_match(req=">=1.2, <2") %}`.
+fn semver_match(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let version = version_value("semver_match", value)?;
+    let requirement = string_arg("semver_match", "req", args)?;
+    let comparators = parse_requirement(requirement).ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `semver_match` received an invalid requirement: {}",
+            requirement
+        ))
+    })?;
+    Ok(Value::Bool(requirement_matches(&comparators, &version)))
+}
+
+/// Compares a version to the `version` argument by semver precedence, e.g.
+/// `"1.10.0" | semver_gt(version="1.9.0")` is true where comparing the
+/// strings isn't.
+fn semver_compare(
+    filter: &str,
+    value: &Value,
+    args: &HashMap<String, Value>,
+    accept: fn(Ordering) -> bool,
+) -> tera::Result<Value> {
+    let version = version_value(filter, value)?;
+    let other = string_arg(filter, "version", args)?;
+    let other = Version::parse(other).ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `{}` expected the `version` argument to be a semantic version but got {}",
+            filter, other
+        ))
+    })?;
+    Ok(Value::Bool(accept(version.precedence(&other))))
+}
@@ -0,0 +1,180 @@
+// Values marked sensitive in the config, either inline with
+// `password = { value = "...", sensitive = true }` or by listing their keys
+// in a `[_sensitive]` table. Templates see sensitive values like any other,
+// while the outputs meant for people and other tools keep them out: they are
+// redacted when flattening and recording, left out of exports and reported
+// when a render writes them to an output that isn't marked secret.
+
+use crate::{
+    entries::sub_section,
+    session::REDACTED,
+    tmptoml::{Config, Group, GroupPath},
+};
+use std::collections::BTreeSet;
+
+/// The table listing the sensitive keys, e.g. `keys = ["db.password"]`. Keys
+/// are dotted paths into the values templates see.
+pub const SENSITIVE_TABLE: &str = "_sensitive";
+
+// A value marked inline is a table of exactly its value and whether it is
+// sensitive.
+fn marked(value: &toml::Value) -> Option<(&toml::Value, bool)> {
+    marked_table(value.as_table()?)
+}
+
+fn marked_table(table: &toml::value::Table) -> Option<(&toml::Value, bool)> {
+    match (table.len(), table.get("value"), table.get("sensitive")) {
+        (2, Some(value), Some(toml::Value::Boolean(sensitive))) => Some((value, *sensitive)),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_marked(value: &toml::Value) -> bool {
+    marked(value).is_some()
+}
+
+/// The value with every inline marking replaced by the value it marks.
+pub(crate) fn unmarked(value: &toml::Value) -> toml::Value {
+    match (marked(value), value) {
+        (Some((value, _)), _) => unmarked(value),
+        (None, toml::Value::Table(table)) => toml::Value::Table(
+            table
+                .iter()
+                .map(|(key, value)| (key.clone(), unmarked(value)))
+                .collect(),
+        ),
+        (None, toml::Value::Array(values)) => {
+            toml::Value::Array(values.iter().map(unmarked).collect())
+        }
+        (None, value) => value.clone(),
+    }
+}
+
+fn join_key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+fn marked_keys(path: &str, table: &toml::value::Table, keys: &mut BTreeSet<String>) {
+    for (key, value) in table {
+        let key_path = join_key(path, key);
+        match (marked(value), value) {
+            (Some((_, true)), _) => {
+                keys.insert(key_path);
+            }
+            (Some(_), _) => {}
+            (None, toml::Value::Table(table)) => marked_keys(&key_path, table, keys),
+            _ => {}
+        }
+    }
+}
+
+/// Lists the keys of the values resolved for `group_paths` that are
+/// sensitive, as dotted paths. Like values, markings of intermediate levels
+/// only apply to their scalar values.
+pub fn sensitive_keys(toml_config: &Config, group_paths: &[GroupPath]) -> BTreeSet<String> {
+    let mut keys: BTreeSet<String> = toml_config
+        .get(SENSITIVE_TABLE)
+        .and_then(|table| table.get("keys"))
+        .and_then(|listed| listed.as_array())
+        .map(|listed| {
+            listed
+                .iter()
+                .filter_map(|key| key.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    for group_path in group_paths {
+        let (group_id, sub_group_ids) = match group_path.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+        let mut level: toml::value::Table = match toml_config.get(group_id) {
+            Some(group) => group.clone().into_iter().collect(),
+            None => continue,
+        };
+        for sub_group_id in sub_group_ids {
+            for (key, value) in &level {
+                if matches!(marked(value), Some((_, true))) {
+                    keys.insert(key.clone());
+                }
+            }
+            level = match sub_section(|key| level.get(key), sub_group_id) {
+                Some(table) if marked_table(table).is_none() => table.clone(),
+                _ => toml::value::Table::new(),
+            };
+        }
+        marked_keys("", &level, &mut keys);
+    }
+    keys
+}
+
+fn value_at<'a>(values: &'a Group, key_path: &str) -> Option<&'a toml::Value> {
+    let mut segments = key_path.split('.');
+    let mut value = values.get(segments.next()?)?;
+    for segment in segments {
+        value = value.as_table()?.get(segment)?;
+    }
+    Some(value)
+}
+
+fn value_at_mut<'a>(values: &'a mut Group, key_path: &str) -> Option<&'a mut toml::Value> {
+    let mut segments = key_path.split('.');
+    let mut value = values.get_mut(segments.next()?)?;
+    for segment in segments {
+        value = value.as_table_mut()?.get_mut(segment)?;
+    }
+    Some(value)
+}
+
+/// Replaces the sensitive values of resolved group values with a
+/// placeholder.
+pub(crate) fn redact_sensitive(values: &mut Group, keys: &BTreeSet<String>) {
+    for key in keys {
+        if let Some(value) = value_at_mut(values, key) {
+            *value = toml::Value::String(REDACTED.to_string());
+        }
+    }
+}
+
+/// Removes the sensitive values from resolved group values.
+pub(crate) fn remove_sensitive(values: &mut Group, keys: &BTreeSet<String>) {
+    for key in keys {
+        match key.rsplit_once('.') {
+            None => {
+                values.remove(key);
+            }
+            Some((parent, last)) => {
+                if let Some(toml::Value::Table(table)) = value_at_mut(values, parent) {
+                    table.remove(last);
+                }
+            }
+        }
+    }
+}
+
+fn collect_strings(value: &toml::Value, strings: &mut Vec<String>) {
+    match value {
+        toml::Value::String(string) if !string.is_empty() => strings.push(string.clone()),
+        toml::Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_strings(value, strings)),
+        toml::Value::Table(table) => table
+            .values()
+            .for_each(|value| collect_strings(value, strings)),
+        _ => {}
+    }
+}
+
+/// The string values of a sensitive key of resolved group values, to look
+/// for in rendered output.
+pub(crate) fn sensitive_strings(values: &Group, key: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    if let Some(value) = value_at(values, key) {
+        collect_strings(value, &mut strings);
+    }
+    strings
+}
@@ -0,0 +1,380 @@
+// Recorded renders, replayed to reproduce a render without its config, e.g.
+// for a bug report. A session keeps the template, the resolved context and
+// the output, while the config and template files are only kept as hashes.
+// Values of keys that look like secrets, sensitive values and provided
+// values are redacted, in the context and wherever they appear in the
+// output, so that replaying renders the same redacted output.
+
+use crate::{
+    lint::is_secret_key,
+    manifest::content_hash,
+    partial::Deferral,
+    passthrough::PassthroughMarkers,
+    selective::parse_toml_groups_file,
+    sensitive::sensitive_keys,
+    tmptoml::{
+        build_template_values, read_file, render_str, resolve_group_paths_with_options,
+        RenderOptions, TeraRenderErr, TmpTomlErr,
+    },
+};
+use serde_json::{json, Map, Value};
+use std::{fs, path::Path};
+
+const SESSION_VERSION: u64 = 1;
+
+/// The value redacted values are replaced with.
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Debug)]
+pub enum SessionErr {
+    Read(String),
+    Invalid(String),
+    Write(String),
+    /// The replayed render didn't produce the recorded output or error.
+    Differs(String),
+    /// The replayed render failed with the recorded error.
+    FailsAsRecorded(String),
+}
+
+impl From<SessionErr> for TmpTomlErr {
+    fn from(err: SessionErr) -> Self {
+        TmpTomlErr::Session(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub config_path: String,
+    pub config_hash: String,
+    pub template_path: String,
+    pub template_hash: String,
+    pub template_source: String,
+    /// The options affecting how the template renders its context.
+    pub options: Value,
+    pub context: Map<String, Value>,
+    /// Dotted paths of the redacted context values.
+    pub redacted: Vec<String>,
+    /// The rendered output, or the error the render failed with.
+    pub result: Result<String, String>,
+}
+
+fn join_key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+fn secret_strings(value: &Value, secrets: &mut Vec<String>) {
+    match value {
+        Value::String(string) => secrets.push(string.clone()),
+        Value::Number(number) => secrets.push(number.to_string()),
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| secret_strings(value, secrets)),
+        Value::Object(values) => values
+            .values()
+            .for_each(|value| secret_strings(value, secrets)),
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+// Replaces the values of secret keys, collecting what they rendered as.
+fn redact(
+    path: &str,
+    values: &mut Map<String, Value>,
+    redacted: &mut Vec<String>,
+    secrets: &mut Vec<String>,
+) {
+    for (key, value) in values.iter_mut() {
+        let key_path = join_key(path, key);
+        if is_secret_key(key) {
+            secret_strings(value, secrets);
+            *value = Value::String(REDACTED.to_string());
+            redacted.push(key_path);
+        } else if let Value::Object(table) = value {
+            redact(&key_path, table, redacted, secrets);
+        }
+    }
+}
+
+// Splits a key path like `hosts[0].token` into its keys and indices.
+fn key_path_steps(key_path: &str) -> Vec<Result<&str, usize>> {
+    let mut steps = Vec::new();
+    for segment in key_path.split('.') {
+        let mut parts = segment.split('[');
+        steps.extend(parts.next().map(Ok));
+        steps.extend(parts.filter_map(|index| index.trim_end_matches(']').parse().ok().map(Err)));
+    }
+    steps
+}
+
+// Redacts the value at a key path, unless it already was redacted along
+// with a secret key.
+fn redact_key_path(
+    key_path: &str,
+    context: &mut Map<String, Value>,
+    redacted: &mut Vec<String>,
+    secrets: &mut Vec<String>,
+) {
+    let steps = key_path_steps(key_path);
+    let (first, rest) = match steps.split_first() {
+        Some((Ok(first), rest)) => (first, rest),
+        _ => return,
+    };
+    let mut value = match context.get_mut(*first) {
+        Some(value) => value,
+        None => return,
+    };
+    for step in rest {
+        let next = match (step, value) {
+            (Ok(key), Value::Object(table)) => table.get_mut(*key),
+            (Err(index), Value::Array(values)) => values.get_mut(*index),
+            _ => None,
+        };
+        value = match next {
+            Some(next) => next,
+            None => return,
+        };
+    }
+    if value.as_str() != Some(REDACTED) {
+        secret_strings(value, secrets);
+        *value = Value::String(REDACTED.to_string());
+        redacted.push(key_path.to_string());
+    }
+}
+
+// Secrets are sorted longer first, so that a secret containing another is
+// replaced whole.
+fn scrub(output: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(output.to_string(), |output, secret| {
+            output.replace(secret.as_str(), REDACTED)
+        })
+}
+
+fn options_to_json(options: &RenderOptions) -> Value {
+    json!({
+        "locale": options.locale,
+        "reproducible": options.reproducible,
+        "source_date_epoch": options.source_date_epoch,
+        "only": match &options.deferral {
+            Some(Deferral::Only(keys)) => json!(keys),
+            _ => Value::Null,
+        },
+        "defer_unknown": options.deferral == Some(Deferral::Unknown),
+        "passthrough_markers": options
+            .passthrough_markers
+            .iter()
+            .map(|markers| json!([markers.begin, markers.end]))
+            .collect::<Vec<Value>>(),
+    })
+}
+
+fn options_from_json(options: &Value) -> RenderOptions {
+    let strings = |value: &Value| -> Vec<String> {
+        value
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    RenderOptions {
+        locale: options["locale"].as_str().map(String::from),
+        reproducible: options["reproducible"].as_bool().unwrap_or_default(),
+        source_date_epoch: options["source_date_epoch"].as_i64(),
+        deferral: match (&options["only"], options["defer_unknown"].as_bool()) {
+            (Value::Array(_), _) => Some(Deferral::Only(strings(&options["only"]))),
+            (_, Some(true)) => Some(Deferral::Unknown),
+            _ => None,
+        },
+        passthrough_markers: options["passthrough_markers"]
+            .as_array()
+            .map(|pairs| {
+                pairs
+                    .iter()
+                    .map(strings)
+                    .filter(|pair| pair.len() == 2)
+                    .map(|pair| PassthroughMarkers {
+                        begin: pair[0].clone(),
+                        end: pair[1].clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        ..RenderOptions::default()
+    }
+}
+
+impl Session {
+    /// Records the render of a template with `options`, `result` being its
+    /// output or error. Macros, files the template reads and config lookups
+    /// aren't recorded, so templates using them don't replay.
+    pub fn record(
+        config_file_path: &Path,
+        template_file_path: &Path,
+        options: &RenderOptions,
+        result: Result<&str, String>,
+    ) -> Result<Session, TmpTomlErr> {
+        let read_bytes = |path: &Path| {
+            fs::read(path).map_err(|err| SessionErr::Read(format!("{}: {}", path.display(), err)))
+        };
+        let config_hash = content_hash(&read_bytes(config_file_path)?);
+        let template_hash = content_hash(&read_bytes(template_file_path)?);
+        let template_source =
+            read_file(template_file_path.to_str()).map_err(TeraRenderErr::from)?;
+        let toml_config = parse_toml_groups_file(config_file_path.to_str(), &options.group_paths)?;
+        let mut group_values =
+            resolve_group_paths_with_options(&toml_config, &options.group_paths, options)?;
+        let provided = options.value_providers.provide(&mut group_values)?;
+        let mut context = build_template_values(&group_values, options)?;
+
+        let mut redacted = Vec::new();
+        let mut secrets = Vec::new();
+        redact("", &mut context, &mut redacted, &mut secrets);
+        // Provided values usually come from secret stores.
+        let sensitive = sensitive_keys(&toml_config, &options.group_paths);
+        for key in provided.iter().chain(&sensitive) {
+            redact_key_path(key, &mut context, &mut redacted, &mut secrets);
+        }
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        let result = match result {
+            Ok(output) => Ok(scrub(output, &secrets)),
+            Err(err) => Err(scrub(&err, &secrets)),
+        };
+        Ok(Session {
+            config_path: config_file_path.display().to_string(),
+            config_hash,
+            template_path: template_file_path.display().to_string(),
+            template_hash,
+            template_source,
+            options: options_to_json(options),
+            context,
+            redacted,
+            result,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Session, SessionErr> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| SessionErr::Read(format!("{}: {}", path.display(), err)))?;
+        let invalid = |reason: &str| SessionErr::Invalid(format!("{}: {}", path.display(), reason));
+        let value: Value =
+            serde_json::from_str(&content).map_err(|err| invalid(&err.to_string()))?;
+        if value["version"].as_u64() != Some(SESSION_VERSION) {
+            return Err(invalid("not a tmptoml session"));
+        }
+        let string = |value: &Value, name: &str| {
+            value[name]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| invalid(&format!("missing {}", name)))
+        };
+        let result = match (&value["output"], &value["error"]) {
+            (Value::String(output), _) => Ok(output.clone()),
+            (_, Value::String(err)) => Err(err.clone()),
+            _ => return Err(invalid("missing output")),
+        };
+        Ok(Session {
+            config_path: string(&value["config"], "path")?,
+            config_hash: string(&value["config"], "hash")?,
+            template_path: string(&value["template"], "path")?,
+            template_hash: string(&value["template"], "hash")?,
+            template_source: string(&value["template"], "source")?,
+            options: value["options"].clone(),
+            context: match &value["context"] {
+                Value::Object(context) => context.clone(),
+                _ => return Err(invalid("missing context")),
+            },
+            redacted: value["redacted"]
+                .as_array()
+                .map(|paths| {
+                    paths
+                        .iter()
+                        .filter_map(|path| path.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            result,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "version": SESSION_VERSION,
+            "tmptoml": env!("CARGO_PKG_VERSION"),
+            "config": { "path": self.config_path, "hash": self.config_hash },
+            "template": {
+                "path": self.template_path,
+                "hash": self.template_hash,
+                "source": self.template_source,
+            },
+            "options": self.options,
+            "context": self.context,
+            "redacted": self.redacted,
+        });
+        match &self.result {
+            Ok(output) => value["output"] = Value::String(output.clone()),
+            Err(err) => value["error"] = Value::String(err.clone()),
+        }
+        value
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SessionErr> {
+        let content = serde_json::to_string_pretty(&self.to_json())
+            .map_err(|err| SessionErr::Write(err.to_string()))?;
+        fs::write(path, content)
+            .map_err(|err| SessionErr::Write(format!("{}: {}", path.display(), err)))
+    }
+
+    /// Renders the recorded template with the recorded context and options,
+    /// failing unless it renders the recorded output or fails with the
+    /// recorded error.
+    pub fn replay(&self) -> Result<String, TmpTomlErr> {
+        let options = RenderOptions {
+            context_values: vec![self.context.clone()],
+            ..options_from_json(&self.options)
+        };
+        let replayed =
+            render_str("", &self.template_source, &options).map_err(|err| format!("{:?}", err));
+        match (&self.result, replayed) {
+            (Ok(recorded), Ok(output)) if *recorded == output => Ok(output),
+            (Err(recorded), Err(err)) if *recorded == err => {
+                Err(SessionErr::FailsAsRecorded(err).into())
+            }
+            (Ok(recorded), Ok(output)) => {
+                let line = recorded
+                    .lines()
+                    .zip(output.lines())
+                    .position(|(recorded, replayed)| recorded != replayed)
+                    .unwrap_or_else(|| recorded.lines().count().min(output.lines().count()));
+                Err(
+                    SessionErr::Differs(format!("the output differs from line {}", line + 1))
+                        .into(),
+                )
+            }
+            (Ok(_), Err(err)) => Err(SessionErr::Differs(format!(
+                "the render fails while it succeeded when recorded: {}",
+                err
+            ))
+            .into()),
+            (Err(recorded), Ok(_)) => Err(SessionErr::Differs(format!(
+                "the render succeeds while it failed when recorded with: {}",
+                recorded
+            ))
+            .into()),
+            (Err(_), Err(err)) => Err(SessionErr::Differs(format!(
+                "the render fails with another error than recorded: {}",
+                err
+            ))
+            .into()),
+        }
+    }
+}
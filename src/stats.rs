@@ -0,0 +1,109 @@
+// Overview of the size and shape of a config and its templates, a quick
+// health check for large config repositories.
+
+use crate::{
+    analysis::template_variables,
+    exclude::{list_template_files, TemplateFilter},
+    sensitive::{is_marked, unmarked},
+    tmptoml::{
+        group_ids, parse_toml_to_config, read_file, secondary_group_ids, Config, TeraRenderErr,
+        TmpTomlErr,
+    },
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+use tera::Template;
+
+// Counts the values below a table that aren't tables themselves, by type. A
+// value marked inline counts once, as the type of the value it marks.
+fn count_values(value: &toml::Value, types: &mut BTreeMap<&'static str, usize>) -> usize {
+    match value {
+        toml::Value::Table(table) if !is_marked(value) => {
+            table.values().map(|value| count_values(value, types)).sum()
+        }
+        value => {
+            *types.entry(unmarked(value).type_str()).or_default() += 1;
+            1
+        }
+    }
+}
+
+/// Describes the config: its groups, secondary groups, how many values each
+/// group holds and how many values there are of each type.
+pub fn config_stats(toml_config: &Config) -> String {
+    let mut group_ids: Vec<&String> = group_ids(toml_config).collect();
+    group_ids.sort();
+    let secondary_groups: usize = group_ids
+        .iter()
+        .map(|group_id| secondary_group_ids(&toml_config[*group_id]).len())
+        .sum();
+
+    let mut types: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let mut lines = vec![
+        format!("Groups: {}", group_ids.len()),
+        format!("Secondary groups: {}", secondary_groups),
+        "Keys per group:".to_string(),
+    ];
+    for group_id in &group_ids {
+        let group = &toml_config[*group_id];
+        let own_keys = group
+            .values()
+            .filter(|value| !value.is_table() || is_marked(value))
+            .count();
+        let total_keys: usize = group
+            .values()
+            .map(|value| count_values(value, &mut types))
+            .sum();
+        lines.push(format!(
+            "  {}: {} own, {} including {} secondary group(s)",
+            group_id,
+            own_keys,
+            total_keys,
+            secondary_group_ids(group).len()
+        ));
+    }
+    lines.push("Value types:".to_string());
+    for (type_name, count) in types {
+        lines.push(format!("  {}: {}", type_name, count));
+    }
+    lines.join("\n")
+}
+
+/// Describes the templates below `template_dir`: how many there are and how
+/// many distinct variables they read. Files excluded by `.tmptomlignore`
+/// files aren't counted.
+pub fn template_stats(template_dir: &Path) -> Result<String, TmpTomlErr> {
+    let files = list_template_files(template_dir, &TemplateFilter::default())?;
+    let mut variables = BTreeSet::new();
+    let mut unparsable = 0;
+    for file in &files {
+        let source = read_file(file.to_str()).map_err(TeraRenderErr::from)?;
+        match Template::new(&file.display().to_string(), None, &source) {
+            Ok(template) => variables.extend(template_variables(&template).0),
+            Err(_) => unparsable += 1,
+        }
+    }
+    let mut lines = vec![
+        format!("Templates: {}", files.len()),
+        format!("Distinct variables: {}", variables.len()),
+    ];
+    if unparsable > 0 {
+        lines.push(format!("Templates failing to parse: {}", unparsable));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Describes the config file and, if given, the templates of a directory.
+pub fn stats_file(
+    config_file_path: &Path,
+    template_dir: Option<&Path>,
+) -> Result<String, TmpTomlErr> {
+    let toml_config = parse_toml_to_config(config_file_path.to_str())?;
+    let mut sections = vec![config_stats(&toml_config)];
+    if let Some(template_dir) = template_dir {
+        sections.push(template_stats(template_dir)?);
+    }
+    Ok(sections.join("\n"))
+}
@@ -0,0 +1,154 @@
+// "Did you mean" suggestions for groups and variables that can't be found,
+// typos in environment names being the most common mistake.
+
+use crate::context::ContextValues;
+use serde_json::Value;
+use std::{error::Error, fmt};
+
+const MAX_SUGGESTIONS: usize = 3;
+// Generated configs can hold thousands of groups, more than are worth
+// printing.
+const MAX_LISTED_CANDIDATES: usize = 20;
+
+/// A group or variable that can't be found, along with the similarly named
+/// ones and every name valid in its place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownName {
+    pub name: String,
+    pub suggestions: Vec<String>,
+    pub candidates: Vec<String>,
+}
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.name)?;
+        if !self.suggestions.is_empty() {
+            let suggestions: Vec<String> = self
+                .suggestions
+                .iter()
+                .map(|suggestion| format!("`{}`", suggestion))
+                .collect();
+            write!(f, ", did you mean {}?", suggestions.join(" or "))?;
+        } else if !self.candidates.is_empty() {
+            write!(f, ".")?;
+        }
+        if !self.candidates.is_empty() {
+            let listed = self.candidates.len().min(MAX_LISTED_CANDIDATES);
+            write!(
+                f,
+                " Valid options: {}",
+                self.candidates[..listed].join(", ")
+            )?;
+            if self.candidates.len() > listed {
+                write!(f, " and {} more", self.candidates.len() - listed)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Edit distance counting insertions, deletions, substitutions and swaps of
+// adjacent characters, the last being a common typo.
+fn edit_distance(first: &str, second: &str) -> usize {
+    let first: Vec<char> = first.chars().collect();
+    let second: Vec<char> = second.chars().collect();
+    let mut distances = vec![vec![0; second.len() + 1]; first.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=second.len()).collect();
+    for i in 1..=first.len() {
+        for j in 1..=second.len() {
+            let cost = usize::from(first[i - 1] != second[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && first[i - 1] == second[j - 2] && first[i - 2] == second[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[first.len()][second.len()]
+}
+
+/// Builds the error details of `name` not being one of `candidates`.
+/// Candidates within a third of the name's length in edits, ignoring case,
+/// are suggested, closest first.
+pub(crate) fn unknown_name<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a String>,
+) -> UnknownName {
+    let mut candidates: Vec<String> = candidates.cloned().collect();
+    candidates.sort();
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut scored: Vec<(usize, &String)> = candidates
+        .iter()
+        .map(|candidate| {
+            let distance = edit_distance(&name.to_lowercase(), &candidate.to_lowercase());
+            (distance, candidate)
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    scored.sort();
+    UnknownName {
+        name: name.to_string(),
+        suggestions: scored
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| candidate.clone())
+            .collect(),
+        candidates,
+    }
+}
+
+// Tera reports a missing variable as a message deep in the error chain, e.g.
+// "Variable `db.hots` not found in context while rendering 'template'".
+fn missing_variable(err: &tera::Error) -> Option<String> {
+    let mut source: Option<&(dyn Error + 'static)> = Some(err);
+    while let Some(err) = source {
+        let message = err.to_string();
+        if let Some(rest) = message.strip_prefix("Variable `") {
+            if let Some((variable, _)) = rest.split_once("` not found in context") {
+                return Some(variable.to_string());
+            }
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// Finds the variable a failed render couldn't find, suggesting the names
+/// valid at the level it goes missing, e.g. the keys of `db` for `db.hots`.
+pub(crate) fn unknown_variable(
+    err: &tera::Error,
+    template_values: &ContextValues,
+) -> Option<UnknownName> {
+    let variable = missing_variable(err)?;
+    let mut object = template_values;
+    let mut prefix = String::new();
+    for segment in variable.split('.') {
+        match object.get(segment) {
+            Some(Value::Object(nested)) => {
+                object = nested;
+                prefix.push_str(segment);
+                prefix.push('.');
+            }
+            _ => {
+                let qualify = |names: Vec<String>| -> Vec<String> {
+                    names
+                        .into_iter()
+                        .map(|name| format!("{}{}", prefix, name))
+                        .collect()
+                };
+                let unknown = unknown_name(segment, object.keys());
+                return Some(UnknownName {
+                    name: variable.clone(),
+                    suggestions: qualify(unknown.suggestions),
+                    candidates: qualify(unknown.candidates),
+                });
+            }
+        }
+    }
+    None
+}
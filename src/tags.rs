@@ -0,0 +1,97 @@
+// Tags naming what a section of the config is for, e.g. `_tags = ["prod",
+// "pci"]`, so that batches can render exactly the sections of a compliance
+// scope without maintaining a separate list of selections.
+
+use crate::{
+    batch::BatchErr,
+    tmptoml::{group_ids, parse_toml_to_config, Config, GroupPath, TmpTomlErr},
+};
+use std::path::Path;
+
+/// The key listing a section's tags. It is not one of the section's values.
+pub const TAGS_KEY: &str = "_tags";
+
+fn section_tags<'a>(
+    path: &[String],
+    tags: Option<&'a toml::Value>,
+) -> Result<Vec<&'a str>, BatchErr> {
+    let invalid = || {
+        BatchErr::Tags(format!(
+            "`{}` of {} must be an array of strings",
+            TAGS_KEY,
+            path.join(".")
+        ))
+    };
+    match tags {
+        None => Ok(Vec::new()),
+        Some(toml::Value::Array(tags)) => tags
+            .iter()
+            .map(|tag| tag.as_str().ok_or_else(invalid))
+            .collect(),
+        Some(_) => Err(invalid()),
+    }
+}
+
+fn tagged_sections(
+    path: &mut Vec<String>,
+    section: &toml::value::Table,
+    tags: &[String],
+    tagged: &mut Vec<GroupPath>,
+) -> Result<(), BatchErr> {
+    if section_tags(path, section.get(TAGS_KEY))?
+        .iter()
+        .any(|tag| tags.iter().any(|wanted| wanted == tag))
+    {
+        tagged.push(path.clone());
+    }
+    for (key, value) in section {
+        if let toml::Value::Table(sub_section) = value {
+            path.push(key.clone());
+            tagged_sections(path, sub_section, tags, tagged)?;
+            path.pop();
+        }
+    }
+    Ok(())
+}
+
+/// Lists the paths of the groups and nested groups tagged with any of
+/// `tags`, in key order. Sections don't inherit the tags of their parents.
+pub fn tagged_group_paths(
+    toml_config: &Config,
+    tags: &[String],
+) -> Result<Vec<GroupPath>, TmpTomlErr> {
+    let mut tagged = Vec::new();
+    for group_id in group_ids(toml_config) {
+        let group: toml::value::Table = toml_config[group_id].clone().into_iter().collect();
+        tagged_sections(&mut vec![group_id.clone()], &group, tags, &mut tagged)?;
+    }
+    tagged.sort();
+    Ok(tagged)
+}
+
+/// Selects the sections of a config file tagged with any of `tags`. Given
+/// selections, only the tagged sections they are or contain are selected.
+pub fn select_tagged_file(
+    config_file_path: &Path,
+    tags: &[String],
+    selections: &[GroupPath],
+) -> Result<Vec<GroupPath>, TmpTomlErr> {
+    let toml_config = parse_toml_to_config(config_file_path.to_str())?;
+    let selected: Vec<GroupPath> = tagged_group_paths(&toml_config, tags)?
+        .into_iter()
+        .filter(|tagged| {
+            selections.is_empty()
+                || selections
+                    .iter()
+                    .any(|selection| tagged.starts_with(selection))
+        })
+        .collect();
+    if selected.is_empty() {
+        return Err(BatchErr::Tags(format!(
+            "no selected section is tagged {}",
+            tags.join(" or ")
+        ))
+        .into());
+    }
+    Ok(selected)
+}
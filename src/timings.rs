@@ -0,0 +1,48 @@
+// Breakdown of the time a render spends in each of its phases, reported by
+// `--timings` to find out where slow generation steps go.
+
+use std::{fmt, time::Duration};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Reading and parsing the config file.
+    pub config: Duration,
+    /// Resolving the selected groups and merging the context values.
+    pub context: Duration,
+    /// Analysing the template for warnings.
+    pub warnings: Duration,
+    /// Parsing the template and the macro files.
+    pub compile: Duration,
+    /// Rendering the compiled template.
+    pub render: Duration,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.config + self.context + self.warnings + self.compile + self.render
+    }
+}
+
+/// Formats the duration of a phase as a line of the timings report, the
+/// duration being in milliseconds.
+pub fn format_timing(phase: &str, duration: Duration) -> String {
+    format!("{:<20} {:>10.3}ms", phase, duration.as_secs_f64() * 1000.0)
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = [
+            ("config read/parse", self.config),
+            ("context building", self.context),
+            ("warnings", self.warnings),
+            ("template compilation", self.compile),
+            ("rendering", self.render),
+            ("total", self.total()),
+        ];
+        let lines: Vec<String> = phases
+            .iter()
+            .map(|(phase, duration)| format_timing(phase, *duration))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
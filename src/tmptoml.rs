@@ -1,19 +1,141 @@
+use crate::{
+    bake::BakeErr,
+    batch::BatchErr,
+    bundle::BundleErr,
+    coercion::{context_coercions, Coercion},
+    compression::decompress,
+    config_template::TEMPLATE_KEY,
+    context::{
+        group_to_context_values, merge_context_values_with_precedence, ContextErr, ContextLimits,
+        ContextValues,
+    },
+    defaults::DEFAULTS_TABLE,
+    entries::{entry_selectors, sub_section},
+    export::ExportErr,
+    filters::register_filters,
+    functions::register_functions,
+    import::ImportErr,
+    lookup::{register_lookup_function, with_lookup_config},
+    manifest::ManifestErr,
+    network::register_network_functions,
+    partial::{defer_expressions, Deferral},
+    passthrough::{extract_regions, restore_regions, PassthroughMarkers},
+    precedence::Precedence,
+    provider::{ProviderErr, ValueProviders},
+    rc::RcErr,
+    rename::RenameErr,
+    selective::{parse_toml_groups, parse_toml_groups_file},
+    semver::register_semver_filters,
+    sensitive::{is_marked, sensitive_keys, unmarked, SENSITIVE_TABLE},
+    session::SessionErr,
+    suggest::{unknown_name, unknown_variable, UnknownName},
+    tags::TAGS_KEY,
+    timings::Timings,
+    warnings::{
+        collect_warnings, sensitive_output_warnings, skipped_table_notices, DeprecatedKey, Notice,
+        Warning,
+    },
+};
 use std::{
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 use tera::{Context, Tera};
-use toml::Value;
 
 pub type Config = std::collections::HashMap<String, Group>;
 pub type Group = std::collections::HashMap<String, toml::Value>;
+pub type GroupPath = Vec<String>;
+
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Group paths merged into the context, later paths taking precedence.
+    pub group_paths: Vec<GroupPath>,
+    /// Structured values deep merged over the group values, later values
+    /// taking precedence.
+    pub context_values: Vec<ContextValues>,
+    /// Whether earlier or later layers win when merging group levels, group
+    /// paths and context values. Later layers win by default.
+    pub precedence: Precedence,
+    /// Exposes the tables next to the groups a path descends into under
+    /// their own keys, rather than leaving them out of the context.
+    pub include_siblings: bool,
+    /// Directories whose `*.tera` files are loaded so templates can import
+    /// the macros they define, named by their path relative to the directory.
+    pub macro_dirs: Vec<PathBuf>,
+    /// Locale used by the formatting filters, e.g. `de-DE`. Defaults to
+    /// `en-US`.
+    pub locale: Option<String>,
+    /// Renders only part of the template, re-emitting the other `{{ }}`
+    /// expressions untouched for a later pass.
+    pub deferral: Option<Deferral>,
+    /// Regions between these markers are emitted byte-for-byte.
+    pub passthrough_markers: Vec<PassthroughMarkers>,
+    /// Keys templates should no longer read, reported as warnings.
+    pub deprecated_keys: Vec<DeprecatedKey>,
+    /// Reports the values of the context the template doesn't read as
+    /// warnings. Off by default, as the templates of a group usually read
+    /// only some of its values each.
+    pub warn_unused_keys: bool,
+    /// Directory the `file` and `glob` functions can read below. Templates
+    /// can't read files when unset.
+    pub restrict_root: Option<PathBuf>,
+    /// Pins or refuses the functions whose result depends on when or where
+    /// the template is rendered, so that the same inputs always render the
+    /// same bytes.
+    pub reproducible: bool,
+    /// Seconds since the Unix epoch `now()` returns in reproducible mode,
+    /// usually read from `SOURCE_DATE_EPOCH`.
+    pub source_date_epoch: Option<i64>,
+    /// Lets templates read any value of the whole config with the `config`
+    /// function, rather than only the values of their groups.
+    pub config_lookup: bool,
+    /// Resolve the group values starting with their prefixes, e.g. secrets
+    /// kept in a secret store.
+    pub value_providers: ValueProviders,
+    /// Caps on the size of the context, checked before rendering.
+    pub context_limits: ContextLimits,
+    /// The output is a secret itself, so writing sensitive values to it
+    /// isn't reported.
+    pub secret_output: bool,
+}
+
+/// A rendered template along with the warnings about its config and
+/// template, the values coerced into its context and the time spent in each
+/// phase of the render.
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub output: String,
+    pub warnings: Vec<Warning>,
+    pub notices: Vec<Notice>,
+    pub coercions: Vec<Coercion>,
+    pub timings: Timings,
+}
 
 #[derive(Debug)]
 pub enum TmpTomlErr {
     File(ReadFileErr),
-    GroupNotFound(String),
+    GroupNotFound(UnknownName),
     Config(toml::de::Error),
     Render(TeraRenderErr),
+    Import(ImportErr),
+    Export(ExportErr),
+    Context(ContextErr),
+    Batch(BatchErr),
+    Manifest(ManifestErr),
+    Bundle(BundleErr),
+    Bake(BakeErr),
+    Session(SessionErr),
+    Rename(RenameErr),
+    Rc(RcErr),
+    Provider(ProviderErr),
+    /// The rendered output couldn't be compressed or written.
+    Output(String),
+    /// Rendering produced warnings while they were denied.
+    DeniedWarnings(Vec<Warning>),
+    /// Values were coerced to another type while coercions were denied.
+    DeniedCoercions(Vec<Coercion>),
 }
 impl From<toml::de::Error> for TmpTomlErr {
     fn from(err: toml::de::Error) -> Self {
@@ -36,6 +158,11 @@ impl From<TeraRenderErr> for TmpTomlErr {
 #[derive(Debug)]
 pub enum ReadFileErr {
     FileNotFound(String),
+    FilesystemDisabled(String),
+    /// A compressed file couldn't be decompressed.
+    Decompress(String),
+    /// The file, once decompressed, isn't UTF-8.
+    InvalidUtf8(String),
 }
 
 #[derive(Debug)]
@@ -43,134 +170,656 @@ pub enum TeraRenderErr {
     TemplateNotFound(String),
     InvalidTemplate(String),
     RenderError(tera::Error),
+    UnknownLocale(String),
+    FilesystemDisabled(String),
+    VariableNotFound(UnknownName),
+    Decompress(String),
+    InvalidUtf8(String),
+    /// The selected group doesn't name its template with a `_template` key.
+    TemplateNotSet(String),
 }
 
 impl From<ReadFileErr> for TeraRenderErr {
     fn from(err: ReadFileErr) -> Self {
         match err {
             ReadFileErr::FileNotFound(path) => TeraRenderErr::TemplateNotFound(path),
+            ReadFileErr::FilesystemDisabled(path) => TeraRenderErr::FilesystemDisabled(path),
+            ReadFileErr::Decompress(reason) => TeraRenderErr::Decompress(reason),
+            ReadFileErr::InvalidUtf8(path) => TeraRenderErr::InvalidUtf8(path),
         }
     }
 }
 
-fn read_file(path: Option<&str>) -> Result<String, ReadFileErr> {
+// The wasm build has no filesystem, so every file access is refused up front
+// rather than failing in platform specific ways.
+#[cfg(feature = "wasm")]
+pub(crate) fn check_filesystem_access(path: &str) -> Result<(), ReadFileErr> {
+    Err(ReadFileErr::FilesystemDisabled(path.to_string()))
+}
+
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn check_filesystem_access(_path: &str) -> Result<(), ReadFileErr> {
+    Ok(())
+}
+
+pub(crate) fn read_file(path: Option<&str>) -> Result<String, ReadFileErr> {
     match path {
         Some(path) => {
-            return fs::read_to_string(path)
-                .map_err(|_| ReadFileErr::FileNotFound(path.to_string()));
+            check_filesystem_access(path)?;
+            let content =
+                fs::read(path).map_err(|_| ReadFileErr::FileNotFound(path.to_string()))?;
+            let content = decompress(path, content)?;
+            String::from_utf8(content).map_err(|_| ReadFileErr::InvalidUtf8(path.to_string()))
         }
-        None => return Err(ReadFileErr::FileNotFound("".to_string())),
+        None => Err(ReadFileErr::FileNotFound("".to_string())),
     }
 }
 
-fn build_tera_context(template_values: std::collections::HashMap<String, String>) -> Context {
+pub(crate) fn build_tera_context(template_values: &ContextValues) -> Context {
     let mut context = Context::new();
     for (key, value) in template_values {
         context.insert(key, &value);
     }
-    return context;
+    context
 }
 
-fn render_tera_template(
-    template_file_path: &Path,
-    context: Context,
-) -> Result<String, TeraRenderErr> {
+fn collect_macro_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), TeraRenderErr> {
+    check_filesystem_access(&dir.display().to_string())?;
+    let entries = fs::read_dir(dir)
+        .map_err(|_| TeraRenderErr::TemplateNotFound(dir.display().to_string()))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|_| TeraRenderErr::TemplateNotFound(dir.display().to_string()))?
+            .path();
+        if path.is_dir() {
+            collect_macro_files(&path, files)?;
+        } else if path.extension().map(|ext| ext == "tera").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn build_tera(options: &RenderOptions) -> Result<Tera, TeraRenderErr> {
     let mut tera = Tera::default();
-    let template_name = "template";
-
-    tera.add_template_file(template_file_path, Some(template_name))
-        .map_err(|err| {
-            TeraRenderErr::InvalidTemplate(format!(
-                "Failed to parse template file with error: {}",
-                &err
-            ))
-        })?;
-    return tera
-        .render(template_name, &context)
-        .map_err(|err| TeraRenderErr::RenderError(err));
-}
-
-fn flatten_sections(
-    group_section: &std::collections::HashMap<String, Value>,
-    secondary_group_section_name: &String,
-) -> std::collections::HashMap<String, String> {
-    let mut flattened: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-
-    group_section.iter().for_each(|(key, value)| {
-        if key == secondary_group_section_name {
-            if let toml::Value::Table(table) = value {
-                table.iter().for_each(|(key, value)| {
-                    if !flattened.contains_key(key) {
-                        flattened.insert(key.to_string(), value.to_string());
-                    }
-                });
-            }
-        } else {
-            if let toml::Value::Table(_) = value {
-                //Skip all other tables in the group section
-                //TODO: Add support for nested groups
-            } else {
-                if !flattened.contains_key(key) {
-                    flattened.insert(key.to_string(), value.to_string());
+    register_filters(&mut tera, options)?;
+    register_functions(&mut tera, options);
+    register_network_functions(&mut tera);
+    register_semver_filters(&mut tera);
+    register_lookup_function(&mut tera, options.config_lookup);
+
+    let mut macro_files: Vec<(PathBuf, Option<String>)> = Vec::new();
+    for macro_dir in &options.macro_dirs {
+        let mut files = Vec::new();
+        collect_macro_files(macro_dir, &mut files)?;
+        for file in files {
+            let name = file
+                .strip_prefix(macro_dir)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .replace('\\', "/");
+            macro_files.push((file, Some(name)));
+        }
+    }
+    // Macro files may import each other, so they are added all at once.
+    tera.add_template_files(macro_files).map_err(|err| {
+        TeraRenderErr::InvalidTemplate(format!("Failed to parse macro file with error: {}", &err))
+    })?;
+
+    Ok(tera)
+}
+
+pub(crate) enum TemplateSource<'a> {
+    File(&'a Path),
+    Str(&'a str),
+}
+
+// Missing variables are reported with suggestions of similarly named ones.
+pub(crate) fn render_error(err: tera::Error, template_values: &ContextValues) -> TeraRenderErr {
+    match unknown_variable(&err, template_values) {
+        Some(unknown) => TeraRenderErr::VariableNotFound(unknown),
+        None => TeraRenderErr::RenderError(err),
+    }
+}
+
+// A template parsed along with the macro files it may import, ready to be
+// rendered with a context.
+pub(crate) struct CompiledTemplate {
+    tera: Tera,
+    regions: Vec<String>,
+}
+
+const TEMPLATE_NAME: &str = "template";
+
+pub(crate) fn compile_tera_template(
+    template: TemplateSource,
+    template_values: &ContextValues,
+    options: &RenderOptions,
+) -> Result<CompiledTemplate, TeraRenderErr> {
+    let preprocessed_source;
+    let mut regions = Vec::new();
+    let template = if options.deferral.is_some() || !options.passthrough_markers.is_empty() {
+        let source = match template {
+            TemplateSource::File(template_file_path) => read_file(template_file_path.to_str())?,
+            TemplateSource::Str(template_content) => template_content.to_string(),
+        };
+        let (mut source, extracted) = extract_regions(&source, &options.passthrough_markers)?;
+        regions = extracted;
+        if let Some(deferral) = &options.deferral {
+            let is_substituted = |variable: &str| match deferral {
+                Deferral::Only(keys) => keys.iter().any(|key| key == variable),
+                Deferral::Unknown => template_values.contains_key(variable),
+            };
+            source = defer_expressions(&source, &is_substituted)?;
+        }
+        preprocessed_source = source;
+        TemplateSource::Str(&preprocessed_source)
+    } else {
+        template
+    };
+    let mut tera = build_tera(options)?;
+
+    match template {
+        TemplateSource::File(template_file_path) => {
+            let template_content = read_file(template_file_path.to_str())?;
+            tera.add_raw_template(TEMPLATE_NAME, &template_content)
+        }
+        TemplateSource::Str(template_content) => {
+            tera.add_raw_template(TEMPLATE_NAME, template_content)
+        }
+    }
+    .map_err(|err| {
+        TeraRenderErr::InvalidTemplate(format!(
+            "Failed to parse template file with error: {}",
+            &err
+        ))
+    })?;
+    Ok(CompiledTemplate { tera, regions })
+}
+
+pub(crate) fn render_compiled_template(
+    compiled: &CompiledTemplate,
+    template_values: ContextValues,
+) -> Result<String, TeraRenderErr> {
+    let context = build_tera_context(&template_values);
+    let rendered = compiled
+        .tera
+        .render(TEMPLATE_NAME, &context)
+        .map_err(|err| render_error(err, &template_values))?;
+    Ok(restore_regions(rendered, &compiled.regions))
+}
+
+pub(crate) fn render_tera_template(
+    template: TemplateSource,
+    template_values: ContextValues,
+    options: &RenderOptions,
+) -> Result<String, TeraRenderErr> {
+    let compiled = compile_tera_template(template, &template_values, options)?;
+    render_compiled_template(&compiled, template_values)
+}
+
+// Tables, whether written inline or as standard tables, are merged key by
+// key so layers can extend them. Any other value replaces the existing one,
+// unless the earlier layer wins.
+fn merge_toml_value(
+    existing: Option<&mut toml::Value>,
+    value: &toml::Value,
+    precedence: Precedence,
+) -> Option<toml::Value> {
+    match (existing, value) {
+        (Some(toml::Value::Table(existing_table)), toml::Value::Table(table)) => {
+            for (key, value) in table {
+                if let Some(merged) =
+                    merge_toml_value(existing_table.get_mut(key), value, precedence)
+                {
+                    existing_table.insert(key.to_string(), merged);
                 }
             }
+            None
+        }
+        (Some(_), _) if precedence.keeps_existing() => None,
+        _ => Some(value.clone()),
+    }
+}
+
+fn merge_into_group(group: &mut Group, key: &str, value: &toml::Value, precedence: Precedence) {
+    if let Some(merged) = merge_toml_value(group.get_mut(key), value, precedence) {
+        group.insert(key.to_string(), merged);
+    }
+}
+
+fn merge_level<'a>(
+    flattened: &mut Group,
+    values: impl Iterator<Item = (&'a String, &'a toml::Value)>,
+    descended_into: Option<&str>,
+    include_siblings: bool,
+    precedence: Precedence,
+) {
+    values.for_each(|(key, value)| {
+        // The template a group is rendered with and its tags aren't among
+        // its values.
+        if key == TEMPLATE_KEY || key == TAGS_KEY {
+            return;
+        }
+        // Tables of intermediate levels are the groups the path may descend
+        // into, so only the last level exposes its tables as values, unless
+        // the siblings of the table descended into are included.
+        // Values marked sensitive inline are tables too, standing for the
+        // value they mark.
+        let exposed = match descended_into {
+            None => true,
+            Some(sub_group_id) => {
+                !value.is_table() || is_marked(value) || (include_siblings && key != sub_group_id)
+            }
+        };
+        if exposed {
+            merge_into_group(flattened, key, &unmarked(value), precedence);
         }
     });
+}
 
-    return flattened;
+/// Lists the IDs of the top-level groups, leaving out the `[_defaults]` and
+/// `[_sensitive]` tables.
+pub(crate) fn group_ids(toml_config: &Config) -> impl Iterator<Item = &String> {
+    toml_config
+        .keys()
+        .filter(|group_id| *group_id != DEFAULTS_TABLE && *group_id != SENSITIVE_TABLE)
+}
+
+// Walks the group path through the config, merging the scalar values of every
+// level with all values of the last level. Deeper levels take precedence.
+pub(crate) fn flatten_sections(
+    toml_config: &Config,
+    group_path: &[String],
+    precedence: Precedence,
+    include_siblings: bool,
+) -> Result<Group, TmpTomlErr> {
+    let (group_id, sub_group_ids) = group_path
+        .split_first()
+        .ok_or_else(|| TmpTomlErr::GroupNotFound(unknown_name("", group_ids(toml_config))))?;
+    let group_section = toml_config
+        .get(group_id)
+        .ok_or_else(|| TmpTomlErr::GroupNotFound(unknown_name(group_id, group_ids(toml_config))))?;
+
+    let mut flattened: Group = Group::new();
+    merge_level(
+        &mut flattened,
+        group_section.iter(),
+        sub_group_ids.first().map(String::as_str),
+        include_siblings,
+        precedence,
+    );
+
+    let mut current_section: Option<&toml::value::Table> = None;
+    for (depth, sub_group_id) in sub_group_ids.iter().enumerate() {
+        let value_of = |key: &str| match current_section {
+            None => group_section.get(key),
+            Some(section) => section.get(key),
+        };
+        match sub_section(value_of, sub_group_id) {
+            Some(table) => {
+                merge_level(
+                    &mut flattened,
+                    table.iter(),
+                    sub_group_ids.get(depth + 1).map(String::as_str),
+                    include_siblings,
+                    precedence,
+                );
+                current_section = Some(table);
+            }
+            None => {
+                // Entries of an array of tables are suggested by the field
+                // they are selected with.
+                if let Some(selectors) = entry_selectors(value_of, sub_group_id) {
+                    return Err(TmpTomlErr::GroupNotFound(unknown_name(
+                        sub_group_id,
+                        selectors.iter(),
+                    )));
+                }
+                let sub_groups: Vec<&String> = match current_section {
+                    None => secondary_group_ids(group_section),
+                    Some(section) => section
+                        .iter()
+                        .filter(|(_, value)| value.is_table())
+                        .map(|(key, _)| key)
+                        .collect(),
+                };
+                return Err(TmpTomlErr::GroupNotFound(unknown_name(
+                    sub_group_id,
+                    sub_groups.into_iter(),
+                )));
+            }
+        }
+    }
+
+    Ok(flattened)
+}
+
+/// Lists the tables of the levels a group path descends through other than
+/// the ones it descends into, as dotted paths, e.g. `qa.system2` for
+/// `qa.system1`. Unless siblings are included, they are left out of the
+/// context.
+pub fn skipped_tables(toml_config: &Config, group_path: &[String]) -> Vec<String> {
+    let (group_id, sub_group_ids) = match group_path.split_first() {
+        Some(split) => split,
+        None => return Vec::new(),
+    };
+    let mut level: Vec<(&String, &toml::Value)> = match toml_config.get(group_id) {
+        Some(group_section) => group_section.iter().collect(),
+        None => return Vec::new(),
+    };
+    let mut skipped = Vec::new();
+    for (depth, sub_group_id) in sub_group_ids.iter().enumerate() {
+        let prefix = group_path[..=depth].join(".");
+        skipped.extend(
+            level
+                .iter()
+                .filter(|(key, value)| {
+                    value.is_table() && !is_marked(value) && *key != sub_group_id
+                })
+                .map(|(key, _)| format!("{}.{}", prefix, key)),
+        );
+        let value_of = |key: &str| {
+            level
+                .iter()
+                .find(|(level_key, _)| *level_key == key)
+                .map(|(_, value)| *value)
+        };
+        level = match sub_section(value_of, sub_group_id) {
+            Some(table) => table.iter().collect(),
+            None => break,
+        };
+    }
+    skipped.sort();
+    skipped
 }
 
 pub fn parse_toml_to_config(path: Option<&str>) -> Result<Config, TmpTomlErr> {
     let file_content = read_file(path)?;
-    let toml_config: Config = toml::from_str(&file_content)?;
-    return Ok(toml_config);
+    parse_toml_str(&file_content)
+}
+
+pub fn parse_toml_str(content: &str) -> Result<Config, TmpTomlErr> {
+    let toml_config: Config = toml::from_str(content)?;
+    Ok(toml_config)
+}
+
+/// Lists the IDs of the secondary groups of a group, sorted.
+pub fn secondary_group_ids(group_section: &Group) -> Vec<&String> {
+    let mut ids: Vec<&String> = group_section
+        .iter()
+        .filter(|(_, value)| value.is_table() && !is_marked(value))
+        .map(|(key, _)| key)
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Resolves the values a template sees for the given group and secondary
+/// group of the config.
+pub fn resolve_group(
+    toml_config: &Config,
+    group_id: &str,
+    sec_group_id: &str,
+) -> Result<Group, TmpTomlErr> {
+    resolve_group_path(
+        toml_config,
+        &[group_id.to_string(), sec_group_id.to_string()],
+    )
+}
+
+/// Resolves the values a template sees for a path of nested groups, e.g.
+/// `["qa", "eu-west", "system2"]`. Each level contributes its scalar values,
+/// overriding those of the levels above it.
+pub fn resolve_group_path(
+    toml_config: &Config,
+    group_path: &[String],
+) -> Result<Group, TmpTomlErr> {
+    flatten_sections(toml_config, group_path, Precedence::default(), false)
+}
+
+/// Parses a dotted group path such as `shared.observability`. The dots of an
+/// entry selector such as `hosts[ip=10.0.0.1]` don't separate groups.
+pub fn parse_group_path(path: &str) -> GroupPath {
+    let mut group_path = vec![String::new()];
+    let mut in_selector = false;
+    for c in path.chars() {
+        match c {
+            '.' if !in_selector => group_path.push(String::new()),
+            c => {
+                in_selector = match c {
+                    '[' => true,
+                    ']' => false,
+                    _ => in_selector,
+                };
+                if let Some(id) = group_path.last_mut() {
+                    id.push(c);
+                }
+            }
+        }
+    }
+    group_path
+}
+
+/// Resolves several independent group paths and merges them into a single
+/// set of values. Later paths take precedence over earlier ones and tables
+/// present in several paths are merged.
+pub fn resolve_group_paths(
+    toml_config: &Config,
+    group_paths: &[GroupPath],
+) -> Result<Group, TmpTomlErr> {
+    resolve_group_paths_with_precedence(toml_config, group_paths, Precedence::default())
+}
+
+/// Resolves several group paths like `resolve_group_paths`, `precedence`
+/// deciding whether earlier or later levels and paths win.
+pub fn resolve_group_paths_with_precedence(
+    toml_config: &Config,
+    group_paths: &[GroupPath],
+    precedence: Precedence,
+) -> Result<Group, TmpTomlErr> {
+    merge_group_paths(toml_config, group_paths, precedence, false)
+}
+
+/// Resolves several group paths with the precedence of the options, exposing
+/// the siblings of the groups they descend into when the options include
+/// them.
+pub(crate) fn resolve_group_paths_with_options(
+    toml_config: &Config,
+    group_paths: &[GroupPath],
+    options: &RenderOptions,
+) -> Result<Group, TmpTomlErr> {
+    merge_group_paths(
+        toml_config,
+        group_paths,
+        options.precedence,
+        options.include_siblings,
+    )
+}
+
+fn merge_group_paths(
+    toml_config: &Config,
+    group_paths: &[GroupPath],
+    precedence: Precedence,
+    include_siblings: bool,
+) -> Result<Group, TmpTomlErr> {
+    let mut merged = Group::new();
+    for group_path in group_paths {
+        for (key, value) in flatten_sections(toml_config, group_path, precedence, include_siblings)?
+        {
+            merge_into_group(&mut merged, &key, &value, precedence);
+        }
+    }
+    Ok(merged)
 }
 
 pub fn render_template(
-    config_file_path: &PathBuf,
-    template_file_path: &PathBuf,
+    config_file_path: &Path,
+    template_file_path: &Path,
     group_id: String,
     sec_group_id: String,
 ) -> Result<String, TmpTomlErr> {
-    let debug_print = false;
-    let toml_config = parse_toml_to_config(config_file_path.to_str())?;
-    if debug_print {
-        println!("Config File:\n{:?}\n", toml_config);
-    }
+    render_template_path(
+        config_file_path,
+        template_file_path,
+        &[group_id, sec_group_id],
+    )
+}
 
-    if !toml_config.contains_key(&group_id) {
-        return Err(TmpTomlErr::GroupNotFound(group_id));
-    }
+pub fn render_template_path(
+    config_file_path: &Path,
+    template_file_path: &Path,
+    group_path: &[String],
+) -> Result<String, TmpTomlErr> {
+    render_template_paths(config_file_path, template_file_path, &[group_path.to_vec()])
+}
 
-    if !toml_config[&group_id].contains_key(&sec_group_id) {
-        return Err(TmpTomlErr::GroupNotFound(sec_group_id));
+pub fn render_template_paths(
+    config_file_path: &Path,
+    template_file_path: &Path,
+    group_paths: &[GroupPath],
+) -> Result<String, TmpTomlErr> {
+    let options = RenderOptions {
+        group_paths: group_paths.to_vec(),
+        ..RenderOptions::default()
+    };
+    render_template_with_options(config_file_path, template_file_path, &options)
+}
+
+pub(crate) fn build_template_values(
+    group_values: &Group,
+    options: &RenderOptions,
+) -> Result<ContextValues, TmpTomlErr> {
+    let mut template_values = group_to_context_values(group_values);
+    for context_values in &options.context_values {
+        merge_context_values_with_precedence(
+            &mut template_values,
+            context_values.clone(),
+            options.precedence,
+        );
     }
+    options.context_limits.check(&template_values)?;
+    Ok(template_values)
+}
 
-    let group_section = &toml_config[&group_id];
-    let sec_group_section = &toml_config[&group_id][&sec_group_id];
+pub fn render_template_with_options(
+    config_file_path: &Path,
+    template_file_path: &Path,
+    options: &RenderOptions,
+) -> Result<String, TmpTomlErr> {
+    // Lookups can read any group, so the whole config is parsed for them.
+    let toml_config = match options.config_lookup {
+        true => parse_toml_to_config(config_file_path.to_str())?,
+        false => parse_toml_groups_file(config_file_path.to_str(), &options.group_paths)?,
+    };
+    let mut group_values =
+        resolve_group_paths_with_options(&toml_config, &options.group_paths, options)?;
+    options.value_providers.provide(&mut group_values)?;
+    let template_values = build_template_values(&group_values, options)?;
+    let lookup_config = options.config_lookup.then(|| Arc::new(toml_config));
+    let rendered_template = with_lookup_config(lookup_config, || {
+        render_tera_template(
+            TemplateSource::File(template_file_path),
+            template_values,
+            options,
+        )
+    })?;
+    Ok(rendered_template)
+}
 
-    if debug_print {
-        println!("Cofnig File:\n{:?}\n", toml_config);
-        println!("Group\n{:?}\n", &group_id);
-        println!("Group Section\n{:?}\n", &group_section);
-        println!("Secondary Group Group\n{:?}\n", &sec_group_id);
-        println!("{:?} Section\n{:?}\n", sec_group_id, sec_group_section);
-        let sub_group_table = sec_group_section.as_table();
-        match sub_group_table {
-            Some(table) => {
-                table.iter().for_each(|(key, value)| {
-                    println!("{:?} {:?}", key, value);
-                });
-            }
-            None => println!("{:?}", sec_group_section),
-        }
-    }
+/// Renders the template like `render_template_with_options`, additionally
+/// reporting shadowed, deprecated and, when asked to, unused keys and values
+/// that change type on their way into the template.
+pub fn render_template_with_warnings(
+    config_file_path: &Path,
+    template_file_path: &Path,
+    options: &RenderOptions,
+) -> Result<Rendered, TmpTomlErr> {
+    let mut timings = Timings::default();
+    let started = Instant::now();
+    // Lookups can read any group, so the whole config is parsed for them.
+    let toml_config = match options.config_lookup {
+        true => parse_toml_to_config(config_file_path.to_str())?,
+        false => parse_toml_groups_file(config_file_path.to_str(), &options.group_paths)?,
+    };
+    timings.config = started.elapsed();
 
-    let template_values = flatten_sections(group_section, &sec_group_id);
-    if debug_print {
-        println!("Template Values:\n{:?}\n", template_values);
-    }
-    let tera_context = build_tera_context(template_values);
-    let rendered_template = render_tera_template(template_file_path.as_path(), tera_context)?;
-    return Ok(rendered_template);
+    let started = Instant::now();
+    let mut group_values =
+        resolve_group_paths_with_options(&toml_config, &options.group_paths, options)?;
+    options.value_providers.provide(&mut group_values)?;
+    let template_values = build_template_values(&group_values, options)?;
+    timings.context = started.elapsed();
+
+    let started = Instant::now();
+    let template_source = read_file(template_file_path.to_str()).map_err(TeraRenderErr::from)?;
+    let (template_source, _) = extract_regions(&template_source, &options.passthrough_markers)?;
+    let mut warnings = collect_warnings(
+        &toml_config,
+        &group_values,
+        &template_values,
+        &template_source,
+        options,
+    )?;
+    timings.warnings = started.elapsed();
+
+    let started = Instant::now();
+    let compiled = compile_tera_template(
+        TemplateSource::File(template_file_path),
+        &template_values,
+        options,
+    )?;
+    timings.compile = started.elapsed();
+
+    let sensitive = sensitive_keys(&toml_config, &options.group_paths);
+    let notices =
+        skipped_table_notices(&toml_config, &options.group_paths, options.include_siblings);
+    let started = Instant::now();
+    let lookup_config = options.config_lookup.then(|| Arc::new(toml_config));
+    let output = with_lookup_config(lookup_config, || {
+        render_compiled_template(&compiled, template_values)
+    })?;
+    timings.render = started.elapsed();
+    warnings.extend(sensitive_output_warnings(
+        sensitive,
+        &group_values,
+        &output,
+        options.secret_output,
+    ));
+    warnings.sort();
+
+    Ok(Rendered {
+        output,
+        warnings,
+        notices,
+        coercions: context_coercions(&group_values),
+        timings,
+    })
+}
+
+/// Renders a template held in memory against a config held in memory, for
+/// embedders that don't work with files. `options.macro_dirs` is the only
+/// option that still reads from the filesystem.
+pub fn render_str(
+    config_content: &str,
+    template_content: &str,
+    options: &RenderOptions,
+) -> Result<String, TmpTomlErr> {
+    let toml_config = match options.config_lookup {
+        true => parse_toml_str(config_content)?,
+        false => parse_toml_groups(config_content, &options.group_paths)?,
+    };
+    let mut group_values =
+        resolve_group_paths_with_options(&toml_config, &options.group_paths, options)?;
+    options.value_providers.provide(&mut group_values)?;
+    let template_values = build_template_values(&group_values, options)?;
+    let lookup_config = options.config_lookup.then(|| Arc::new(toml_config));
+    let rendered_template = with_lookup_config(lookup_config, || {
+        render_tera_template(
+            TemplateSource::Str(template_content),
+            template_values,
+            options,
+        )
+    })?;
+    Ok(rendered_template)
 }
@@ -0,0 +1,111 @@
+// All-or-nothing writing of a batch's output files. Every file is first
+// written next to its target and only renamed into place once all of them
+// are staged, so a failed batch leaves the output directory untouched.
+
+use crate::batch::BatchErr;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+struct StagedFile {
+    target: PathBuf,
+    staged: PathBuf,
+    backup: Option<PathBuf>,
+}
+
+#[derive(Default)]
+pub(crate) struct Transaction {
+    files: Vec<StagedFile>,
+    created_dirs: Vec<PathBuf>,
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.tmptoml-{}", name, suffix))
+}
+
+fn write_err(path: &Path, err: std::io::Error) -> BatchErr {
+    BatchErr::Write(format!("{}: {}", path.display(), err))
+}
+
+impl Transaction {
+    fn create_dirs(&mut self, dir: &Path) -> Result<(), BatchErr> {
+        let missing: Vec<&Path> = dir.ancestors().take_while(|dir| !dir.exists()).collect();
+        fs::create_dir_all(dir).map_err(|err| write_err(dir, err))?;
+        self.created_dirs
+            .extend(missing.into_iter().rev().map(Path::to_path_buf));
+        Ok(())
+    }
+
+    /// Writes `content` next to `target`, to be moved into place by
+    /// `commit`.
+    pub(crate) fn stage(&mut self, target: &Path, content: &[u8]) -> Result<(), BatchErr> {
+        if let Some(parent) = target.parent() {
+            self.create_dirs(parent)?;
+        }
+        let staged = sibling(target, "staged");
+        fs::write(&staged, content).map_err(|err| write_err(&staged, err))?;
+        self.files.push(StagedFile {
+            target: target.to_path_buf(),
+            staged,
+            backup: None,
+        });
+        Ok(())
+    }
+
+    // Moves a staged file into place, keeping the file it replaces until
+    // every file is in place.
+    fn commit_file(file: &mut StagedFile) -> Result<(), BatchErr> {
+        if file.target.exists() {
+            let backup = sibling(&file.target, "backup");
+            fs::rename(&file.target, &backup).map_err(|err| write_err(&file.target, err))?;
+            file.backup = Some(backup);
+        }
+        fs::rename(&file.staged, &file.target).map_err(|err| write_err(&file.target, err))
+    }
+
+    /// Moves every staged file into place. If any of them can't be, the
+    /// files already moved are restored and the transaction rolled back.
+    pub(crate) fn commit(mut self) -> Result<(), BatchErr> {
+        let mut result = Ok(());
+        let mut committed = 0;
+        for file in self.files.iter_mut() {
+            result = Transaction::commit_file(file);
+            if result.is_err() {
+                break;
+            }
+            committed += 1;
+        }
+        if let Err(err) = result {
+            for file in self.files[..committed].iter().rev() {
+                let _ = fs::remove_file(&file.target);
+                if let Some(backup) = &file.backup {
+                    let _ = fs::rename(backup, &file.target);
+                }
+            }
+            if let Some(backup) = &self.files[committed].backup {
+                let _ = fs::rename(backup, &self.files[committed].target);
+            }
+            self.rollback();
+            return Err(err);
+        }
+        for backup in self.files.iter().filter_map(|file| file.backup.as_ref()) {
+            let _ = fs::remove_file(backup);
+        }
+        Ok(())
+    }
+
+    /// Removes every staged file and the directories created for them.
+    pub(crate) fn rollback(self) {
+        for file in &self.files {
+            let _ = fs::remove_file(&file.staged);
+        }
+        for dir in self.created_dirs.iter().rev() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
@@ -0,0 +1,297 @@
+// Warnings about configs and templates that render but are likely mistaken:
+// values shadowed by another selected group, deprecated keys still read by a
+// template, values no template reads, values changed on their way into the
+// template context and sensitive values written to an output that isn't
+// secret. Along with them, notices about the tables of the config a render
+// leaves out.
+
+use crate::{
+    analysis::template_variables,
+    context::ContextValues,
+    precedence::Precedence,
+    sensitive::sensitive_strings,
+    tmptoml::{
+        flatten_sections, skipped_tables, Config, Group, GroupPath, RenderOptions, TeraRenderErr,
+        TmpTomlErr,
+    },
+};
+use std::{collections::BTreeSet, fmt, str::FromStr};
+use tera::Template;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WarningKind {
+    ShadowedKey,
+    DeprecatedKey,
+    UnusedKey,
+    LossyCoercion,
+    SensitiveOutput,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WarningKind::ShadowedKey => "shadowed-key",
+            WarningKind::DeprecatedKey => "deprecated-key",
+            WarningKind::UnusedKey => "unused-key",
+            WarningKind::LossyCoercion => "lossy-coercion",
+            WarningKind::SensitiveOutput => "sensitive-output",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Warning {
+    pub key: String,
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WARNING: {}: {} [{}]", self.key, self.message, self.kind)
+    }
+}
+
+/// Something about how a render read the config that isn't likely mistaken
+/// but easy to miss, e.g. a table it left out of the context.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Notice {
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for Notice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NOTICE: {}: {}", self.key, self.message)
+    }
+}
+
+/// A key templates should no longer read, written `key` or
+/// `key=replacement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedKey {
+    pub key: String,
+    pub replacement: Option<String>,
+}
+
+impl FromStr for DeprecatedKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, replacement) = match s.split_once('=') {
+            Some((key, replacement)) => (key, Some(replacement.to_string())),
+            None => (s, None),
+        };
+        if key.is_empty() || replacement.as_deref() == Some("") {
+            return Err(format!("invalid deprecated key: {}", s));
+        }
+        Ok(DeprecatedKey {
+            key: key.to_string(),
+            replacement,
+        })
+    }
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+// Tables are merged across group paths, so only the values inside them can
+// be shadowed.
+fn check_shadowed(
+    path: &str,
+    earlier: &toml::value::Table,
+    later: &toml::value::Table,
+    sources: (&str, &str),
+    warnings: &mut Vec<Warning>,
+) {
+    for (key, value) in later {
+        let key_path = join_path(path, key);
+        match (earlier.get(key), value) {
+            (Some(toml::Value::Table(earlier_table)), toml::Value::Table(later_table)) => {
+                check_shadowed(&key_path, earlier_table, later_table, sources, warnings)
+            }
+            (Some(earlier_value), value) if earlier_value != value => warnings.push(Warning {
+                key: key_path,
+                kind: WarningKind::ShadowedKey,
+                message: format!("value of {} is shadowed by {}", sources.0, sources.1),
+            }),
+            _ => {}
+        }
+    }
+}
+
+// With first-wins precedence the later selection is the one shadowed.
+fn shadowed_keys(
+    toml_config: &Config,
+    options: &RenderOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), TmpTomlErr> {
+    let mut resolved: Vec<(String, toml::value::Table)> = Vec::new();
+    for group_path in &options.group_paths {
+        let values = flatten_sections(
+            toml_config,
+            group_path,
+            options.precedence,
+            options.include_siblings,
+        )?;
+        resolved.push((group_path.join("."), values.into_iter().collect()));
+    }
+    for (index, (later_name, later)) in resolved.iter().enumerate() {
+        for (earlier_name, earlier) in &resolved[..index] {
+            let sources = match options.precedence {
+                Precedence::FirstWins => (later_name.as_str(), earlier_name.as_str()),
+                Precedence::LastWins => (earlier_name.as_str(), later_name.as_str()),
+            };
+            check_shadowed("", earlier, later, sources, warnings);
+        }
+    }
+    Ok(())
+}
+
+fn lossy_coercions(path: &str, value: &toml::Value, warnings: &mut Vec<Warning>) {
+    let message = match value {
+        toml::Value::Float(float) if !float.is_finite() => {
+            format!("{} has no JSON equivalent and is passed as null", float)
+        }
+        toml::Value::Datetime(_) => "datetime is passed as a string".to_string(),
+        toml::Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                lossy_coercions(&format!("{}[{}]", path, index), value, warnings);
+            }
+            return;
+        }
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                lossy_coercions(&join_path(path, key), value, warnings);
+            }
+            return;
+        }
+        _ => return,
+    };
+    warnings.push(Warning {
+        key: path.to_string(),
+        kind: WarningKind::LossyCoercion,
+        message,
+    });
+}
+
+/// Collects the warnings of rendering `template_source` with the values of
+/// the group paths of the options, `template_values` being the context the
+/// template is rendered with. Unused keys are only reported when the options
+/// ask for them and every variable the template reads is known, i.e. it
+/// neither includes nor extends another template.
+pub(crate) fn collect_warnings(
+    toml_config: &Config,
+    group_values: &Group,
+    template_values: &ContextValues,
+    template_source: &str,
+    options: &RenderOptions,
+) -> Result<Vec<Warning>, TmpTomlErr> {
+    let mut warnings = Vec::new();
+    shadowed_keys(toml_config, options, &mut warnings)?;
+
+    for (key, value) in group_values {
+        lossy_coercions(key, value, &mut warnings);
+    }
+
+    let template = Template::new("template", None, template_source).map_err(|err| {
+        TeraRenderErr::InvalidTemplate(format!(
+            "Failed to parse template file with error: {}",
+            &err
+        ))
+    })?;
+    let (variables, complete) = template_variables(&template);
+    for deprecated_key in &options.deprecated_keys {
+        if variables.contains(&deprecated_key.key) {
+            warnings.push(Warning {
+                key: deprecated_key.key.clone(),
+                kind: WarningKind::DeprecatedKey,
+                message: match &deprecated_key.replacement {
+                    Some(replacement) => format!("key is deprecated, use {} instead", replacement),
+                    None => "key is deprecated".to_string(),
+                },
+            });
+        }
+    }
+    if options.warn_unused_keys && complete {
+        for key in template_values.keys() {
+            if !variables.contains(key) {
+                warnings.push(Warning {
+                    key: key.clone(),
+                    kind: WarningKind::UnusedKey,
+                    message: "value isn't read by the template".to_string(),
+                });
+            }
+        }
+    }
+    warnings.sort();
+    Ok(warnings)
+}
+
+/// Warns about the values of the sensitive keys that the rendered output
+/// contains, unless the output is a secret itself.
+pub(crate) fn sensitive_output_warnings(
+    sensitive_keys: BTreeSet<String>,
+    group_values: &Group,
+    output: &str,
+    secret_output: bool,
+) -> Vec<Warning> {
+    if secret_output {
+        return Vec::new();
+    }
+    sensitive_keys
+        .into_iter()
+        .filter(|key| {
+            sensitive_strings(group_values, key)
+                .iter()
+                .any(|value| output.contains(value.as_str()))
+        })
+        .map(|key| Warning {
+            key,
+            kind: WarningKind::SensitiveOutput,
+            message:
+                "sensitive value is written to an output that isn't secret, see --secret-output"
+                    .to_string(),
+        })
+        .collect()
+}
+
+/// Notes the tables of the levels the group paths descend through that are
+/// left out of the context, none when siblings are included. Tables another
+/// group path selects, or descends through, aren't noted.
+pub(crate) fn skipped_table_notices(
+    toml_config: &Config,
+    group_paths: &[GroupPath],
+    include_siblings: bool,
+) -> Vec<Notice> {
+    if include_siblings {
+        return Vec::new();
+    }
+    let selected: Vec<String> = group_paths
+        .iter()
+        .map(|group_path| group_path.join("."))
+        .collect();
+    let skipped: BTreeSet<String> = group_paths
+        .iter()
+        .flat_map(|group_path| skipped_tables(toml_config, group_path))
+        .filter(|table| {
+            !selected
+                .iter()
+                .any(|selected| selected == table || selected.starts_with(&format!("{}.", table)))
+        })
+        .collect();
+    skipped
+        .into_iter()
+        .map(|key| Notice {
+            key,
+            message: "table isn't selected and is left out of the context, see --include-siblings"
+                .to_string(),
+        })
+        .collect()
+}
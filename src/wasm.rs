@@ -0,0 +1,39 @@
+// wasm-bindgen bindings exposing in-memory rendering to JavaScript, so web
+// previews render exactly what tmptoml renders. Filesystem access is disabled
+// in this build.
+//
+//     import { renderStr } from "tmptoml";
+//     renderStr(config, template, ["qa", "system1"], '{"replicas": 3}', "de-DE");
+
+use crate::{
+    context::parse_context_json,
+    tmptoml::{self, RenderOptions, TmpTomlErr},
+};
+use wasm_bindgen::prelude::*;
+
+fn to_js_err(err: TmpTomlErr) -> JsError {
+    JsError::new(&format!("{:?}", err))
+}
+
+/// Renders a template string with the values of the group path of a config
+/// string. `context_json` is merged like `--context-json`.
+#[wasm_bindgen(js_name = renderStr)]
+pub fn render_str(
+    config: &str,
+    template: &str,
+    groups: Vec<String>,
+    context_json: Option<String>,
+    locale: Option<String>,
+) -> Result<String, JsError> {
+    let context_values = match context_json {
+        Some(json) => vec![parse_context_json(&json).map_err(|err| to_js_err(err.into()))?],
+        None => Vec::new(),
+    };
+    let options = RenderOptions {
+        group_paths: vec![groups],
+        context_values,
+        locale,
+        ..RenderOptions::default()
+    };
+    tmptoml::render_str(config, template, &options).map_err(to_js_err)
+}
@@ -0,0 +1,72 @@
+use tmptoml::{parse_group_path, render_str, RenderOptions, TmpTomlErr};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+[[qa.databases]]
+name = "primary"
+host = "10.0.0.1"
+port = 5432
+[qa.databases.tls]
+enabled = true
+[[qa.databases]]
+name = "replica"
+host = "10.0.0.2"
+port = 5433
+"#;
+
+fn render(template: &str, group_path: &str) -> Result<String, TmpTomlErr> {
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path(group_path)],
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, template, &options)
+}
+
+#[test]
+fn selectors_are_kept_whole_in_group_paths() {
+    assert_eq!(
+        parse_group_path("qa.hosts[ip=10.0.0.1].tls"),
+        ["qa", "hosts[ip=10.0.0.1]", "tls"]
+    );
+}
+
+#[test]
+fn selected_entry_is_promoted_into_the_context() {
+    let template = "{{ env }} {{ name }} {{ host }}:{{ port }} {{ tls.enabled }}";
+    assert_eq!(
+        render(template, "qa.databases[name=primary]").unwrap(),
+        "qa primary 10.0.0.1:5432 true"
+    );
+    assert_eq!(
+        render("{{ host }}", "qa.databases[port=5433]").unwrap(),
+        "10.0.0.2"
+    );
+    assert_eq!(
+        render("{{ name }}", r#"qa.databases[name="replica"]"#).unwrap(),
+        "replica"
+    );
+}
+
+#[test]
+fn entries_stay_iterable() {
+    let template = "{% for db in databases %}{{ db.name }} {% endfor %}";
+    assert_eq!(
+        render(template, "qa.databases[name=replica]").unwrap(),
+        "primary replica "
+    );
+}
+
+#[test]
+fn unmatched_selector_suggests_the_entries() {
+    match render("{{ host }}", "qa.databases[name=secondary]") {
+        Err(TmpTomlErr::GroupNotFound(unknown)) => {
+            assert_eq!(unknown.name, "databases[name=secondary]");
+            assert_eq!(
+                unknown.candidates,
+                ["databases[name=primary]", "databases[name=replica]"]
+            );
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
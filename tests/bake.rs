@@ -0,0 +1,53 @@
+use std::fs;
+use tmptoml::{
+    bake, parse_group_path, read_baked, render_baked, Baked, BatchOptions, Bundle, RenderOptions,
+};
+
+#[test]
+fn baked_executables_render_their_payload() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-bake-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let executable = dir.join("tmptoml");
+    fs::write(&executable, b"\x7fELF not really an executable").unwrap();
+    assert_eq!(read_baked(&executable).unwrap(), None);
+
+    let baked = Baked {
+        config: "[qa.system1]\nhost = \"db\"\n[qa.system2]\nhost = \"db2\"\n".to_string(),
+        bundle: Bundle {
+            templates: vec![("host.conf".to_string(), "host={{ host }}".to_string())],
+        },
+        group_paths: vec![parse_group_path("qa.system1")],
+    };
+    bake(&executable, &baked, &dir.join("edge")).unwrap();
+    let read = read_baked(&dir.join("edge")).unwrap().unwrap();
+    assert_eq!(read, baked);
+    assert!(bake(&dir.join("edge"), &baked, &dir.join("edge2")).is_err());
+
+    let out = dir.join("out");
+    render_baked(
+        &read,
+        &out,
+        &[],
+        &BatchOptions::default(),
+        RenderOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(out.join("qa.system1/host.conf")).unwrap(),
+        "host=db"
+    );
+    render_baked(
+        &read,
+        &out,
+        &[parse_group_path("qa.system2")],
+        &BatchOptions::default(),
+        RenderOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        fs::read_to_string(out.join("qa.system2/host.conf")).unwrap(),
+        "host=db2"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,317 @@
+use std::{fs, path::PathBuf, process::Command, time::Duration};
+use tmptoml::{
+    clean_manifest, outdated_outputs, parse_group_path, prune_output_dir, render_batch, BatchErr,
+    BatchOptions, Cleaned, Manifest, ManifestEntry, Outdated, RenderOptions, TmpTomlErr,
+};
+
+fn batch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tmptoml-batch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("templates")).unwrap();
+    fs::write(
+        dir.join("config.toml"),
+        "[qa.system1]\nhost = \"db\"\n[qa.system2]\nport = 5432\n",
+    )
+    .unwrap();
+    fs::write(dir.join("templates/host.txt"), "{{ host }}").unwrap();
+    fs::write(dir.join("templates/static.txt"), "static").unwrap();
+    dir
+}
+
+#[test]
+fn continue_on_error_writes_the_files_that_render() {
+    let dir = batch_dir("continue");
+    let batch = render_batch(
+        &dir.join("config.toml"),
+        &dir.join("templates"),
+        &dir.join("out"),
+        &[
+            parse_group_path("qa.system1"),
+            parse_group_path("qa.system2"),
+        ],
+        &BatchOptions {
+            continue_on_error: true,
+            ..BatchOptions::default()
+        },
+        RenderOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(batch.outputs.len(), 3);
+    assert_eq!(batch.failures.len(), 1);
+    assert_eq!(batch.failures[0].template, "host.txt");
+    assert_eq!(batch.failures[0].group_path, ["qa", "system2"]);
+    assert!(dir.join("out/qa.system2/static.txt").exists());
+    assert!(!dir.join("out/qa.system2/host.txt").exists());
+
+    let report = batch.report();
+    assert_eq!(report["success"], false);
+    assert_eq!(report["failures"][0]["group"], "qa.system2");
+    assert_eq!(report["outputs"].as_array().unwrap().len(), 3);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn a_failed_render_aborts_the_batch_by_default() {
+    let dir = batch_dir("abort");
+    let result = render_batch(
+        &dir.join("config.toml"),
+        &dir.join("templates"),
+        &dir.join("out"),
+        &[
+            parse_group_path("qa.system1"),
+            parse_group_path("qa.system2"),
+        ],
+        &BatchOptions::default(),
+        RenderOptions::default(),
+    );
+    assert!(result.is_err());
+    assert!(!dir.join("out").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn a_render_taking_too_long_times_out() {
+    let dir = batch_dir("timeout");
+    fs::write(
+        dir.join("templates/loop.txt"),
+        "{% for i in range(end=100000000) %}{{ i }}{% endfor %}",
+    )
+    .unwrap();
+    let batch = render_batch(
+        &dir.join("config.toml"),
+        &dir.join("templates"),
+        &dir.join("out"),
+        &[parse_group_path("qa.system1")],
+        &BatchOptions {
+            continue_on_error: true,
+            timeout_per_file: Some(Duration::from_millis(200)),
+            ..BatchOptions::default()
+        },
+        RenderOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(batch.outputs.len(), 2);
+    assert_eq!(batch.failures.len(), 1);
+    assert_eq!(batch.failures[0].template, "loop.txt");
+    assert!(matches!(
+        batch.failures[0].error,
+        TmpTomlErr::Batch(BatchErr::TimedOut(_))
+    ));
+    // The abandoned render never writes its file.
+    assert!(!dir.join("out/qa.system1/loop.txt").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+fn batch_stderr(name: &str, extra: &[&str]) -> String {
+    let dir = batch_dir(name);
+    let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .current_dir(&dir)
+        .args([
+            "batch",
+            "config.toml",
+            "templates",
+            "-s",
+            "qa.system1",
+            "-o",
+            "out",
+        ])
+        .args(extra)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(dir).unwrap();
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn skipped_tables_are_summed_up_on_stderr() {
+    assert_eq!(
+        batch_stderr("notices", &[]),
+        "NOTICE: 1 table(s) are left out of the context, see --explain\n"
+    );
+}
+
+#[test]
+fn skipped_tables_are_listed_with_explain() {
+    assert_eq!(
+        batch_stderr("explain", &["--explain"]),
+        "NOTICE: qa.system2: table isn't selected and is left out of the context, \
+        see --include-siblings\n"
+    );
+}
+
+#[test]
+fn parallel_jobs_report_files_in_order() {
+    let dir = batch_dir("jobs");
+    for index in 0..8 {
+        fs::write(
+            dir.join(format!("templates/file{}.txt", index)),
+            "{{ host }}",
+        )
+        .unwrap();
+    }
+    let batch = render_batch(
+        &dir.join("config.toml"),
+        &dir.join("templates"),
+        &dir.join("out"),
+        &[parse_group_path("qa.system1")],
+        &BatchOptions {
+            jobs: 4,
+            ordered: true,
+            ..BatchOptions::default()
+        },
+        RenderOptions::default(),
+    )
+    .unwrap();
+    let templates: Vec<&str> = batch
+        .outputs
+        .iter()
+        .map(|output| output.template.as_str())
+        .collect();
+    assert_eq!(templates.len(), 10);
+    let mut sorted = templates.clone();
+    sorted.sort_unstable();
+    assert_eq!(templates, sorted);
+    assert_eq!(
+        fs::read_to_string(dir.join("out/qa.system1/file7.txt")).unwrap(),
+        "db"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn files_no_longer_generated_are_cleaned_from_the_manifest() {
+    let dir = batch_dir("manifest");
+    let manifest_path = dir.join("manifest.json");
+    let run = |dir: &PathBuf| {
+        let batch = render_batch(
+            &dir.join("config.toml"),
+            &dir.join("templates"),
+            &dir.join("out"),
+            &[parse_group_path("qa.system1")],
+            &BatchOptions::default(),
+            RenderOptions::default(),
+        )
+        .unwrap();
+        let previous = Manifest::read(&manifest_path).unwrap();
+        Manifest::from_batch(&batch, &previous)
+            .unwrap()
+            .write(&manifest_path)
+            .unwrap();
+    };
+    run(&dir);
+    fs::remove_file(dir.join("templates/static.txt")).unwrap();
+    run(&dir);
+
+    let manifest = Manifest::read(&manifest_path).unwrap();
+    assert_eq!(manifest.files.len(), 1);
+    assert_eq!(manifest.stale.len(), 1);
+    let stale = dir.join("out/qa.system1/static.txt");
+    assert_eq!(
+        clean_manifest(&manifest_path).unwrap(),
+        [Cleaned::Removed(stale.display().to_string())]
+    );
+    assert!(!stale.exists());
+    assert!(dir.join("out/qa.system1/host.txt").exists());
+    assert!(Manifest::read(&manifest_path).unwrap().stale.is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn outputs_of_changed_templates_are_outdated() {
+    let dir = batch_dir("outdated");
+    let manifest_path = dir.join("manifest.json");
+    fs::write(
+        dir.join("templates/page.txt"),
+        "{% include \"static.txt\" %}",
+    )
+    .unwrap();
+    let batch = render_batch(
+        &dir.join("config.toml"),
+        &dir.join("templates"),
+        &dir.join("out"),
+        &[parse_group_path("qa.system1")],
+        &BatchOptions::default(),
+        RenderOptions::default(),
+    )
+    .unwrap();
+    Manifest::from_batch(&batch, &Manifest::default())
+        .unwrap()
+        .write(&manifest_path)
+        .unwrap();
+    assert!(outdated_outputs(&manifest_path).unwrap().is_empty());
+
+    fs::write(dir.join("templates/static.txt"), "changed").unwrap();
+    fs::remove_file(dir.join("out/qa.system1/host.txt")).unwrap();
+    let output = |name: &str| dir.join("out/qa.system1").join(name).display().to_string();
+    let input = dir.join("templates/static.txt").display().to_string();
+    assert_eq!(
+        outdated_outputs(&manifest_path).unwrap(),
+        [
+            Outdated::OutputMissing(output("host.txt")),
+            Outdated::InputChanged {
+                output: output("static.txt"),
+                input: input.clone(),
+            },
+            Outdated::InputChanged {
+                output: output("page.txt"),
+                input,
+            },
+        ]
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn prune_removes_only_the_files_the_previous_manifest_lists() {
+    let dir = batch_dir("prune");
+    let out = dir.join("out");
+    let render = |group_paths: &[&str]| {
+        let group_paths: Vec<_> = group_paths
+            .iter()
+            .map(|path| parse_group_path(path))
+            .collect();
+        let batch = render_batch(
+            &dir.join("config.toml"),
+            &dir.join("templates"),
+            &out,
+            &group_paths,
+            &BatchOptions {
+                continue_on_error: true,
+                ..BatchOptions::default()
+            },
+            RenderOptions::default(),
+        )
+        .unwrap();
+        Manifest::from_batch(&batch, &Manifest::default()).unwrap()
+    };
+    let previous = render(&["qa.system1", "qa.system2"]);
+    fs::create_dir_all(out.join(".git")).unwrap();
+    fs::write(out.join(".git/HEAD"), "ref").unwrap();
+    fs::write(out.join("notes.txt"), "kept by hand").unwrap();
+    fs::write(out.join("qa.system2/README.md"), "kept").unwrap();
+    fs::write(out.join("qa.system2/host.txt"), "db").unwrap();
+    let current = render(&["qa.system1"]);
+
+    let pruned = prune_output_dir(&out, &previous, &current, &["*.md".to_string()]).unwrap();
+    assert_eq!(pruned, vec![out.join("qa.system2/static.txt")]);
+    assert!(out.join(".git/HEAD").exists());
+    assert!(out.join("notes.txt").exists());
+    assert!(out.join("qa.system2/README.md").exists());
+    assert!(out.join("qa.system2/host.txt").exists());
+    assert!(out.join("qa.system1/host.txt").exists());
+
+    // A listed file in a hidden directory is never removed either.
+    let hidden = Manifest {
+        files: vec![ManifestEntry {
+            path: out.join(".git/HEAD").display().to_string(),
+            hash: String::new(),
+            inputs: Vec::new(),
+        }],
+        stale: Vec::new(),
+    };
+    assert!(prune_output_dir(&out, &hidden, &current, &[])
+        .unwrap()
+        .is_empty());
+    assert!(out.join(".git/HEAD").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
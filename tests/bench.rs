@@ -0,0 +1,39 @@
+use std::{fs, num::NonZeroUsize, time::Duration};
+use tmptoml::{bench_template, parse_group_path, percentile, RenderOptions};
+
+#[test]
+fn every_iteration_is_timed() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), "[qa.system1]\nitems = [1, 2, 3]\n").unwrap();
+    fs::write(
+        dir.join("template"),
+        "{% for item in items %}{{ item }}{% endfor %}",
+    )
+    .unwrap();
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        ..RenderOptions::default()
+    };
+    let bench = bench_template(
+        &dir.join("config.toml"),
+        &dir.join("template"),
+        &options,
+        NonZeroUsize::new(5).unwrap(),
+    )
+    .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(bench.compile.len(), 5);
+    assert_eq!(bench.render.len(), 5);
+    assert!(bench.render.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(bench.to_string().starts_with("iterations: 5\n"));
+}
+
+#[test]
+fn percentiles_are_nearest_ranks() {
+    let durations: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+    assert_eq!(percentile(&durations, 50), Duration::from_millis(5));
+    assert_eq!(percentile(&durations, 90), Duration::from_millis(9));
+    assert_eq!(percentile(&durations, 99), Duration::from_millis(10));
+    assert_eq!(percentile(&[], 50), Duration::ZERO);
+}
@@ -0,0 +1,83 @@
+use std::fs;
+use tmptoml::{
+    parse_group_path, render_bundled_template, Bundle, LoadedConfig, RenderOptions, TemplateFilter,
+};
+
+#[test]
+fn bundled_templates_render_like_the_template_directory() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-bundle-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("templates/k8s")).unwrap();
+    fs::write(
+        dir.join("templates/k8s/deployment.yaml"),
+        "{% include \"labels.yaml\" %}replicas: {{ replicas }}",
+    )
+    .unwrap();
+    fs::write(dir.join("templates/labels.yaml"), "env: {{ env }}\n").unwrap();
+
+    let bundle = Bundle::from_dir(&dir.join("templates"), &TemplateFilter::default()).unwrap();
+    bundle.write(&dir.join("templates.tpak")).unwrap();
+    let bundle = Bundle::read(&dir.join("templates.tpak")).unwrap();
+    let renderer = bundle.renderer(RenderOptions::default()).unwrap();
+    let config = LoadedConfig::parse("[qa]\nenv = \"qa\"\n[qa.system1]\nreplicas = 2\n").unwrap();
+    assert_eq!(
+        renderer
+            .render(
+                "k8s/deployment.yaml",
+                &config,
+                &[parse_group_path("qa.system1")]
+            )
+            .unwrap(),
+        "env: qa\nreplicas: 2"
+    );
+
+    fs::write(dir.join("templates/broken.yaml"), "{{ unclosed").unwrap();
+    assert!(Bundle::from_dir(&dir.join("templates"), &TemplateFilter::default()).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn bundled_renders_only_compile_the_templates_they_use() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-bundle-used-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("config.toml"),
+        "[qa]\nenv = \"qa\"\n[qa.system1]\nreplicas = 2\n",
+    )
+    .unwrap();
+    let template = |name: &str, source: &str| (name.to_string(), source.to_string());
+    let bundle = Bundle {
+        templates: vec![
+            template("base.yaml", "{% block body %}{% endblock body %}"),
+            template(
+                "deployment.yaml",
+                "{% extends \"base.yaml\" %}{% import \"macros.yaml\" as macros %}\
+                {% block body %}{{ macros::replicas() }}{% endblock body %}",
+            ),
+            template(
+                "macros.yaml",
+                "{% macro replicas() %}{% include \"labels.yaml\" %}\
+                replicas: {{ replicas }}{% endmacro replicas %}",
+            ),
+            template("labels.yaml", "env: {{ env }}\n"),
+            // Bundles read from a file aren't checked to compile.
+            template("broken.yaml", "{{ unclosed"),
+        ],
+    };
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        ..RenderOptions::default()
+    };
+    let rendered = render_bundled_template(
+        &dir.join("config.toml"),
+        &bundle,
+        "deployment.yaml",
+        &options,
+    );
+    let broken =
+        render_bundled_template(&dir.join("config.toml"), &bundle, "broken.yaml", &options);
+    fs::remove_dir_all(dir).unwrap();
+    assert_eq!(rendered.unwrap().output, "env: qa\nreplicas: 2");
+    assert!(broken.is_err());
+}
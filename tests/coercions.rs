@@ -0,0 +1,65 @@
+use std::fs;
+use tmptoml::{
+    export_group_with_coercions, parse_group_path, render_template_with_warnings, Coercion,
+    ExportFormat, RenderOptions,
+};
+
+const CONFIG: &str = r#"
+[qa.system1]
+name = "db"
+replicas = 2
+released = 2021-06-01T10:00:00Z
+[qa.system1.limits]
+ratio = inf
+"#;
+
+fn coercion(key: &str, from: &'static str, to: &'static str) -> Coercion {
+    Coercion {
+        key: key.to_string(),
+        from,
+        to,
+    }
+}
+
+#[test]
+fn values_coerced_into_the_template_context_are_reported() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-coercions-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(dir.join("template"), "{{ name }} {{ released }}").unwrap();
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        ..RenderOptions::default()
+    };
+    let rendered =
+        render_template_with_warnings(&dir.join("config.toml"), &dir.join("template"), &options)
+            .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        rendered.coercions,
+        vec![
+            coercion("limits.ratio", "float", "null"),
+            coercion("released", "datetime", "string"),
+        ]
+    );
+}
+
+#[test]
+fn values_exported_as_shell_strings_are_reported() {
+    let path = std::env::temp_dir().join(format!("tmptoml-coercions-{}.toml", std::process::id()));
+    fs::write(&path, CONFIG).unwrap();
+    let exported =
+        export_group_with_coercions(&path, "qa", "system1", ExportFormat::Shell, false).unwrap();
+    let toml =
+        export_group_with_coercions(&path, "qa", "system1", ExportFormat::Toml, false).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        exported.coercions,
+        vec![
+            coercion("limits.ratio", "float", "string"),
+            coercion("released", "datetime", "string"),
+            coercion("replicas", "integer", "string"),
+        ]
+    );
+    assert!(toml.coercions.is_empty());
+}
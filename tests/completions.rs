@@ -0,0 +1,160 @@
+use std::{fs, process::Command};
+use tmptoml::{complete_group_ids, complete_group_path, parse_toml_str};
+
+const CONFIG: &str = r#"
+[_defaults]
+group = "qa"
+[qa]
+env = "qa"
+password = { value = "hunter2", sensitive = true }
+[qa.system1]
+host = "db"
+[qa.system2]
+host = "cache"
+[qa.storage]
+bucket = "b"
+[qualification.system1]
+host = "q"
+[production.system1]
+host = "p"
+"#;
+
+fn tmptoml(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .args(args)
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// The names listed under a heading of clap's help, e.g. `OPTIONS:`.
+fn help_section(help: &str, heading: &str) -> Vec<String> {
+    help.lines()
+        .skip_while(|line| *line != heading)
+        .skip(1)
+        .take_while(|line| line.starts_with(' '))
+        .filter_map(|line| {
+            line.split_whitespace()
+                .map(|word| word.trim_end_matches(','))
+                .find(|word| !word.starts_with('-') || word.starts_with("--"))
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+#[test]
+fn bash_group_completion_skips_the_values_of_every_render_option() {
+    let script = tmptoml(&["completions", "bash"]);
+    // The patterns of the options the group completion skips over.
+    let skipped: Vec<&str> = script
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "case \"$word\" in")
+        .take_while(|line| *line != "esac")
+        .filter_map(|line| line.split_once(')'))
+        .flat_map(|(patterns, _)| patterns.split('|'))
+        .collect();
+    assert!(skipped.contains(&"--passthrough-markers"));
+    for subcommand in ["render", "render-config"] {
+        let options = help_section(&tmptoml(&[subcommand, "--help"]), "OPTIONS:");
+        assert!(options.contains(&"--precedence".to_string()));
+        for option in options.iter().filter(|option| *option != "--group") {
+            assert!(
+                skipped.contains(&option.as_str()),
+                "{} isn't skipped",
+                option
+            );
+        }
+    }
+}
+
+#[test]
+fn bash_group_completion_dispatches_every_subcommand() {
+    let script = tmptoml(&["completions", "bash"]);
+    let dispatched: Vec<&str> = script
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "case \"${positional[0]}\" in")
+        .take_while(|line| *line != "esac")
+        .filter_map(|line| line.split_once(')'))
+        .flat_map(|(patterns, _)| patterns.split('|'))
+        .collect();
+    let subcommands = help_section(&tmptoml(&["--help"]), "SUBCOMMANDS:");
+    for subcommand in ["eval", "plan", "bench", "rename-key"] {
+        assert!(subcommands.contains(&subcommand.to_string()));
+    }
+    for subcommand in &subcommands {
+        assert!(
+            dispatched.contains(&subcommand.as_str()),
+            "{} isn't dispatched",
+            subcommand
+        );
+    }
+}
+
+fn ids(group_path: &[&str], partial: &str) -> Vec<String> {
+    let group_path: Vec<String> = group_path.iter().map(|id| id.to_string()).collect();
+    complete_group_ids(&parse_toml_str(CONFIG).unwrap(), &group_path, partial)
+}
+
+#[test]
+fn group_ids_complete_by_prefix_at_the_first_level() {
+    assert_eq!(ids(&[], ""), ["production", "qa", "qualification"]);
+    assert_eq!(ids(&[], "q"), ["qa", "qualification"]);
+    assert_eq!(ids(&[], "qa"), ["qa"]);
+    assert!(ids(&[], "staging").is_empty());
+}
+
+#[test]
+fn group_ids_complete_by_prefix_at_the_second_level() {
+    assert_eq!(ids(&["qa"], ""), ["storage", "system1", "system2"]);
+    assert_eq!(ids(&["qa"], "sys"), ["system1", "system2"]);
+    assert_eq!(ids(&["qualification"], "s"), ["system1"]);
+    assert!(ids(&["qa"], "pass").is_empty());
+    assert!(ids(&["staging"], "").is_empty());
+    assert!(ids(&["qa", "system1"], "").is_empty());
+}
+
+#[test]
+fn dotted_group_paths_complete_in_full() {
+    let toml_config = parse_toml_str(CONFIG).unwrap();
+    assert_eq!(
+        complete_group_path(&toml_config, "q"),
+        ["qa", "qualification"]
+    );
+    assert_eq!(
+        complete_group_path(&toml_config, "qa.sys"),
+        ["qa.system1", "qa.system2"]
+    );
+    assert_eq!(
+        complete_group_path(&toml_config, "qa."),
+        ["qa.storage", "qa.system1", "qa.system2"]
+    );
+}
+
+#[test]
+fn complete_command_lists_the_candidates() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-complete-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    fs::write(&config, CONFIG).unwrap();
+    let config = config.to_str().unwrap();
+    let first = tmptoml(&["__complete", config, "q"]);
+    let second = tmptoml(&["__complete", config, "qa", "sys"]);
+    let dotted = tmptoml(&["__complete", "--dotted", config, "qa.st"]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(first, "qa\nqualification\n");
+    assert_eq!(second, "system1\nsystem2\n");
+    assert_eq!(dotted, "qa.storage\n");
+}
+
+#[test]
+fn complete_command_completes_nothing_without_a_readable_config() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .args(["__complete", "missing.toml", "q"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\n");
+    assert!(output.stderr.is_empty());
+}
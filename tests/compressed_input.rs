@@ -0,0 +1,115 @@
+#[cfg(feature = "compression")]
+use std::{fs, io::Read, process::Command};
+use tmptoml::{base64_encode, encode_output, OutputEncoding};
+#[cfg(feature = "compression")]
+use tmptoml::{render_template, Compression};
+
+#[cfg(feature = "compression")]
+#[test]
+fn gzip_compressed_configs_and_templates_are_decompressed() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-compressed-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), "[qa.system1]\nhost = \"db\"\n").unwrap();
+    fs::write(dir.join("template.txt"), "host={{ host }}").unwrap();
+    for file in ["config.toml", "template.txt"] {
+        let status = Command::new("gzip").arg(dir.join(file)).status().unwrap();
+        assert!(status.success());
+    }
+    let rendered = render_template(
+        &dir.join("config.toml.gz"),
+        &dir.join("template.txt.gz"),
+        "qa".to_string(),
+        "system1".to_string(),
+    )
+    .unwrap();
+    assert_eq!(rendered, "host=db");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn zstd_compressed_configs_are_decompressed() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-zstd-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config = zstd::encode_all(&b"[qa.system1]\nhost = \"db\"\n"[..], 3).unwrap();
+    fs::write(dir.join("config.toml.zst"), config).unwrap();
+    fs::write(dir.join("template.txt"), "host={{ host }}").unwrap();
+    let rendered = render_template(
+        &dir.join("config.toml.zst"),
+        &dir.join("template.txt"),
+        "qa".to_string(),
+        "system1".to_string(),
+    )
+    .unwrap();
+    assert_eq!(rendered, "host=db");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn decompressed_files_that_arent_utf8_are_reported_as_such() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-not-utf8-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config = zstd::encode_all(&b"[qa.system1]\nhost = \"db\"\n"[..], 3).unwrap();
+    fs::write(dir.join("config.toml.zst"), config).unwrap();
+    let template = zstd::encode_all(&b"host=\xff{{ host }}"[..], 3).unwrap();
+    fs::write(dir.join("template.txt.zst"), template).unwrap();
+    let rendered = render_template(
+        &dir.join("config.toml.zst"),
+        &dir.join("template.txt.zst"),
+        "qa".to_string(),
+        "system1".to_string(),
+    );
+    let path = dir.join("template.txt.zst").display().to_string();
+    fs::remove_dir_all(dir).unwrap();
+    assert_eq!(
+        format!("{:?}", rendered.unwrap_err()),
+        format!("Render(InvalidUtf8({:?}))", path)
+    );
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compressed_output_round_trips() {
+    let output = encode_output(b"host=db".to_vec(), Some(Compression::Gzip), None).unwrap();
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(&output[..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, b"host=db");
+    assert_eq!(
+        encode_output(b"host=db".to_vec(), Some(Compression::Gzip), None).unwrap(),
+        output
+    );
+    let output = encode_output(b"host=db".to_vec(), Some(Compression::Zstd), None).unwrap();
+    assert_eq!(zstd::decode_all(&output[..]).unwrap(), b"host=db");
+}
+
+#[test]
+fn base64_output_is_padded() {
+    assert_eq!(base64_encode(b"host=db"), "aG9zdD1kYg==");
+    assert_eq!(base64_encode(b"ab"), "YWI=");
+    assert_eq!(base64_encode(b"abc"), "YWJj");
+    assert_eq!(base64_encode(b""), "");
+    assert_eq!(base64_encode(b"a"), "YQ==");
+    assert_eq!(base64_encode(&[0xff, 0xfe, 0xfd, 0xfc]), "//79/A==");
+    let output = encode_output(b"ab".to_vec(), None, Some(OutputEncoding::Base64)).unwrap();
+    assert_eq!(output, b"YWI=");
+}
+
+#[test]
+fn utf16_output_starts_with_a_byte_order_mark() {
+    let output = encode_output(
+        "hé😀".as_bytes().to_vec(),
+        None,
+        Some(OutputEncoding::Utf16LeBom),
+    )
+    .unwrap();
+    assert_eq!(
+        output,
+        [0xff, 0xfe, b'h', 0x00, 0xe9, 0x00, 0x3d, 0xd8, 0x00, 0xde]
+    );
+}
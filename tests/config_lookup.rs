@@ -0,0 +1,45 @@
+use tmptoml::{parse_group_path, render_str, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa.system1]
+host = "db.qa"
+
+[prod.system1]
+host = "db.prod"
+replicas = [1, 2]
+"#;
+
+fn options(config_lookup: bool) -> RenderOptions {
+    RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        config_lookup,
+        ..RenderOptions::default()
+    }
+}
+
+#[test]
+fn templates_read_other_groups_when_allowed() {
+    let rendered = render_str(
+        CONFIG,
+        r#"{{ host }} {{ config(path="prod.system1.host") }} {{ config(path="prod.system1.replicas") | length }}"#,
+        &options(true),
+    )
+    .unwrap();
+    assert_eq!(rendered, "db.qa db.prod 2");
+    assert!(render_str(
+        CONFIG,
+        r#"{{ config(path="prod.system9.host") }}"#,
+        &options(true)
+    )
+    .is_err());
+}
+
+#[test]
+fn lookups_are_refused_unless_allowed() {
+    assert!(render_str(
+        CONFIG,
+        r#"{{ config(path="prod.system1.host") }}"#,
+        &options(false)
+    )
+    .is_err());
+}
@@ -0,0 +1,45 @@
+use tmptoml::{group_template, parse_toml_str, resolve_group_path};
+
+const CONFIG: &str = r#"
+[qa]
+_template = "service.conf.tera"
+host = "db"
+
+[qa.system1]
+port = 1
+
+[qa.system2]
+_template = "legacy.conf.tera"
+port = 2
+"#;
+
+fn path(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn nested_groups_override_the_template_of_their_parent() {
+    let config = parse_toml_str(CONFIG).unwrap();
+    assert_eq!(
+        group_template(&config, &[path(&["qa", "system1"])]).unwrap(),
+        Some("service.conf.tera".to_string())
+    );
+    assert_eq!(
+        group_template(&config, &[path(&["qa", "system2"])]).unwrap(),
+        Some("legacy.conf.tera".to_string())
+    );
+}
+
+#[test]
+fn the_template_is_not_a_value_of_the_group() {
+    let config = parse_toml_str(CONFIG).unwrap();
+    let values = resolve_group_path(&config, &path(&["qa", "system2"])).unwrap();
+    assert!(!values.contains_key("_template"));
+    assert_eq!(values.len(), 2);
+}
+
+#[test]
+fn non_string_templates_are_refused() {
+    let config = parse_toml_str("[qa]\n_template = 1\n").unwrap();
+    assert!(group_template(&config, &[path(&["qa"])]).is_err());
+}
@@ -0,0 +1,64 @@
+use tmptoml::{parse_group_path, render_str, ContextErr, ContextLimits, RenderOptions, TmpTomlErr};
+
+const CONFIG: &str = r#"
+[qa.system1]
+host = "db"
+[qa.system1.pool]
+size = 10
+[qa.system1.pool.timeouts]
+connect = 5
+"#;
+
+fn render(limits: ContextLimits) -> Result<String, TmpTomlErr> {
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        context_limits: limits,
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, "{{ host }}", &options)
+}
+
+fn limit_exceeded(result: Result<String, TmpTomlErr>) -> String {
+    match result {
+        Err(TmpTomlErr::Context(ContextErr::LimitExceeded(reason))) => reason,
+        other => panic!("expected a context limit error, got {:?}", other),
+    }
+}
+
+#[test]
+fn contexts_within_the_limits_render() {
+    let limits = ContextLimits {
+        max_keys: Some(5),
+        max_depth: Some(3),
+        max_bytes: Some(1024),
+    };
+    assert_eq!(render(limits).unwrap(), "db");
+}
+
+#[test]
+fn contexts_exceeding_a_limit_fail() {
+    let keys = ContextLimits {
+        max_keys: Some(4),
+        ..ContextLimits::default()
+    };
+    assert_eq!(
+        limit_exceeded(render(keys)),
+        "the context has more than 4 keys"
+    );
+    let depth = ContextLimits {
+        max_depth: Some(2),
+        ..ContextLimits::default()
+    };
+    assert_eq!(
+        limit_exceeded(render(depth)),
+        "the context is nested more than 2 levels deep"
+    );
+    let bytes = ContextLimits {
+        max_bytes: Some(16),
+        ..ContextLimits::default()
+    };
+    assert_eq!(
+        limit_exceeded(render(bytes)),
+        "the context is larger than 16 bytes"
+    );
+}
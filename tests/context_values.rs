@@ -0,0 +1,399 @@
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+use tmptoml::{apply_override, run_context_command, ContextErr, ContextValues};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+[qa.system1]
+replicas = 1
+hosts = ["db1"]
+[qa.system1.labels]
+team = "platform"
+tier = "backend"
+"#;
+
+fn project(name: &str, template: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tmptoml-context-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(dir.join("template"), template).unwrap();
+    dir
+}
+
+// Renders `qa system1` in the project directory, piping `stdin` in.
+fn render(dir: &PathBuf, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .current_dir(dir)
+        .args(["render", "config.toml", "template", "qa", "system1"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn context_json_is_merged_with_the_highest_precedence() {
+    let dir = project(
+        "json",
+        "{{ env }} {{ replicas }} {{ hosts | json_encode() }} {{ labels | json_encode() }} {{ extra.ports | json_encode() }}",
+    );
+    fs::write(
+        dir.join("extra.json"),
+        r#"{"replicas": 2, "labels": {"tier": "frontend", "zone": "a"}}"#,
+    )
+    .unwrap();
+    let output = render(
+        &dir,
+        &[
+            "--context-cmd",
+            "cat extra.json",
+            "--set",
+            "replicas=3",
+            "--context-json",
+            r#"{"replicas": 4, "hosts": ["web1", "web2"], "labels": {"team": "core"}}"#,
+            "--context-json",
+            r#"{"extra": {"ports": [80, {"tls": 443}]}}"#,
+        ],
+        "",
+    );
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        stdout(&output),
+        concat!(
+            r#"qa 4 ["web1","web2"] {"team":"core","tier":"frontend","zone":"a"} "#,
+            r#"[80,{"tls":443}]"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn context_json_must_be_an_object() {
+    let dir = project("json-invalid", "{{ env }}");
+    let not_an_object = render(&dir, &["--context-json", "[1, 2]"], "");
+    let invalid = render(&dir, &["--context-json", "{\"replicas\": "], "");
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(stdout(&not_an_object).contains("NotAnObject(\"[1,2]\")"));
+    assert!(stdout(&invalid).contains("InvalidJson"));
+}
+
+fn overridden(assignments: &[(&str, bool)]) -> Result<ContextValues, ContextErr> {
+    let mut values = ContextValues::new();
+    for (assignment, typed) in assignments {
+        apply_override(&mut values, assignment, *typed)?;
+    }
+    Ok(values)
+}
+
+#[test]
+fn set_parses_values_as_toml() {
+    let values = overridden(&[
+        ("debug=true", true),
+        ("port=8080", true),
+        ("ratio=0.5", true),
+        ("name=web", true),
+        ("quoted=\"8080\"", true),
+        ("ports=[80, 443]", true),
+        ("empty=", true),
+    ])
+    .unwrap();
+    assert_eq!(
+        Value::Object(values),
+        json!({
+            "debug": true,
+            "port": 8080,
+            "ratio": 0.5,
+            "name": "web",
+            "quoted": "8080",
+            "ports": [80, 443],
+            "empty": "",
+        })
+    );
+}
+
+#[test]
+fn set_string_keeps_values_as_strings() {
+    let values = overridden(&[
+        ("port=8080", false),
+        ("debug=true", false),
+        ("ratio=nan", false),
+    ])
+    .unwrap();
+    assert_eq!(
+        Value::Object(values),
+        json!({"port": "8080", "debug": "true", "ratio": "nan"})
+    );
+}
+
+#[test]
+fn set_rejects_numbers_json_cannot_hold() {
+    for assignment in ["ratio=nan", "ratio=inf", "ratio=-inf", "ratio=+nan"] {
+        match overridden(&[(assignment, true)]) {
+            Err(ContextErr::InvalidOverride(invalid)) => assert_eq!(invalid, assignment),
+            other => panic!("{} was accepted: {:?}", assignment, other),
+        }
+    }
+}
+
+#[test]
+fn set_flags_type_the_rendered_values() {
+    let dir = project(
+        "set",
+        "{{ port + 1 }} {% if debug %}debug{% endif %} {{ version ~ \"!\" }} {{ replicas + 1 }}",
+    );
+    let output = render(
+        &dir,
+        &[
+            "--set",
+            "port=8080",
+            "--set",
+            "debug=true",
+            "--set-string",
+            "version=1.0",
+            "--set",
+            "replicas=2",
+        ],
+        "",
+    );
+    let nan = render(&dir, &["--set", "replicas=nan"], "");
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(stdout(&output), "8081 debug 1.0! 3\n");
+    assert_eq!(
+        stdout(&nan),
+        "ERROR: Unable to build the template context. Reason: InvalidOverride(\"replicas=nan\")\n"
+    );
+}
+
+#[test]
+fn set_appends_to_lists_with_brackets() {
+    let values = overridden(&[
+        ("hosts[]=a.example.com", true),
+        ("hosts[]=b.example.com", true),
+        ("ports[]=80", true),
+        ("ports[]=443", false),
+        ("database.replicas[]=r1", true),
+    ])
+    .unwrap();
+    assert_eq!(
+        Value::Object(values),
+        json!({
+            "hosts": ["a.example.com", "b.example.com"],
+            "ports": [80, "443"],
+            "database": {"replicas": ["r1"]},
+        })
+    );
+}
+
+#[test]
+fn set_nests_dotted_keys_into_existing_tables() {
+    let values = overridden(&[
+        ("database.host=db", true),
+        ("database.pool.size=10", true),
+        ("database.pool.timeout=30", true),
+        ("database.pool.size=20", true),
+    ])
+    .unwrap();
+    assert_eq!(
+        Value::Object(values),
+        json!({"database": {"host": "db", "pool": {"size": 20, "timeout": 30}}})
+    );
+}
+
+#[test]
+fn set_fails_on_paths_through_values_that_are_not_tables() {
+    for assignments in [
+        &[("database=db", true), ("database.pool.size=10", true)][..],
+        &[("database.pool=10", true), ("database.pool.size=10", true)],
+        &[("hosts[]=a", true), ("hosts.first=a", true)],
+    ] {
+        let last = assignments.last().unwrap().0;
+        match overridden(assignments) {
+            Err(ContextErr::InvalidOverride(invalid)) => assert_eq!(invalid, last),
+            other => panic!("{} was accepted: {:?}", last, other),
+        }
+    }
+    for invalid in ["database..size=1", ".size=1", "hosts[0]=a", "size"] {
+        assert!(overridden(&[(invalid, true)]).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn nested_overrides_merge_into_the_config_tables() {
+    let dir = project(
+        "nested",
+        "{{ labels | json_encode() }} {{ hosts | json_encode() }}",
+    );
+    let output = render(
+        &dir,
+        &[
+            "--set",
+            "labels.tier=frontend",
+            "--set",
+            "labels.owner.team=web",
+            "--set",
+            "hosts[]=web1",
+            "--set",
+            "hosts[]=web2",
+        ],
+        "",
+    );
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        stdout(&output),
+        concat!(
+            r#"{"owner":{"team":"web"},"team":"platform","tier":"frontend"} "#,
+            r#"["web1","web2"]"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn stdin_context_is_merged_into_the_context() {
+    let dir = project(
+        "stdin",
+        "{{ env }} {{ replicas }} {{ labels | json_encode() }}",
+    );
+    let json = render(
+        &dir,
+        &["--stdin-context", "json"],
+        r#"{"replicas": 3, "labels": {"zone": "a"}}"#,
+    );
+    let toml = render(
+        &dir,
+        &["--stdin-context", "toml", "--set", "replicas=4"],
+        "replicas = 3\n[labels]\nzone = \"b\"\n",
+    );
+    let invalid = render(&dir, &["--stdin-context", "json"], "replicas = 3");
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        stdout(&json),
+        "qa 3 {\"team\":\"platform\",\"tier\":\"backend\",\"zone\":\"a\"}\n"
+    );
+    assert_eq!(
+        stdout(&toml),
+        "qa 4 {\"team\":\"platform\",\"tier\":\"backend\",\"zone\":\"b\"}\n"
+    );
+    assert!(stdout(&invalid)
+        .starts_with("ERROR: Unable to build the template context. Reason: InvalidJson"));
+}
+
+#[test]
+fn batch_reads_the_stdin_context_once_for_every_group() {
+    let dir = project("stdin-batch", "");
+    fs::create_dir_all(dir.join("templates")).unwrap();
+    fs::write(dir.join("templates/site.conf"), "{{ env }} {{ region }}").unwrap();
+    fs::write(
+        dir.join("config.toml"),
+        "[qa.system1]\nenv = \"qa1\"\n[qa.system2]\nenv = \"qa2\"\n",
+    )
+    .unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .current_dir(&dir)
+        .args(["batch", "config.toml", "templates", "-o", "out"])
+        .args(["-s", "qa.system1", "-s", "qa.system2"])
+        .args(["--stdin-context", "json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(br#"{"region": "eu"}"#)
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let rendered: Vec<String> = ["qa.system1", "qa.system2"]
+        .iter()
+        .map(|group| fs::read_to_string(dir.join("out").join(group).join("site.conf")).unwrap())
+        .collect();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success());
+    assert_eq!(rendered, ["qa1 eu", "qa2 eu"]);
+}
+
+#[test]
+fn context_commands_print_json_or_toml() {
+    assert_eq!(
+        Value::Object(run_context_command(r#"echo '{"a": 1, "b": {"c": [true]}}'"#).unwrap()),
+        json!({"a": 1, "b": {"c": [true]}})
+    );
+    assert_eq!(
+        Value::Object(run_context_command(r#"printf 'a = 1\n[b]\nc = "d"\n'"#).unwrap()),
+        json!({"a": 1, "b": {"c": "d"}})
+    );
+    assert_eq!(
+        Value::Object(run_context_command("true").unwrap()),
+        json!({})
+    );
+}
+
+#[test]
+fn context_commands_failing_are_reported() {
+    match run_context_command("false") {
+        Err(ContextErr::CommandFailed(reason)) => assert_eq!(reason, "false: exit status: 1"),
+        other => panic!("expected a failed command, got {:?}", other),
+    }
+    match run_context_command("echo '{\"a\": 1}'; exit 3") {
+        Err(ContextErr::CommandFailed(reason)) => {
+            assert_eq!(reason, "echo '{\"a\": 1}'; exit 3: exit status: 3")
+        }
+        other => panic!("expected a failed command, got {:?}", other),
+    }
+}
+
+#[test]
+fn context_commands_printing_neither_json_nor_toml_are_reported() {
+    assert!(matches!(
+        run_context_command("echo '{\"a\": '"),
+        Err(ContextErr::InvalidJson(_))
+    ));
+    assert!(matches!(
+        run_context_command("echo 'not a document'"),
+        Err(ContextErr::InvalidToml(_))
+    ));
+    assert!(matches!(
+        run_context_command("echo '[1, 2]'"),
+        Err(ContextErr::InvalidToml(_))
+    ));
+}
+
+#[test]
+fn context_command_flag_merges_what_it_prints() {
+    let dir = project("cmd", "{{ replicas }} {{ labels.zone }}");
+    let merged = render(
+        &dir,
+        &[
+            "--context-cmd",
+            r#"echo '{"replicas": 5, "labels": {"zone": "b"}}'"#,
+        ],
+        "",
+    );
+    let failed = render(&dir, &["--context-cmd", "false"], "");
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(stdout(&merged), "5 b\n");
+    assert_eq!(
+        stdout(&failed),
+        "ERROR: Unable to build the template context. Reason: CommandFailed(\"false: exit status: 1\")\n"
+    );
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use tmptoml::{
+    config_stats, default_group_path, flatten_config_file, parse_toml_str, GROUP_ENV_VAR,
+    SECONDARY_ENV_VAR,
+};
+
+const CONFIG: &str = r#"
+[_defaults]
+group = "qa"
+secondary = "system1"
+
+[qa.system1]
+replicas = 2
+"#;
+
+// Environment variables are shared by the whole test binary, so every case
+// runs in this single test.
+#[test]
+fn environment_variables_take_precedence_over_config_defaults() {
+    let config = parse_toml_str(CONFIG).unwrap();
+    std::env::remove_var(GROUP_ENV_VAR);
+    std::env::remove_var(SECONDARY_ENV_VAR);
+    assert_eq!(default_group_path(&config), ["qa", "system1"]);
+
+    std::env::set_var(SECONDARY_ENV_VAR, "system2");
+    assert_eq!(default_group_path(&config), ["qa", "system2"]);
+
+    std::env::set_var(GROUP_ENV_VAR, "production");
+    std::env::remove_var(SECONDARY_ENV_VAR);
+    assert_eq!(default_group_path(&config), ["production"]);
+
+    std::env::remove_var(GROUP_ENV_VAR);
+    assert!(default_group_path(&parse_toml_str("[qa]\n").unwrap()).is_empty());
+}
+
+#[test]
+fn defaults_and_sensitive_tables_are_not_groups() {
+    let config = format!("{}[_sensitive]\nkeys = [\"replicas\"]\n", CONFIG);
+    let stats = config_stats(&parse_toml_str(&config).unwrap());
+    assert!(
+        stats.starts_with("Groups: 1\nSecondary groups: 1\n"),
+        "{}",
+        stats
+    );
+    assert!(!stats.contains("_defaults") && !stats.contains("_sensitive"));
+
+    let path = std::env::temp_dir().join(format!("tmptoml-defaults-{}.toml", std::process::id()));
+    fs::write(&path, &config).unwrap();
+    let flattened = flatten_config_file(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(flattened.contains("qa.system1"));
+    assert!(!flattened.contains("_defaults") && !flattened.contains("_sensitive"));
+}
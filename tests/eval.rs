@@ -0,0 +1,29 @@
+use std::fs;
+use tmptoml::{eval_expression, expression_template, parse_group_path, RenderOptions};
+
+#[test]
+fn bare_expressions_are_wrapped_in_braces() {
+    assert_eq!(expression_template("port + 100"), "{{ port + 100 }}");
+    assert_eq!(expression_template(" {{ port }} "), "{{ port }}");
+    assert_eq!(
+        expression_template("{% if tls %}https{% endif %}"),
+        "{% if tls %}https{% endif %}"
+    );
+}
+
+#[test]
+fn expression_is_evaluated_against_the_group() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-eval-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    fs::write(&config, "[qa]\nenv = \"qa\"\n[qa.system1]\nport = 8080\n").unwrap();
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        ..RenderOptions::default()
+    };
+    let port = eval_expression(&config, "{{ port + 100 }}", &options).unwrap();
+    let env = eval_expression(&config, "env | upper", &options).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(port, "8180");
+    assert_eq!(env, "QA");
+}
@@ -0,0 +1,178 @@
+use std::{fs, path::PathBuf};
+use tmptoml::{
+    parse_group_path, render_batch, BatchErr, BatchOptions, RenderOptions, TemplateFilter,
+    TmpTomlErr,
+};
+
+// Writes the files of a template directory, each rendering to its own name.
+fn template_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tmptoml-exclude-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("templates")).unwrap();
+    fs::write(dir.join("config.toml"), "[qa]\nenv = \"qa\"\n").unwrap();
+    for (path, content) in files {
+        let path = dir.join("templates").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn rendered(dir: &PathBuf, filter: TemplateFilter) -> Result<Vec<String>, TmpTomlErr> {
+    let batch = render_batch(
+        &dir.join("config.toml"),
+        &dir.join("templates"),
+        &dir.join("out"),
+        &[parse_group_path("qa")],
+        &BatchOptions {
+            filter,
+            ..BatchOptions::default()
+        },
+        RenderOptions::default(),
+    );
+    fs::remove_dir_all(dir).unwrap();
+    let mut templates: Vec<String> = batch?
+        .outputs
+        .into_iter()
+        .map(|output| output.template.replace('\\', "/"))
+        .collect();
+    templates.sort();
+    Ok(templates)
+}
+
+const FILES: [(&str, &str); 7] = [
+    ("app.conf", "{{ env }}"),
+    ("app.conf.swp", "swap"),
+    ("keep.swp", "kept"),
+    ("README.md", "docs"),
+    ("fixtures/data.conf", "fixture"),
+    ("nested/fixtures", "a file, not a directory"),
+    ("nested/site.conf", "{{ env }}"),
+];
+
+#[test]
+fn tmptomlignore_files_use_gitignore_syntax() {
+    let dir = template_dir(
+        "ignore-file",
+        &[
+            FILES.as_slice(),
+            &[(
+                ".tmptomlignore",
+                "*.swp\n!keep.swp\nfixtures/\n/README.md\n",
+            )],
+        ]
+        .concat(),
+    );
+    assert_eq!(
+        rendered(&dir, TemplateFilter::default()).unwrap(),
+        [
+            "app.conf",
+            "keep.swp",
+            "nested/fixtures",
+            "nested/site.conf"
+        ]
+    );
+}
+
+#[test]
+fn nested_tmptomlignore_files_apply_below_their_directory() {
+    let dir = template_dir(
+        "nested-ignore-file",
+        &[
+            FILES.as_slice(),
+            &[("nested/.tmptomlignore", "*.conf\n"), ("app.conf.swp", "x")],
+        ]
+        .concat(),
+    );
+    assert_eq!(
+        rendered(&dir, TemplateFilter::default()).unwrap(),
+        [
+            "README.md",
+            "app.conf",
+            "app.conf.swp",
+            "fixtures/data.conf",
+            "keep.swp",
+            "nested/fixtures"
+        ]
+    );
+}
+
+#[test]
+fn exclude_globs_leave_out_matching_files() {
+    let dir = template_dir("exclude", &FILES);
+    let filter = TemplateFilter {
+        exclude: vec![
+            "*.swp".to_string(),
+            "*.md".to_string(),
+            "fixtures/**".to_string(),
+        ],
+        ..TemplateFilter::default()
+    };
+    assert_eq!(
+        rendered(&dir, filter).unwrap(),
+        ["app.conf", "nested/fixtures", "nested/site.conf"]
+    );
+}
+
+#[test]
+fn exclude_globs_combine_with_tmptomlignore_negations() {
+    // A negation in `.tmptomlignore` can't bring back a file `--exclude`
+    // leaves out, and a directory glob leaves files of that name alone.
+    let dir = template_dir(
+        "exclude-negation",
+        &[
+            FILES.as_slice(),
+            &[(".tmptomlignore", "*.swp\n!keep.swp\n")],
+        ]
+        .concat(),
+    );
+    let filter = TemplateFilter {
+        exclude: vec!["keep.*".to_string(), "fixtures/".to_string()],
+        ..TemplateFilter::default()
+    };
+    assert_eq!(
+        rendered(&dir, filter).unwrap(),
+        [
+            "README.md",
+            "app.conf",
+            "nested/fixtures",
+            "nested/site.conf"
+        ]
+    );
+}
+
+#[test]
+fn gitignore_files_are_only_honored_when_asked_for() {
+    let files = [FILES.as_slice(), &[(".gitignore", "*.md\n")]].concat();
+    let honored = rendered(
+        &template_dir("gitignore-on", &files),
+        TemplateFilter {
+            gitignore: true,
+            ..TemplateFilter::default()
+        },
+    )
+    .unwrap();
+    let ignored = rendered(
+        &template_dir("gitignore-off", &files),
+        TemplateFilter::default(),
+    )
+    .unwrap();
+    assert!(!honored.contains(&"README.md".to_string()));
+    assert!(ignored.contains(&"README.md".to_string()));
+    assert!(ignored.contains(&".gitignore".to_string()));
+}
+
+#[test]
+fn invalid_exclude_globs_are_rejected() {
+    let dir = template_dir("invalid", &FILES);
+    let filter = TemplateFilter {
+        exclude: vec!["{unclosed".to_string()],
+        ..TemplateFilter::default()
+    };
+    match rendered(&dir, filter) {
+        Err(TmpTomlErr::Batch(BatchErr::InvalidPattern(pattern))) => {
+            assert!(pattern.starts_with("{unclosed: "), "{}", pattern)
+        }
+        other => panic!("expected an invalid pattern, got {:?}", other),
+    }
+}
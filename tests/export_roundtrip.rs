@@ -0,0 +1,51 @@
+use std::fs;
+use tmptoml::{export_group_with_coercions, ExportErr, ExportFormat};
+
+const CONFIG: &str = r#"
+[qa.system1]
+quote = "it's"
+ratio = nan
+replicas = 3
+released = 2021-06-01T10:00:00Z
+[qa.system1.limits]
+hosts = [1, "two"]
+
+[qa.system2]
+a-b = "x"
+a_b = "y"
+nul = "a\u0000b"
+"#;
+
+fn verify(sec_group_id: &str, format: ExportFormat) -> Result<(), ExportErr> {
+    let path = std::env::temp_dir().join(format!(
+        "tmptoml-roundtrip-{}-{}.toml",
+        sec_group_id,
+        std::process::id()
+    ));
+    fs::write(&path, CONFIG).unwrap();
+    let exported = export_group_with_coercions(&path, "qa", sec_group_id, format, false).unwrap();
+    fs::remove_file(&path).unwrap();
+    exported.verify_roundtrip()
+}
+
+#[test]
+fn exports_reading_back_as_their_values_verify() {
+    assert!(verify("system1", ExportFormat::Toml).is_ok());
+    assert!(verify("system1", ExportFormat::Shell).is_ok());
+    assert!(verify("system2", ExportFormat::Toml).is_ok());
+}
+
+#[test]
+fn shell_exports_losing_values_fail_to_verify() {
+    match verify("system2", ExportFormat::Shell) {
+        Err(ExportErr::LossyRoundtrip(losses)) => assert_eq!(
+            losses,
+            vec![
+                "a-b: exported as A_B like a_b",
+                "a_b: exported as A_B like a-b",
+                "nul: contains a NUL byte, which environment variables can't hold",
+            ]
+        ),
+        other => panic!("expected a lossy roundtrip, got {:?}", other),
+    }
+}
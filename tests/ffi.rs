@@ -0,0 +1,186 @@
+#![cfg(feature = "ffi")]
+
+use std::{
+    ffi::{CStr, CString},
+    fs,
+    os::raw::{c_char, c_int},
+    ptr,
+};
+
+// Links the library, whose exported symbols the declarations below resolve
+// to, as nothing else of it is used.
+extern crate tmptoml;
+
+// The declarations of include/tmptoml.h.
+extern "C" {
+    fn tmptoml_render(
+        config: *const c_char,
+        template: *const c_char,
+        group: *const c_char,
+        secondary: *const c_char,
+        out: *mut *mut c_char,
+        err: *mut *mut c_char,
+    ) -> c_int;
+    fn tmptoml_free_string(value: *mut c_char);
+}
+
+const TMPTOML_OK: c_int = 0;
+const TMPTOML_ERR: c_int = 1;
+const TMPTOML_INVALID_ARGUMENT: c_int = 2;
+
+struct Call {
+    status: c_int,
+    out: Option<String>,
+    err: Option<String>,
+}
+
+// Takes over a string returned through `out` or `err`, releasing it.
+unsafe fn take(value: *mut c_char) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    let string = CStr::from_ptr(value).to_str().unwrap().to_string();
+    tmptoml_free_string(value);
+    Some(string)
+}
+
+unsafe fn render(
+    config: *const c_char,
+    template: *const c_char,
+    group: *const c_char,
+    secondary: *const c_char,
+) -> Call {
+    // Garbage the call has to reset.
+    let mut out = ptr::dangling_mut();
+    let mut err = ptr::dangling_mut();
+    let status = tmptoml_render(config, template, group, secondary, &mut out, &mut err);
+    Call {
+        status,
+        out: take(out),
+        err: take(err),
+    }
+}
+
+fn c_string(value: &str) -> CString {
+    CString::new(value).unwrap()
+}
+
+fn project(name: &str) -> (CString, CString) {
+    let dir = std::env::temp_dir().join(format!("tmptoml-ffi-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("config.toml"),
+        "[qa]\nenv = \"qa\"\n[qa.system1]\nhost = \"db\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("template"),
+        "{{ env }} {{ host | default(value=\"-\") }}",
+    )
+    .unwrap();
+    let path = |file: &str| c_string(dir.join(file).to_str().unwrap());
+    (path("config.toml"), path("template"))
+}
+
+#[test]
+fn renders_through_the_c_abi() {
+    let (config, template) = project("render");
+    let (group, secondary) = (c_string("qa"), c_string("system1"));
+    unsafe {
+        let call = render(
+            config.as_ptr(),
+            template.as_ptr(),
+            group.as_ptr(),
+            secondary.as_ptr(),
+        );
+        assert_eq!(call.status, TMPTOML_OK);
+        assert_eq!(call.out.as_deref(), Some("qa db"));
+        assert_eq!(call.err, None);
+
+        let call = render(
+            config.as_ptr(),
+            template.as_ptr(),
+            group.as_ptr(),
+            ptr::null(),
+        );
+        assert_eq!(call.status, TMPTOML_OK);
+        assert_eq!(call.out.as_deref(), Some("qa -"));
+
+        let unknown = c_string("prod");
+        let call = render(
+            config.as_ptr(),
+            template.as_ptr(),
+            unknown.as_ptr(),
+            ptr::null(),
+        );
+        assert_eq!(call.status, TMPTOML_ERR);
+        assert_eq!(call.out, None);
+        assert!(call.err.unwrap().starts_with("GroupNotFound"));
+    }
+}
+
+#[test]
+fn null_arguments_are_rejected() {
+    let (config, template) = project("null");
+    let group = c_string("qa");
+    unsafe {
+        for (config, template, group) in [
+            (ptr::null(), template.as_ptr(), group.as_ptr()),
+            (config.as_ptr(), ptr::null(), group.as_ptr()),
+            (config.as_ptr(), template.as_ptr(), ptr::null()),
+        ] {
+            let call = render(config, template, group, ptr::null());
+            assert_eq!(call.status, TMPTOML_INVALID_ARGUMENT);
+            assert_eq!(call.out, None);
+            assert_eq!(
+                call.err.as_deref(),
+                Some("config, template and group must be valid UTF-8 strings")
+            );
+        }
+
+        // Outputs may be NULL when the caller doesn't want them.
+        let status = tmptoml_render(
+            config.as_ptr(),
+            template.as_ptr(),
+            group.as_ptr(),
+            ptr::null(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        );
+        assert_eq!(status, TMPTOML_OK);
+        tmptoml_free_string(ptr::null_mut());
+    }
+}
+
+#[test]
+fn invalid_utf8_arguments_are_rejected() {
+    let (config, template) = project("utf8");
+    let group = c_string("qa");
+    let invalid = CString::new(vec![b'q', 0xff, b'a']).unwrap();
+    unsafe {
+        let call = render(
+            config.as_ptr(),
+            template.as_ptr(),
+            invalid.as_ptr(),
+            ptr::null(),
+        );
+        assert_eq!(call.status, TMPTOML_INVALID_ARGUMENT);
+        assert_eq!(
+            call.err.as_deref(),
+            Some("config, template and group must be valid UTF-8 strings")
+        );
+
+        let call = render(
+            config.as_ptr(),
+            template.as_ptr(),
+            group.as_ptr(),
+            invalid.as_ptr(),
+        );
+        assert_eq!(call.status, TMPTOML_INVALID_ARGUMENT);
+        assert_eq!(call.out, None);
+        assert_eq!(
+            call.err.as_deref(),
+            Some("secondary must be a valid UTF-8 string")
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use tmptoml::{parse_group_path, render_str, RenderOptions};
+
+const CONFIG: &str = "[qa.system1]\nuser = \"deploy\"\n";
+
+#[test]
+fn templates_read_files_below_the_restricted_root() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-functions-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("root/keys/old")).unwrap();
+    fs::write(dir.join("root/keys/deploy.pub"), "ssh-ed25519 AAAA deploy").unwrap();
+    fs::write(dir.join("root/keys/admin.pub"), "ssh-ed25519 BBBB admin").unwrap();
+    fs::write(dir.join("root/keys/old/legacy.pub"), "ssh-rsa CCCC").unwrap();
+    fs::write(dir.join("secret"), "outside").unwrap();
+
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        restrict_root: Some(dir.join("root")),
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        render_str(
+            CONFIG,
+            "{{ file(path=\"keys/\" ~ user ~ \".pub\") }}",
+            &options
+        )
+        .unwrap(),
+        "ssh-ed25519 AAAA deploy"
+    );
+    assert_eq!(
+        render_str(
+            CONFIG,
+            "{{ glob(pattern=\"keys/*.pub\") | join(sep=\",\") }}",
+            &options
+        )
+        .unwrap(),
+        "keys/admin.pub,keys/deploy.pub"
+    );
+    assert!(render_str(CONFIG, "{{ file(path=\"../secret\") }}", &options).is_err());
+
+    let unrestricted = RenderOptions {
+        restrict_root: None,
+        ..options
+    };
+    assert!(render_str(
+        CONFIG,
+        "{{ file(path=\"keys/deploy.pub\") }}",
+        &unrestricted
+    )
+    .is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn checksums_hash_values_and_files() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-checksum-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("long.txt"), "x".repeat(200)).unwrap();
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        restrict_root: Some(dir.clone()),
+        ..RenderOptions::default()
+    };
+    let checksum = |template: &str| render_str(CONFIG, template, &options).unwrap();
+    assert_eq!(
+        checksum("{{ checksum(value=\"abc\") }}"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        checksum("{{ checksum(value=\"abc\", algorithm=\"sha256\") }}"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        checksum("{{ checksum(value=[1, true]) }}"),
+        "6de7d67a8d95bdbb5b4cd8498a8f41f7307c5d4a3a9a25b6b2e0f76347e8abfa"
+    );
+    assert_eq!(
+        checksum("{{ checksum(file=\"long.txt\") }}"),
+        "aa20c23e3201834050679e1d88941b9a6fed0557c9a705cb2c315e2e63fd486d"
+    );
+    assert!(render_str(
+        CONFIG,
+        "{{ checksum(value=\"abc\", algorithm=\"sha1\") }}",
+        &options
+    )
+    .is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,134 @@
+use tmptoml::{import_helm_values, ImportErr};
+use toml::Value;
+
+fn import(values: &str) -> Value {
+    let config = import_helm_values(values, "production").unwrap();
+    toml::from_str(&config).unwrap()
+}
+
+fn toml(config: &str) -> Value {
+    toml::from_str(config).unwrap()
+}
+
+#[test]
+fn nested_mappings_become_secondary_groups_and_tables() {
+    let values = "
+replicaCount: 2
+image:
+  repository: nginx
+  tag: \"1.25\"
+  pullPolicy:
+    default: IfNotPresent
+resources:
+  limits:
+    cpu: 100m
+";
+    assert_eq!(
+        import(values),
+        toml(
+            r#"
+[production]
+replicaCount = 2
+[production.image]
+repository = "nginx"
+tag = "1.25"
+[production.image.pullPolicy]
+default = "IfNotPresent"
+[production.resources.limits]
+cpu = "100m"
+"#
+        )
+    );
+}
+
+#[test]
+fn lists_become_arrays() {
+    let values = "
+ports: [80, 443]
+ratio: 0.5
+env:
+  - name: LOG_LEVEL
+    value: debug
+  - name: DEBUG
+    value: true
+";
+    assert_eq!(
+        import(values),
+        toml(
+            r#"
+[production]
+ports = [80, 443]
+ratio = 0.5
+[[production.env]]
+name = "LOG_LEVEL"
+value = "debug"
+[[production.env]]
+name = "DEBUG"
+value = true
+"#
+        )
+    );
+}
+
+#[test]
+fn nulls_are_dropped() {
+    let values = "
+nodeSelector: ~
+tolerations:
+  - null
+  - key: dedicated
+    operator: null
+image:
+  tag:
+";
+    assert_eq!(
+        import(values),
+        toml(
+            r#"
+[production]
+[[production.tolerations]]
+key = "dedicated"
+[production.image]
+"#
+        )
+    );
+    assert_eq!(import("~"), toml("[production]"));
+}
+
+#[test]
+fn keys_that_are_not_bare_are_quoted() {
+    let values = "
+podAnnotations:
+  app.kubernetes.io/name: web
+  \"with space\": 1
+  8080: http
+  true: yes
+";
+    let config = import_helm_values(values, "production").unwrap();
+    assert!(config.contains("\"app.kubernetes.io/name\" = \"web\""));
+    assert!(config.contains("\"with space\" = 1"));
+    assert_eq!(
+        toml::from_str::<Value>(&config).unwrap(),
+        toml(
+            r#"
+[production.podAnnotations]
+"app.kubernetes.io/name" = "web"
+"with space" = 1
+8080 = "http"
+true = "yes"
+"#
+        )
+    );
+}
+
+#[test]
+fn documents_that_are_not_mappings_are_rejected() {
+    assert!(matches!(
+        import_helm_values("- a\n- b\n", "production"),
+        Err(ImportErr::NotAMapping)
+    ));
+    assert!(matches!(
+        import_helm_values("image: [", "production"),
+        Err(ImportErr::InvalidYaml(_))
+    ));
+}
@@ -0,0 +1,116 @@
+use tmptoml::{render_str, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+one = 1
+three = 3
+half = 0.5
+"#;
+
+fn render(template: &str) -> String {
+    let options = RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, template, &options).unwrap()
+}
+
+fn filter_each(filter: &str, words: &[&str]) -> String {
+    words
+        .iter()
+        .map(|word| render(&format!(r#"{{{{ "{}" | {} }}}}"#, word, filter)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[test]
+fn pluralize_on_numbers_keeps_the_builtin_suffixes() {
+    assert_eq!(render("server{{ one | pluralize }}"), "server");
+    assert_eq!(render("server{{ three | pluralize }}"), "servers");
+    assert_eq!(render("server{{ half | pluralize }}"), "servers");
+    assert_eq!(
+        render(r#"cherr{{ one | pluralize(singular="y", plural="ies") }}"#),
+        "cherry"
+    );
+    assert_eq!(
+        render(r#"cherr{{ three | pluralize(singular="y", plural="ies") }}"#),
+        "cherries"
+    );
+}
+
+#[test]
+fn pluralize_on_words_applies_english_rules() {
+    assert_eq!(
+        filter_each(
+            "pluralize",
+            &["server", "policy", "day", "box", "match", "analysis", "shelf", "hero", "photo"]
+        ),
+        "servers policies days boxes matches analyses shelves heroes photos"
+    );
+    assert_eq!(
+        filter_each(
+            "pluralize",
+            &["person", "child", "index", "status", "people", "sheep", "data"]
+        ),
+        "people children indices statuses people sheep data"
+    );
+    assert_eq!(
+        filter_each("pluralize", &["load balancer", "Person", "NODE"]),
+        "load balancers People NODES"
+    );
+}
+
+#[test]
+fn pluralize_on_words_follows_count_and_plural() {
+    assert_eq!(render(r#"{{ "server" | pluralize(count=one) }}"#), "server");
+    assert_eq!(
+        render(r#"{{ "server" | pluralize(count=three) }}"#),
+        "servers"
+    );
+    assert_eq!(
+        render(r#"{{ "octopus" | pluralize(plural="octopodes") }}"#),
+        "octopodes"
+    );
+    assert_eq!(
+        render(r#"{{ "octopus" | pluralize(count=1, plural="octopodes") }}"#),
+        "octopus"
+    );
+}
+
+#[test]
+fn singularize_reverses_pluralize() {
+    assert_eq!(
+        filter_each(
+            "singularize",
+            &["servers", "policies", "boxes", "matches", "analyses", "shelves", "heroes"]
+        ),
+        "server policy box match analysis shelf hero"
+    );
+    assert_eq!(
+        filter_each(
+            "singularize",
+            &["people", "children", "indices", "statuses", "status", "class", "series"]
+        ),
+        "person child index status status class series"
+    );
+    assert_eq!(
+        filter_each("singularize", &["load balancers", "People", "NODES"]),
+        "load balancer Person NODE"
+    );
+}
+
+#[test]
+fn ordinal_suffixes_teens_with_th() {
+    let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 23, 101, 111, 112, 113, -1]
+        .iter()
+        .map(|number| render(&format!("{{{{ {} | ordinal }}}}", number)))
+        .collect();
+    assert_eq!(
+        ordinals,
+        [
+            "1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "23rd", "101st",
+            "111th", "112th", "113th", "-1st"
+        ]
+    );
+    assert_eq!(render(r#"{{ "12" | ordinal }}"#), "12th");
+}
@@ -0,0 +1,104 @@
+use tmptoml::{parse_group_path, render_str, RenderOptions};
+
+fn render(config: &str, template: &str, selections: &[&str]) -> String {
+    let options = RenderOptions {
+        group_paths: selections
+            .iter()
+            .map(|path| parse_group_path(path))
+            .collect(),
+        ..RenderOptions::default()
+    };
+    render_str(config, template, &options).unwrap()
+}
+
+#[test]
+fn inline_table_in_secondary_group_is_a_nested_object() {
+    let config = r#"
+[qa]
+env = "qa"
+[qa.system1]
+point = { x = 1, y = 2 }
+"#;
+    assert_eq!(
+        render(config, "{{ point.x }},{{ point.y }}", &["qa.system1"]),
+        "1,2"
+    );
+}
+
+#[test]
+fn inline_and_standard_tables_render_identically() {
+    let inline = r#"
+[qa.system1]
+point = { x = 1, y = { z = "deep" } }
+"#;
+    let standard = r#"
+[qa.system1.point]
+x = 1
+[qa.system1.point.y]
+z = "deep"
+"#;
+    let dotted = r#"
+[qa.system1]
+point.x = 1
+point.y.z = "deep"
+"#;
+    let template = "{{ point.x }} {{ point.y.z }} {{ point | json_encode() }}";
+    let expected = render(standard, template, &["qa.system1"]);
+    assert_eq!(expected, r#"1 deep {"x":1,"y":{"z":"deep"}}"#);
+    assert_eq!(render(inline, template, &["qa.system1"]), expected);
+    assert_eq!(render(dotted, template, &["qa.system1"]), expected);
+}
+
+#[test]
+fn inline_and_standard_tables_merge_across_selections() {
+    let config = r#"
+[shared.observability]
+labels = { team = "platform", tier = "backend" }
+[qa.system1.labels]
+team = "core"
+app = "system1"
+"#;
+    assert_eq!(
+        render(
+            config,
+            "{{ labels.team }} {{ labels.tier }} {{ labels.app }}",
+            &["shared.observability", "qa.system1"]
+        ),
+        "core backend system1"
+    );
+}
+
+#[test]
+fn arrays_of_inline_tables_are_lists_of_objects() {
+    let config = r#"
+[qa.system1]
+ports = [{ name = "http", port = 80 }, { name = "https", port = 443 }]
+"#;
+    assert_eq!(
+        render(
+            config,
+            "{% for p in ports %}{{ p.name }}={{ p.port }};{% endfor %}",
+            &["qa.system1"]
+        ),
+        "http=80;https=443;"
+    );
+}
+
+#[test]
+fn inline_tables_of_intermediate_levels_are_not_exposed() {
+    let config = r#"
+[qa]
+env = "qa"
+point = { x = 1 }
+[qa.system1]
+name = "system1"
+"#;
+    assert_eq!(
+        render(
+            config,
+            "{{ env }} {{ name }} {{ point | default(value='none') }}",
+            &["qa.system1"]
+        ),
+        "qa system1 none"
+    );
+}
@@ -0,0 +1,37 @@
+use tmptoml::{inspect_template, ItemKind};
+
+#[test]
+fn items_are_listed_with_their_positions() {
+    let source =
+        "{% for host in hosts %}\n  é{{ host.name | upper }} {{ \"{{ x }}\" }}{{ now() }}\n{% endfor %}";
+    let items = inspect_template(source).unwrap();
+    let listed: Vec<(ItemKind, &str, usize, usize, bool)> = items
+        .iter()
+        .map(|item| {
+            (
+                item.kind,
+                item.name.as_str(),
+                item.line,
+                item.column,
+                item.local,
+            )
+        })
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            (ItemKind::Statement, "for", 1, 4, false),
+            (ItemKind::Variable, "hosts", 1, 16, false),
+            (ItemKind::Variable, "host.name", 2, 7, true),
+            (ItemKind::Filter, "upper", 2, 19, false),
+            (ItemKind::Function, "now", 2, 46, false),
+            (ItemKind::Statement, "endfor", 3, 4, false),
+        ]
+    );
+    assert_eq!(&source[items[2].start..items[2].end], "host.name");
+}
+
+#[test]
+fn invalid_templates_are_refused() {
+    assert!(inspect_template("{{ host").is_err());
+}
@@ -0,0 +1,49 @@
+use tmptoml::{render_str, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+hosts = ["10.0.0.1", "10.0.0.2", "10.0.0.1"]
+names = ["web", "api"]
+ports = [80, 8080, 9090]
+domains = "example.com, example.org,,"
+"#;
+
+fn render(template: &str) -> String {
+    let options = RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, template, &options).unwrap()
+}
+
+#[test]
+fn join_wraps_every_item() {
+    assert_eq!(
+        render(r#"listen {{ hosts | uniq | join(sep=" ", suffix=":80") }};"#),
+        "listen 10.0.0.1:80 10.0.0.2:80;"
+    );
+    assert_eq!(render(r#"{{ ports | join(sep=",") }}"#), "80,8080,9090");
+}
+
+#[test]
+fn split_keeps_items_unless_trimmed() {
+    assert_eq!(
+        render(r#"{{ domains | split(sep=",") | json_encode() }}"#),
+        r#"["example.com"," example.org","",""]"#
+    );
+    assert_eq!(render(r#"{{ domains | split(pat=",") | length }}"#), "4");
+    assert_eq!(
+        render(r#"{{ domains | split(sep=",", trim=true) | json_encode() }}"#),
+        r#"["example.com","example.org"]"#
+    );
+}
+
+#[test]
+fn zip_pairs_items_up_to_the_shorter_array() {
+    assert_eq!(
+        render(
+            r#"{% for pair in names | zip(with=ports) %}{{ pair.0 }}={{ pair.1 }} {% endfor %}"#
+        ),
+        "web=80 api=8080 "
+    );
+}
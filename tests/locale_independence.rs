@@ -0,0 +1,110 @@
+use std::{fs, process::Command};
+use tmptoml::{apply_override, parse_group_path, render_str, ContextValues, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa.system1]
+ratio = 0.5
+large = 1234567.891
+tiny = 1e-7
+huge = 6.02e23
+count = 1234567
+released = 2024-03-05T10:30:00Z
+day = 2024-03-05
+"#;
+
+const TEMPLATE: &str = "{{ ratio }} {{ large }} {{ tiny }} {{ huge }} {{ count }} {{ released }} {{ day }} {{ ratio * 3 }}";
+
+const EXPECTED: &str = "0.5 1234567.891 0.0000001 602000000000000000000000 1234567 2024-03-05T10:30:00Z 2024-03-05 1.5";
+
+fn options(locale: Option<&str>) -> RenderOptions {
+    RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        locale: locale.map(String::from),
+        ..RenderOptions::default()
+    }
+}
+
+#[test]
+fn values_render_the_same_for_every_locale() {
+    for locale in [None, Some("de-DE"), Some("fr-FR"), Some("sv-SE")] {
+        assert_eq!(
+            render_str(CONFIG, TEMPLATE, &options(locale)).unwrap(),
+            EXPECTED
+        );
+    }
+}
+
+#[test]
+fn only_the_formatting_filters_follow_the_locale() {
+    let template = "{{ large | format_number(decimals=2) }} {{ large }}";
+    assert_eq!(
+        render_str(CONFIG, template, &options(Some("de-DE"))).unwrap(),
+        "1.234.567,89 1234567.891"
+    );
+}
+
+#[test]
+fn format_date_uses_the_month_and_weekday_names_of_the_locale() {
+    let template = r#"{{ day | format_date }} | {{ released | format_date(format="%A, %d. %b %H:%M") }} | {{ day | format_date(locale="fr-FR") }}"#;
+    assert_eq!(
+        render_str(CONFIG, template, &options(Some("de-DE"))).unwrap(),
+        "5. März 2024 | Dienstag, 05. März 10:30 | 5 mars 2024"
+    );
+    assert_eq!(
+        render_str(CONFIG, "{{ day | format_date }}", &options(None)).unwrap(),
+        render_str(CONFIG, "{{ day | format_date }}", &options(Some("en-US"))).unwrap()
+    );
+}
+
+#[test]
+fn locale_flag_sets_the_date_locale() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-locale-flag-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(
+        dir.join("template"),
+        "{{ day | format_date }} {{ large | format_number(decimals=1) }}",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .current_dir(&dir)
+        .args(["render", "config.toml", "template", "qa", "system1"])
+        .args(["--locale", "de-DE"])
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "5. März 2024 1.234.567,9\n"
+    );
+}
+
+#[test]
+fn overrides_are_parsed_the_same_for_every_locale() {
+    let mut values = ContextValues::new();
+    apply_override(&mut values, "ratio=0.5", true).unwrap();
+    apply_override(&mut values, "comma=0,5", true).unwrap();
+    assert_eq!(values["ratio"], serde_json::json!(0.5));
+    assert_eq!(values["comma"], serde_json::json!("0,5"));
+}
+
+#[test]
+fn the_host_locale_does_not_change_the_output() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-locale-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(dir.join("template"), TEMPLATE).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .arg("render")
+        .arg(dir.join("config.toml"))
+        .arg(dir.join("template"))
+        .args(["qa", "system1"])
+        .env("LANG", "de_DE.UTF-8")
+        .env("LC_ALL", "de_DE.UTF-8")
+        .env("LC_NUMERIC", "fr_FR.UTF-8")
+        .env("LC_TIME", "fr_FR.UTF-8")
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), EXPECTED);
+}
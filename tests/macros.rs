@@ -0,0 +1,131 @@
+use std::{fs, path::PathBuf, process::Command};
+use tmptoml::{render_template_with_warnings, RenderOptions, TeraRenderErr, TmpTomlErr};
+
+const LABELS: &str = r#"{% macro labels(app) %}app: {{ app }}{% endmacro labels %}"#;
+
+const DEPLOYMENT: &str = r#"{% import "k8s/labels.tera" as k8s %}
+{% macro metadata(app) %}name: {{ app }}, {{ k8s::labels(app=app) }}{% endmacro metadata %}"#;
+
+fn macro_project(name: &str, template: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tmptoml-macros-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), "[qa]\napp = \"web\"\n").unwrap();
+    fs::write(dir.join("template"), template).unwrap();
+    for (path, content) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn render(dir: &PathBuf, macro_dirs: &[&str]) -> Result<String, TmpTomlErr> {
+    let options = RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        macro_dirs: macro_dirs.iter().map(|macros| dir.join(macros)).collect(),
+        ..RenderOptions::default()
+    };
+    let rendered =
+        render_template_with_warnings(&dir.join("config.toml"), &dir.join("template"), &options);
+    fs::remove_dir_all(dir).unwrap();
+    rendered.map(|rendered| rendered.output)
+}
+
+#[test]
+fn templates_import_macros_by_their_path_in_the_directory() {
+    let dir = macro_project(
+        "nested",
+        r#"{% import "k8s/labels.tera" as k8s %}{{ k8s::labels(app=app) }}"#,
+        &[
+            ("macros/k8s/labels.tera", LABELS),
+            ("macros/k8s/README.md", "{{ not a template"),
+        ],
+    );
+    assert_eq!(render(&dir, &["macros"]).unwrap(), "app: web");
+}
+
+#[test]
+fn macro_files_import_each_other() {
+    let dir = macro_project(
+        "chained",
+        r#"{% import "deployment.tera" as deployment %}{{ deployment::metadata(app=app) }}"#,
+        &[
+            ("macros/deployment.tera", DEPLOYMENT),
+            ("macros/k8s/labels.tera", LABELS),
+        ],
+    );
+    assert_eq!(render(&dir, &["macros"]).unwrap(), "name: web, app: web");
+}
+
+#[test]
+fn macros_of_several_directories_are_loaded() {
+    let dir = macro_project(
+        "several",
+        r#"{% import "deployment.tera" as deployment %}{{ deployment::metadata(app=app) }}"#,
+        &[
+            ("team/deployment.tera", DEPLOYMENT),
+            ("shared/k8s/labels.tera", LABELS),
+        ],
+    );
+    assert_eq!(
+        render(&dir, &["team", "shared"]).unwrap(),
+        "name: web, app: web"
+    );
+}
+
+#[test]
+fn missing_and_invalid_macros_fail_the_render() {
+    let missing = macro_project("missing", "{{ app }}", &[]);
+    assert!(matches!(
+        render(&missing, &["macros"]),
+        Err(TmpTomlErr::Render(TeraRenderErr::TemplateNotFound(_)))
+    ));
+
+    let invalid = macro_project(
+        "invalid",
+        "{{ app }}",
+        &[("macros/broken.tera", "{% macro broken() %}")],
+    );
+    match render(&invalid, &["macros"]) {
+        Err(TmpTomlErr::Render(TeraRenderErr::InvalidTemplate(message))) => {
+            assert!(
+                message.starts_with("Failed to parse macro file"),
+                "{}",
+                message
+            )
+        }
+        other => panic!("expected an invalid macro file, got {:?}", other),
+    }
+
+    let unimported = macro_project(
+        "unimported",
+        r#"{% import "k8s/labels.tera" as k8s %}{{ k8s::labels(app=app) }}"#,
+        &[],
+    );
+    assert!(render(&unimported, &[]).is_err());
+}
+
+#[test]
+fn macros_flag_loads_the_directory() {
+    let dir = macro_project(
+        "cli",
+        r#"{% import "k8s/labels.tera" as k8s %}{{ k8s::labels(app=app) }}"#,
+        &[("macros/k8s/labels.tera", LABELS)],
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .current_dir(&dir)
+        .args([
+            "render",
+            "config.toml",
+            "template",
+            "qa",
+            "--macros",
+            "macros",
+        ])
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "app: web\n");
+}
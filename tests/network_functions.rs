@@ -0,0 +1,43 @@
+use tmptoml::{render_str, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+net = "10.12.112.0/20"
+net6 = "fd00:1::/64"
+"#;
+
+fn render(template: &str) -> Result<String, tmptoml::TmpTomlErr> {
+    let options = RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, template, &options)
+}
+
+#[test]
+fn addresses_are_derived_from_a_network() {
+    assert_eq!(
+        render(r#"{{ cidr_host(net=net, n=5) }} {{ cidr_host(net=net, n=-1) }} {{ cidr_netmask(net=net) }}"#)
+            .unwrap(),
+        "10.12.112.5 10.12.127.255 255.255.240.0"
+    );
+    assert_eq!(
+        render(r#"{{ cidr_host(net=net6, n=16) }}"#).unwrap(),
+        "fd00:1::10"
+    );
+}
+
+#[test]
+fn ip_add_carries_across_octets() {
+    assert_eq!(
+        render(r#"{{ ip_add(ip="10.0.0.250", n=10) }} {{ ip_add(ip="10.0.1.0", n=-1) }}"#).unwrap(),
+        "10.0.1.4 10.0.0.255"
+    );
+}
+
+#[test]
+fn addresses_outside_the_network_are_refused() {
+    assert!(render(r#"{{ cidr_host(net=net, n=4096) }}"#).is_err());
+    assert!(render(r#"{{ ip_add(ip="255.255.255.255", n=1) }}"#).is_err());
+    assert!(render(r#"{{ cidr_netmask(net=net6) }}"#).is_err());
+}
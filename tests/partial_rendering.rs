@@ -0,0 +1,131 @@
+use std::{fs, process::Command};
+use tmptoml::{parse_group_path, render_str, Deferral, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+region = "eu-west-1"
+hosts = ["db1", "db2"]
+replicas = 2
+labels = { app = "web" }
+"#;
+
+// The values of the second pass, filling in what the first left out.
+const LATER: &str = r#"
+[qa]
+env = "qa"
+region = "eu-west-1"
+hosts = ["db1", "db2"]
+replicas = 2
+image = "nginx:1.25"
+secret = "hunter2"
+"#;
+
+fn render(config: &str, template: &str, deferral: Option<Deferral>) -> String {
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa")],
+        deferral,
+        ..RenderOptions::default()
+    };
+    render_str(config, template, &options).unwrap()
+}
+
+fn only(keys: &[&str]) -> Option<Deferral> {
+    Some(Deferral::Only(
+        keys.iter().map(|key| key.to_string()).collect(),
+    ))
+}
+
+#[test]
+fn only_substitutes_the_listed_variables() {
+    let template = "{{ env }} {{ region | upper }} {{ replicas }} {{ env ~ replicas }} {{ image }}";
+    assert_eq!(
+        render(CONFIG, template, only(&["env", "region"])),
+        "qa EU-WEST-1 {{ replicas }} {{ env ~ replicas }} {{ image }}"
+    );
+}
+
+#[test]
+fn defer_unknown_keeps_the_variables_missing_from_the_context() {
+    let template = "{{ env }} {{ image }} {{ secret | default(value=\"none\") }} {{- env -}}";
+    assert_eq!(
+        render(CONFIG, template, Some(Deferral::Unknown)),
+        "qa {{ image }} {{ secret | default(value=\"none\") }}qa"
+    );
+}
+
+#[test]
+fn variables_bound_by_the_template_are_substituted() {
+    let template = concat!(
+        "{% set prefix = \"srv-\" %}",
+        "{% for host in hosts %}{{ prefix ~ host }}:{{ loop.index }}:{{ image }} {% endfor %}",
+        "{% for key, value in labels %}{{ key }}={{ value }} {% endfor %}",
+        "{% set_global tier = \"web\" %}{{ tier }}/{{ image }} {{ host }} {{ key }}",
+    );
+    assert_eq!(
+        render(CONFIG, template, Some(Deferral::Unknown)),
+        "srv-db1:1:{{ image }} srv-db2:2:{{ image }} app=web web/{{ image }} {{ host }} {{ key }}"
+    );
+}
+
+#[test]
+fn raw_blocks_comments_and_strings_are_left_alone() {
+    let template = concat!(
+        "{% raw %}{{ env }}{% endraw %} ",
+        "{# {{ image }} #}",
+        "{{ \"}}\" ~ env }} {{ image | default(value=\"{{ x }}\") }}",
+    );
+    assert_eq!(
+        render(CONFIG, template, Some(Deferral::Unknown)),
+        "{{ env }} }}qa {{ image | default(value=\"{{ x }}\") }}"
+    );
+}
+
+#[test]
+fn partial_output_renders_in_a_second_pass() {
+    let template = concat!(
+        "env={{ env }}\n",
+        "{% for host in hosts %}{{ host }}={{ image }}\n{% endfor %}",
+        "secret={{ secret | upper }}\n",
+    );
+    let first = render(CONFIG, template, Some(Deferral::Unknown));
+    assert_eq!(
+        first,
+        "env=qa\ndb1={{ image }}\ndb2={{ image }}\nsecret={{ secret | upper }}\n"
+    );
+    let second = render(LATER, &first, None);
+    assert_eq!(second, render(LATER, template, None));
+    assert_eq!(
+        second,
+        "env=qa\ndb1=nginx:1.25\ndb2=nginx:1.25\nsecret=HUNTER2\n"
+    );
+
+    let first = render(LATER, template, only(&["image", "secret"]));
+    assert_eq!(render(LATER, &first, None), second);
+}
+
+#[test]
+fn only_and_defer_unknown_flags_select_the_deferral() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-partial-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(
+        dir.join("template"),
+        "{{ env }} {{ region }} {{ replicas }} {{ image }}",
+    )
+    .unwrap();
+    let render = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+            .current_dir(&dir)
+            .args(["render", "config.toml", "template", "qa"])
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let only = render(&["--only", "env,region"]);
+    let unknown = render(&["--defer-unknown"]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(only, "qa eu-west-1 {{ replicas }} {{ image }}\n");
+    assert_eq!(unknown, "qa eu-west-1 2 {{ image }}\n");
+}
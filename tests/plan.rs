@@ -0,0 +1,109 @@
+use std::{fs, path::Path, process::Command, time::Instant};
+use tmptoml::{
+    parse_group_path, plan_batch, unified_diff, BatchOptions, PlannedAction, RenderOptions,
+};
+
+#[test]
+fn diff_shows_changed_lines_with_context() {
+    let original = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let changed = "a\nb\nc\nd\ne\nF\ng\nh\ni\n";
+    assert_eq!(
+        unified_diff(Path::new("out/app"), original, changed),
+        "--- out/app\n+++ out/app\n@@ -3,6 +3,7 @@\n c\n d\n e\n-f\n+F\n g\n h\n+i"
+    );
+}
+
+#[test]
+fn files_are_planned_without_writing() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-plan-{}", std::process::id()));
+    let (templates, out) = (dir.join("templates"), dir.join("out"));
+    fs::create_dir_all(&templates).unwrap();
+    fs::create_dir_all(out.join("qa.system1")).unwrap();
+    fs::create_dir_all(out.join("qa.system2")).unwrap();
+    fs::write(
+        dir.join("config.toml"),
+        "[qa.system1]\nport = 1\n[qa.system2]\nport = 2\n[qa.system3]\nhost = \"db\"\n",
+    )
+    .unwrap();
+    fs::write(templates.join("app"), "port: {{ port }}\n").unwrap();
+    fs::write(out.join("qa.system1/app"), "port: 1\n").unwrap();
+    fs::write(out.join("qa.system2/app"), "port: 9\n").unwrap();
+
+    let group_paths: Vec<_> = ["qa.system1", "qa.system2", "qa.system3"]
+        .iter()
+        .map(|path| parse_group_path(path))
+        .collect();
+    let plan = plan_batch(
+        &dir.join("config.toml"),
+        &templates,
+        &out,
+        &group_paths,
+        &BatchOptions::default(),
+        RenderOptions::default(),
+    )
+    .unwrap();
+    let written = fs::read_to_string(out.join("qa.system2/app")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(written, "port: 9\n");
+    assert_eq!(plan.count(PlannedAction::Unchanged), 1);
+    assert_eq!(plan.count(PlannedAction::Change), 1);
+    assert!(plan.files[1].diff.ends_with("-port: 9\n+port: 2"));
+    assert_eq!(plan.failures.len(), 1);
+    assert_eq!(plan.undefined.len(), 1);
+    assert_eq!(plan.undefined[0].name, "port");
+    assert!(plan
+        .to_string()
+        .ends_with("Plan: 0 to add, 1 to change, 1 unchanged, 1 failing."));
+}
+
+#[test]
+fn large_rewrites_diff_quickly() {
+    let original: String = (0..20_000).map(|line| format!("a{}\n", line)).collect();
+    let changed: String = (0..20_000).map(|line| format!("b{}\n", line)).collect();
+    let started = Instant::now();
+    let diff = unified_diff(Path::new("out/app"), &original, &changed);
+    assert!(started.elapsed().as_secs() < 10);
+    assert!(diff.starts_with("--- out/app\n+++ out/app\n@@ -1,20000 +1,20000 @@\n-a0\n"));
+    assert_eq!(
+        diff.lines().filter(|line| line.starts_with('+')).count(),
+        20_001
+    );
+}
+
+#[test]
+fn options_writing_files_are_refused() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-plan-refused-{}", std::process::id()));
+    fs::create_dir_all(dir.join("templates")).unwrap();
+    fs::write(dir.join("config.toml"), "[qa.system1]\nport = 1\n").unwrap();
+    fs::write(dir.join("templates/app"), "port: {{ port }}\n").unwrap();
+    let plan = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+            .current_dir(&dir)
+            .args([
+                "plan",
+                "config.toml",
+                "templates",
+                "-s",
+                "qa.system1",
+                "-o",
+                "out",
+            ])
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let refused = [
+        plan(&["--report", "report.json"]),
+        plan(&["--manifest", "manifest.json", "--prune"]),
+    ];
+    let planned = plan(&[]);
+    let written = dir.join("report.json").exists() || dir.join("manifest.json").exists();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(refused[0].contains("NotPlanned(\"--report writes"));
+    assert!(refused[1].contains("NotPlanned(\"--manifest writes"));
+    assert!(!written);
+    assert!(planned.ends_with("Plan: 1 to add, 0 to change, 0 unchanged.\n"));
+}
@@ -0,0 +1,88 @@
+use serde_json::json;
+use tmptoml::{
+    merge_context_values_with_precedence, parse_group_path, parse_toml_str, render_str,
+    resolve_group_paths_with_precedence, ContextValues, Precedence, RenderOptions,
+};
+
+const CONFIG: &str = r#"
+[shared]
+port = 80
+region = "eu"
+
+[qa]
+port = 8080
+env = "qa"
+[qa.system1]
+port = 9090
+[qa.system1.limits]
+cpu = 2
+"#;
+
+fn port(group_paths: &[&str], precedence: Precedence) -> toml::Value {
+    let toml_config = parse_toml_str(CONFIG).unwrap();
+    let group_paths: Vec<_> = group_paths
+        .iter()
+        .map(|path| parse_group_path(path))
+        .collect();
+    let values =
+        resolve_group_paths_with_precedence(&toml_config, &group_paths, precedence).unwrap();
+    values["port"].clone()
+}
+
+#[test]
+fn later_layers_win_by_default() {
+    assert_eq!(
+        port(&["qa.system1"], Precedence::default()),
+        toml::Value::Integer(9090)
+    );
+    assert_eq!(
+        port(&["qa.system1", "shared"], Precedence::LastWins),
+        toml::Value::Integer(80)
+    );
+}
+
+#[test]
+fn earlier_layers_win_with_first_wins() {
+    assert_eq!(
+        port(&["qa.system1"], Precedence::FirstWins),
+        toml::Value::Integer(8080)
+    );
+    assert_eq!(
+        port(&["shared", "qa.system1"], Precedence::FirstWins),
+        toml::Value::Integer(80)
+    );
+}
+
+#[test]
+fn context_values_only_add_keys_with_first_wins() {
+    let mut base = json!({"port": 80, "labels": {"team": "core"}})
+        .as_object()
+        .unwrap()
+        .clone();
+    let overlay: ContextValues = json!({"port": 1, "labels": {"team": "web", "tier": "db"}})
+        .as_object()
+        .unwrap()
+        .clone();
+    merge_context_values_with_precedence(&mut base, overlay, Precedence::FirstWins);
+    assert_eq!(
+        serde_json::Value::Object(base),
+        json!({"port": 80, "labels": {"team": "core", "tier": "db"}})
+    );
+
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        context_values: vec![json!({"port": 1, "limits": {"memory": "1G"}})
+            .as_object()
+            .unwrap()
+            .clone()],
+        precedence: Precedence::FirstWins,
+        ..RenderOptions::default()
+    };
+    let output = render_str(
+        CONFIG,
+        "{{ port }} {{ limits.cpu }} {{ limits.memory }}",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(output, "8080 2 1G");
+}
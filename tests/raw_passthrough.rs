@@ -0,0 +1,106 @@
+use tmptoml::{render_str, Deferral, PassthroughMarkers, RenderOptions, TeraRenderErr, TmpTomlErr};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+"#;
+
+fn options() -> RenderOptions {
+    RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        ..RenderOptions::default()
+    }
+}
+
+fn with_markers(begin: &str, end: &str) -> RenderOptions {
+    RenderOptions {
+        passthrough_markers: vec![PassthroughMarkers {
+            begin: begin.to_string(),
+            end: end.to_string(),
+        }],
+        ..options()
+    }
+}
+
+const GITHUB_ACTIONS: &str =
+    "  run: echo ${{ github.sha }} ${{ secrets.TOKEN }}\n  if: ${{ always() }}\n";
+const PROMETHEUS: &str =
+    "summary: \"{{ $labels.instance }} down\"\nvalue: {{ $value | humanize }}\n{{- /* note */ -}}\n";
+
+#[test]
+fn raw_block_is_emitted_byte_for_byte() {
+    for content in [GITHUB_ACTIONS, PROMETHEUS, "\r\n  {% if %}\t{# #}\r\n", ""] {
+        let template = format!("env: {{{{ env }}}}\n{{% raw %}}{}{{% endraw %}}", content);
+        assert_eq!(
+            render_str(CONFIG, &template, &options()).unwrap(),
+            format!("env: qa\n{}", content)
+        );
+    }
+}
+
+#[test]
+fn raw_block_is_kept_by_partial_rendering() {
+    let template = format!("{{% raw %}}{}{{% endraw %}}{{{{ env }}}}", PROMETHEUS);
+    let options = RenderOptions {
+        deferral: Some(Deferral::Unknown),
+        ..options()
+    };
+    assert_eq!(
+        render_str(CONFIG, &template, &options).unwrap(),
+        format!("{}qa", PROMETHEUS)
+    );
+}
+
+#[test]
+fn marker_region_is_emitted_byte_for_byte() {
+    for content in [
+        GITHUB_ACTIONS,
+        PROMETHEUS,
+        "{% raw %}{% endraw %}{% for %}\r\n",
+    ] {
+        let template = format!(
+            "env: {{{{ env }}}}\n# tmptoml:off\n{}# tmptoml:on\n",
+            content
+        );
+        assert_eq!(
+            render_str(
+                CONFIG,
+                &template,
+                &with_markers("# tmptoml:off", "# tmptoml:on")
+            )
+            .unwrap(),
+            format!("env: qa\n# tmptoml:off\n{}# tmptoml:on\n", content)
+        );
+    }
+}
+
+#[test]
+fn marker_regions_inside_loops_are_repeated_verbatim() {
+    let template = "{% for i in [1, 2] %}{{ i }}<<${{ x }}>>{% endfor %}";
+    assert_eq!(
+        render_str(CONFIG, template, &with_markers("<<", ">>")).unwrap(),
+        "1<<${{ x }}>>2<<${{ x }}>>"
+    );
+}
+
+#[test]
+fn marker_regions_are_kept_by_partial_rendering() {
+    let template = "{{ env }} <<{{ env }} {% if %}>> {{ other }}";
+    let options = RenderOptions {
+        deferral: Some(Deferral::Unknown),
+        ..with_markers("<<", ">>")
+    };
+    assert_eq!(
+        render_str(CONFIG, template, &options).unwrap(),
+        "qa <<{{ env }} {% if %}>> {{ other }}"
+    );
+}
+
+#[test]
+fn unclosed_marker_region_is_an_error() {
+    let result = render_str(CONFIG, "<<{{ env }}", &with_markers("<<", ">>"));
+    assert!(matches!(
+        result,
+        Err(TmpTomlErr::Render(TeraRenderErr::InvalidTemplate(_)))
+    ));
+}
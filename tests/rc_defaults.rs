@@ -0,0 +1,101 @@
+use std::{fs, process::Command};
+use tmptoml::{find_rc_file, rc_arguments, RcErr, TmpTomlErr};
+
+const RC: &str = r#"
+[render]
+locale = "de-DE"
+deny-warnings = true
+report-coercions = false
+set = ["replicas=3", "name=db"]
+
+[batch]
+output-dir = "out"
+"#;
+
+#[test]
+fn defaults_are_found_in_the_nearest_directory_and_passed_as_flags() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-rc-{}", std::process::id()));
+    fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+    fs::write(dir.join(".tmptomlrc"), RC).unwrap();
+    fs::write(dir.join("nested/tmptoml.rc.toml"), "[render]\n").unwrap();
+    let nearest = find_rc_file(&dir.join("nested/deeper"));
+    let top = find_rc_file(&dir);
+    let arguments = rc_arguments(&dir.join(".tmptomlrc"), "render").unwrap();
+    let none = rc_arguments(&dir.join(".tmptomlrc"), "export-group").unwrap();
+    fs::write(dir.join(".tmptomlrc"), "locale = \"de-DE\"\n").unwrap();
+    let invalid = rc_arguments(&dir.join(".tmptomlrc"), "render");
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(nearest, Some(dir.join("nested/tmptoml.rc.toml")));
+    assert_eq!(top, Some(dir.join(".tmptomlrc")));
+    assert_eq!(
+        arguments,
+        vec![
+            "--deny-warnings",
+            "--locale=de-DE",
+            "--set=replicas=3",
+            "--set=name=db"
+        ]
+    );
+    assert!(none.is_empty());
+    assert!(matches!(invalid, Err(TmpTomlErr::Rc(RcErr::Invalid(_)))));
+}
+
+#[test]
+fn flags_on_the_command_line_override_the_defaults() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-rc-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join(".tmptomlrc"),
+        "[render]\nset = [\"port=1\"]\nprecedence = \"first-wins\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("config.toml"),
+        "[qa]\nport = 80\n[qa.system1]\nport = 90\n",
+    )
+    .unwrap();
+    fs::write(dir.join("template"), "{{ port }}").unwrap();
+    let render = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+            .current_dir(&dir)
+            .args(["render", "config.toml", "template", "qa", "system1"])
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let defaulted = render(&[]);
+    let overridden = render(&["--precedence", "last-wins", "--set", "port=2"]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(defaulted, "80\n");
+    assert_eq!(overridden, "2\n");
+}
+
+#[test]
+fn boolean_defaults_are_turned_off_with_their_negation() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-rc-negation-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join(".tmptomlrc"),
+        "[render]\ndeny-warnings = true\ndeprecated-key = \"port\"\n",
+    )
+    .unwrap();
+    fs::write(dir.join("config.toml"), "[qa.system1]\nport = 80\n").unwrap();
+    fs::write(dir.join("template"), "{{ port }}").unwrap();
+    let render = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+            .current_dir(&dir)
+            .args(["render", "config.toml", "template", "qa", "system1"])
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let defaulted = render(&[]);
+    let repeated = render(&["--deny-warnings"]);
+    let negated = render(&["--no-deny-warnings"]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(defaulted.starts_with("ERROR: Rendering produced 1 warning(s)"));
+    assert!(repeated.starts_with("ERROR: Rendering produced 1 warning(s)"));
+    assert_eq!(negated, "80\n");
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use tmptoml::{
+    parse_group_path, render_template_with_options, RenderOptions, Session, TmpTomlErr, REDACTED,
+};
+
+#[test]
+fn recorded_sessions_replay_with_secrets_redacted() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-session-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (config, template, session_path) = (
+        dir.join("config.toml"),
+        dir.join("template"),
+        dir.join("session.json"),
+    );
+    fs::write(
+        &config,
+        "[qa.system1]\nhost = \"db\"\n[qa.system1.db]\npassword = \"hunter2\"\n",
+    )
+    .unwrap();
+    fs::write(&template, "{{ host }}:{{ db.password }}").unwrap();
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        ..RenderOptions::default()
+    };
+    let output = render_template_with_options(&config, &template, &options).unwrap();
+    Session::record(&config, &template, &options, Ok(&output))
+        .unwrap()
+        .write(&session_path)
+        .unwrap();
+    let recorded = fs::read_to_string(&session_path).unwrap();
+    let session = Session::read(&session_path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(!recorded.contains("hunter2"));
+    assert_eq!(session.redacted, vec!["db.password".to_string()]);
+    assert_eq!(session.replay().unwrap(), format!("db:{}", REDACTED));
+
+    let changed = Session {
+        template_source: "{{ host }}".to_string(),
+        ..session
+    };
+    assert!(matches!(changed.replay(), Err(TmpTomlErr::Session(_))));
+}
@@ -0,0 +1,130 @@
+use std::fs;
+use tmptoml::{
+    rename_config_source, rename_key, rename_template_source, RenameErr, TemplateFilter, TmpTomlErr,
+};
+
+const CONFIG: &str = r#"# db_host is renamed below
+[_sensitive]
+keys = ["db.db_host"]
+
+[qa]
+db_host = "a" # db_host
+note = """
+db_host = "kept"
+"""
+[qa.system1]
+db = { db_host = "c", port = 1 }
+[[qa.system1.items]]
+db_host = "e"
+"#;
+
+#[test]
+fn config_keys_are_renamed_keeping_the_rest_as_written() {
+    let renamed = rename_config_source(CONFIG, "db_host", "database_host").unwrap();
+    assert_eq!(
+        renamed,
+        CONFIG
+            .replace("\"db.db_host\"", "\"db.database_host\"")
+            .replace("db_host = \"a\"", "database_host = \"a\"")
+            .replace("{ db_host", "{ database_host")
+            .replace("db_host = \"e\"", "database_host = \"e\"")
+    );
+}
+
+#[test]
+fn template_variables_bound_by_the_template_are_left_alone() {
+    let template = "{{ db_host }} {{ db.db_host }}\
+        {% for item in items %}{{ item.db_host }}{% endfor %}\
+        {% for db_host in hosts %}{{ db_host }}{% endfor %}";
+    assert_eq!(
+        rename_template_source(template, "db_host", "database_host").unwrap(),
+        "{{ database_host }} {{ db.database_host }}\
+        {% for item in items %}{{ item.database_host }}{% endfor %}\
+        {% for db_host in hosts %}{{ db_host }}{% endfor %}"
+    );
+}
+
+#[test]
+fn group_tables_keep_their_names() {
+    let config = r#"[qa]
+name = "qa"
+[qa.host]
+host = "x"
+[prod]
+host = "a"
+servers = [{ host = "b" }]
+"#;
+    assert_eq!(
+        rename_config_source(config, "host", "hostname").unwrap(),
+        r#"[qa]
+name = "qa"
+[qa.host]
+hostname = "x"
+[prod]
+hostname = "a"
+servers = [{ hostname = "b" }]
+"#
+    );
+}
+
+#[test]
+fn attributes_and_string_subscripts_of_indexed_variables_are_renamed() {
+    let template = "{{ servers[0].host }} {{ x[\"host\"] }} {{ servers[i].host | upper }}\
+        {% for s in servers %}{{ s['host'] }}{{ s['port'] }}{% endfor %}";
+    assert_eq!(
+        rename_template_source(template, "host", "hostname").unwrap(),
+        "{{ servers[0].hostname }} {{ x[\"hostname\"] }} {{ servers[i].hostname | upper }}\
+        {% for s in servers %}{{ s['hostname'] }}{{ s['port'] }}{% endfor %}"
+    );
+}
+
+#[test]
+fn keys_read_through_other_strings_are_reported() {
+    let template = "{% set key = \"host\" %}{{ x[key] }}\n{{ get_env(name=\"host\") }}";
+    match rename_template_source(template, "host", "hostname") {
+        Err(TmpTomlErr::Rename(RenameErr::Unsupported(reason))) => {
+            assert!(reason.contains("1:14, 2:17"), "{}", reason)
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn renames_captured_by_a_template_variable_conflict() {
+    let template = "{% set database_host = 1 %}{{ database_host }}{{ db_host }}";
+    assert!(matches!(
+        rename_template_source(template, "db_host", "database_host"),
+        Err(TmpTomlErr::Rename(RenameErr::Conflict(_)))
+    ));
+    assert!(matches!(
+        rename_config_source(CONFIG, "db_host", "port"),
+        Err(TmpTomlErr::Rename(RenameErr::Conflict(_)))
+    ));
+}
+
+#[test]
+fn renames_are_written_to_the_config_and_templates() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-rename-{}", std::process::id()));
+    fs::create_dir_all(dir.join("templates")).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(dir.join("templates/app.yaml"), "host: {{ db_host }}\n").unwrap();
+    fs::write(dir.join("templates/other.yaml"), "name: {{ name }}\n").unwrap();
+    let rename = rename_key(
+        &dir.join("config.toml"),
+        &dir.join("templates"),
+        "db_host",
+        "database_host",
+        &TemplateFilter::default(),
+    )
+    .unwrap();
+    assert_eq!(rename.files.len(), 2);
+    assert!(rename
+        .diff()
+        .contains("-host: {{ db_host }}\n+host: {{ database_host }}"));
+    rename.write().unwrap();
+    let template = fs::read_to_string(dir.join("templates/app.yaml")).unwrap();
+    let config = fs::read_to_string(dir.join("config.toml")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(template, "host: {{ database_host }}\n");
+    assert!(config.contains("database_host = \"a\""));
+}
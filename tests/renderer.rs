@@ -0,0 +1,99 @@
+use std::{sync::Arc, thread};
+use tmptoml::{parse_group_path, LoadedConfig, RenderOptions, Renderer};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+[qa.system1]
+replicas = 2
+[production]
+env = "production"
+[production.system1]
+replicas = 5
+"#;
+
+fn templates() -> Vec<(String, String)> {
+    vec![
+        (
+            "deployment.yaml".to_string(),
+            "{% include \"labels.yaml\" %}replicas: {{ replicas }}".to_string(),
+        ),
+        ("labels.yaml".to_string(), "env: {{ env }}\n".to_string()),
+    ]
+}
+
+#[test]
+fn compiled_templates_are_rendered_for_every_selection() {
+    let config = LoadedConfig::parse(CONFIG).unwrap();
+    let renderer = Renderer::new(templates(), RenderOptions::default()).unwrap();
+    assert_eq!(
+        renderer.template_names(),
+        ["deployment.yaml", "labels.yaml"]
+    );
+    assert_eq!(
+        renderer
+            .render(
+                "deployment.yaml",
+                &config,
+                &[parse_group_path("qa.system1")]
+            )
+            .unwrap(),
+        "env: qa\nreplicas: 2"
+    );
+    assert_eq!(
+        renderer
+            .render(
+                "deployment.yaml",
+                &config,
+                &[parse_group_path("production.system1")]
+            )
+            .unwrap(),
+        "env: production\nreplicas: 5"
+    );
+}
+
+#[test]
+fn unknown_template_is_an_error() {
+    let config = LoadedConfig::parse(CONFIG).unwrap();
+    let renderer = Renderer::new(templates(), RenderOptions::default()).unwrap();
+    assert!(renderer
+        .render("missing.yaml", &config, &[parse_group_path("qa")])
+        .is_err());
+}
+
+#[test]
+fn renderer_is_shared_between_threads() {
+    let config = Arc::new(LoadedConfig::parse(CONFIG).unwrap());
+    let renderer = Arc::new(Renderer::new(templates(), RenderOptions::default()).unwrap());
+    let handles: Vec<_> = (0..8)
+        .map(|index| {
+            let config = Arc::clone(&config);
+            let renderer = Arc::clone(&renderer);
+            thread::spawn(move || {
+                let (group_path, expected) = if index % 2 == 0 {
+                    ("qa.system1", "env: qa\nreplicas: 2")
+                } else {
+                    ("production.system1", "env: production\nreplicas: 5")
+                };
+                for _ in 0..50 {
+                    let rendered = renderer
+                        .render("deployment.yaml", &config, &[parse_group_path(group_path)])
+                        .unwrap();
+                    assert_eq!(rendered, expected);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn included_templates_come_first_in_dependency_order() {
+    let renderer = Renderer::new(templates(), RenderOptions::default()).unwrap();
+    assert_eq!(
+        renderer.dependency_order(),
+        ["labels.yaml", "deployment.yaml"]
+    );
+}
@@ -0,0 +1,32 @@
+use tmptoml::{render_str, RenderOptions};
+
+fn reproducible(source_date_epoch: Option<i64>) -> RenderOptions {
+    RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        reproducible: true,
+        source_date_epoch,
+        ..RenderOptions::default()
+    }
+}
+
+#[test]
+fn now_returns_the_source_date() {
+    let rendered = render_str(
+        "[qa]\n",
+        "{{ now() }} {{ now(timestamp=true, utc=true) }}",
+        &reproducible(Some(1700000000)),
+    )
+    .unwrap();
+    assert_eq!(rendered, "2023-11-14T22:13:20+00:00 1700000000");
+}
+
+#[test]
+fn host_dependent_functions_are_refused() {
+    for template in [
+        "{{ now() }}",
+        "{{ get_env(name=\"HOME\") }}",
+        "{{ get_random(end=10) }}",
+    ] {
+        assert!(render_str("[qa]\n", template, &reproducible(None)).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::{fs, process::Command};
+use tmptoml::{parse_group_path, parse_toml_groups, parse_toml_str, render_str, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+[qa.system1]
+replicas = 2
+[production]
+env = "production"
+[production.system1]
+replicas = 5
+[shared.observability]
+endpoint = "otel:4317"
+"#;
+
+#[test]
+fn only_the_selected_groups_keep_their_values() {
+    let config = parse_toml_groups(
+        CONFIG,
+        &[parse_group_path("qa.system1"), parse_group_path("shared")],
+    )
+    .unwrap();
+    let mut group_ids: Vec<&String> = config
+        .iter()
+        .filter(|(_, group)| !group.is_empty())
+        .map(|(group_id, _)| group_id)
+        .collect();
+    group_ids.sort();
+    assert_eq!(group_ids, ["qa", "shared"]);
+    assert!(config["production"].is_empty());
+}
+
+#[test]
+fn skipped_groups_are_still_validated() {
+    let invalid = format!("{}\n[production]\nenv = \"again\"\n", CONFIG);
+    assert!(parse_toml_groups(&invalid, &[parse_group_path("qa")]).is_err());
+}
+
+#[test]
+fn rendering_reads_only_the_selected_groups() {
+    let options = RenderOptions {
+        group_paths: vec![
+            parse_group_path("qa.system1"),
+            parse_group_path("shared.observability"),
+        ],
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        render_str(CONFIG, "{{ env }} {{ replicas }} {{ endpoint }}", &options).unwrap(),
+        "qa 2 otel:4317"
+    );
+}
+
+#[test]
+fn skipped_groups_must_be_tables() {
+    let invalid = format!("generated = 1\n{}", CONFIG);
+    let selective = parse_toml_groups(&invalid, &[parse_group_path("qa")]).unwrap_err();
+    let full = parse_toml_str(&invalid).unwrap_err();
+    assert_eq!(format!("{:?}", selective), format!("{:?}", full));
+}
+
+#[test]
+fn render_command_rejects_the_configs_a_full_parse_rejects() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-selective-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    // A group that isn't a table is rejected whether or not it is selected.
+    fs::write(
+        dir.join("config.toml"),
+        format!("generated = 1\n{}", CONFIG),
+    )
+    .unwrap();
+    fs::write(dir.join("template"), "{{ env }} {{ replicas }}").unwrap();
+    let render = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+            .current_dir(&dir)
+            .args(["render", "config.toml", "template", "qa", "system1"])
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let selective = render(&[]);
+    let full = render(&["--allow-config-lookup"]);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(selective.starts_with("ERROR: The specified config file could not be parsed."));
+    assert!(selective.contains("invalid type: integer `1`, expected a map"));
+    assert_eq!(selective, full);
+}
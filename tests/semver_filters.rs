@@ -0,0 +1,60 @@
+use tmptoml::{render_str, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+version = "v1.4.2"
+release_candidate = "2.0.0-rc.1"
+"#;
+
+fn render(template: &str) -> String {
+    let options = RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, template, &options).unwrap()
+}
+
+#[test]
+fn bumping_resets_the_lower_parts() {
+    assert_eq!(
+        render(
+            r#"{{ version | semver_bump(part="major") }} {{ version | semver_bump(part="minor") }} {{ version | semver_bump(part="patch") }}"#
+        ),
+        "v2.0.0 v1.5.0 v1.4.3"
+    );
+    assert_eq!(
+        render(r#"{{ release_candidate | semver_bump(part="major") }}"#),
+        "2.0.0"
+    );
+}
+
+#[test]
+fn requirements_follow_cargo() {
+    let matches = |requirement: &str| {
+        render(&format!(
+            r#"{{{{ version | semver_match(req="{}") }}}}"#,
+            requirement
+        ))
+    };
+    assert_eq!(matches("^1.2"), "true");
+    assert_eq!(matches(">=1.2, <1.4"), "false");
+    assert_eq!(matches("~1.4"), "true");
+    assert_eq!(matches("1.*"), "true");
+    assert_eq!(matches("=1.4.1"), "false");
+    assert_eq!(
+        render(
+            r#"{{ release_candidate | semver_match(req=">=1.0") }} {{ release_candidate | semver_match(req=">=2.0.0-rc.0") }}"#
+        ),
+        "false true"
+    );
+}
+
+#[test]
+fn versions_compare_by_precedence() {
+    assert_eq!(
+        render(
+            r#"{{ "1.10.0" | semver_gt(version="1.9.0") }} {{ release_candidate | semver_lt(version="2.0.0") }} {{ "1.0.0+build.5" | semver_eq(version="1.0.0") }}"#
+        ),
+        "true true true"
+    );
+}
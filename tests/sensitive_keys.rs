@@ -0,0 +1,93 @@
+use std::{collections::BTreeSet, fs, path::PathBuf};
+use tmptoml::{
+    export_group, flatten_config_file, parse_group_path, parse_toml_str,
+    render_template_with_warnings, sensitive_keys, ExportFormat, RenderOptions, WarningKind,
+};
+
+const CONFIG: &str = r#"
+[_sensitive]
+keys = ["api.token"]
+
+[qa]
+password = { value = "hunter2", sensitive = true }
+[qa.system1]
+host = "db"
+[qa.system1.api]
+token = "abc123"
+url = "https://example.com"
+"#;
+
+fn write_files(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("tmptoml-sensitive-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(
+        dir.join("template"),
+        "{{ host }} {{ password }} {{ api.token }}",
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn keys_listed_or_marked_inline_are_sensitive() {
+    let toml_config = parse_toml_str(CONFIG).unwrap();
+    let keys = sensitive_keys(&toml_config, &[parse_group_path("qa.system1")]);
+    let expected: BTreeSet<String> = vec!["api.token".to_string(), "password".to_string()]
+        .into_iter()
+        .collect();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn templates_see_sensitive_values_and_their_output_is_reported() {
+    let dir = write_files("render");
+    let mut options = RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        ..RenderOptions::default()
+    };
+    let rendered =
+        render_template_with_warnings(&dir.join("config.toml"), &dir.join("template"), &options)
+            .unwrap();
+    options.secret_output = true;
+    let secret =
+        render_template_with_warnings(&dir.join("config.toml"), &dir.join("template"), &options)
+            .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(rendered.output, "db hunter2 abc123");
+    let reported: Vec<(&str, &WarningKind)> = rendered
+        .warnings
+        .iter()
+        .map(|warning| (warning.key.as_str(), &warning.kind))
+        .collect();
+    assert_eq!(
+        reported,
+        vec![
+            ("api.token", &WarningKind::SensitiveOutput),
+            ("password", &WarningKind::SensitiveOutput),
+        ]
+    );
+    assert!(secret.warnings.is_empty());
+}
+
+#[test]
+fn sensitive_values_are_kept_out_of_exports_and_flattening() {
+    let dir = write_files("export");
+    let exported = export_group(
+        &dir.join("config.toml"),
+        "qa",
+        "system1",
+        ExportFormat::Toml,
+    )
+    .unwrap();
+    let flattened = flatten_config_file(&dir.join("config.toml")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        exported,
+        "host = \"db\"\n\n[api]\nurl = \"https://example.com\"\n"
+    );
+    assert!(!flattened.contains("hunter2"));
+    assert!(!flattened.contains("abc123"));
+    assert!(!flattened.contains("[qa.password]"));
+}
@@ -0,0 +1,29 @@
+use std::fs;
+use tmptoml::{export_group, ExportFormat};
+
+#[test]
+fn groups_are_exported_as_quoted_shell_variables() {
+    let path = std::env::temp_dir().join(format!("tmptoml-shell-{}.toml", std::process::id()));
+    fs::write(
+        &path,
+        r#"
+[qa]
+replicas = 2
+[qa.system1]
+motd = "it's $HOME"
+ports = [80, 443]
+[qa.system1.db]
+host-name = "db.internal"
+"#,
+    )
+    .unwrap();
+    let exported = export_group(&path, "qa", "system1", ExportFormat::Shell).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        exported,
+        "export DB_HOST_NAME='db.internal'\n\
+         export MOTD='it'\\''s $HOME'\n\
+         export PORTS='[80,443]'\n\
+         export REPLICAS='2'"
+    );
+}
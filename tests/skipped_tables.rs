@@ -0,0 +1,40 @@
+use tmptoml::{parse_group_path, parse_toml_str, render_str, skipped_tables, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+[qa.eu-west.system1]
+port = 1
+[qa.eu-west.system2]
+port = 2
+[qa.us-east.system1]
+port = 3
+[qa.password]
+value = "hunter2"
+sensitive = true
+"#;
+
+#[test]
+fn tables_not_descended_into_are_listed() {
+    let toml_config = parse_toml_str(CONFIG).unwrap();
+    assert_eq!(
+        skipped_tables(&toml_config, &parse_group_path("qa.eu-west.system1")),
+        ["qa.eu-west.system2", "qa.us-east"]
+    );
+    assert!(skipped_tables(&toml_config, &parse_group_path("qa")).is_empty());
+}
+
+#[test]
+fn siblings_are_exposed_under_their_own_keys() {
+    let render = |include_siblings| {
+        let options = RenderOptions {
+            group_paths: vec![parse_group_path("qa.eu-west.system1")],
+            include_siblings,
+            ..RenderOptions::default()
+        };
+        let template = "{{ port }}{% if system2 %} {{ system2.port }} {{ us_east }}{% endif %}";
+        render_str(&CONFIG.replace("us-east", "us_east"), template, &options).unwrap()
+    };
+    assert_eq!(render(false), "1");
+    assert_eq!(render(true), "1 2 [object]");
+}
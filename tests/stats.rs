@@ -0,0 +1,84 @@
+use std::{fs, path::PathBuf};
+use tmptoml::{config_stats, parse_toml_str, stats_file, template_stats};
+
+const CONFIG: &str = r#"
+[_defaults]
+region = "eu-west-1"
+[_sensitive]
+keys = ["password"]
+[qa]
+env = "qa"
+debug = true
+password = { value = "hunter2", sensitive = true }
+[qa.system1]
+host = "db"
+replicas = 2
+[qa.system1.limits]
+cpu = 0.5
+[qa.system2]
+ports = [80, 443]
+[prod]
+env = "prod"
+"#;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tmptoml-stats-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn config_stats_count_groups_keys_and_types() {
+    assert_eq!(
+        config_stats(&parse_toml_str(CONFIG).unwrap()),
+        "Groups: 2\n\
+         Secondary groups: 2\n\
+         Keys per group:\n  \
+           prod: 1 own, 1 including 0 secondary group(s)\n  \
+           qa: 3 own, 7 including 2 secondary group(s)\n\
+         Value types:\n  \
+           array: 1\n  \
+           boolean: 1\n  \
+           float: 1\n  \
+           integer: 1\n  \
+           string: 4"
+    );
+}
+
+#[test]
+fn template_stats_count_templates_and_distinct_variables() {
+    let dir = temp_dir("templates");
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join("a.conf"), "{{ env }} {{ host }}").unwrap();
+    fs::write(dir.join("nested/b.conf"), "{{ host }} {{ replicas }}").unwrap();
+    fs::write(dir.join("broken.conf"), "{{ env ").unwrap();
+    fs::write(dir.join("skipped.conf"), "{{ skipped }}").unwrap();
+    fs::write(dir.join(".tmptomlignore"), "skipped.conf\n").unwrap();
+
+    let stats = template_stats(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        stats.unwrap(),
+        "Templates: 3\nDistinct variables: 3\nTemplates failing to parse: 1"
+    );
+}
+
+#[test]
+fn stats_file_appends_the_template_stats_when_given_a_directory() {
+    let dir = temp_dir("file");
+    fs::write(dir.join("config.toml"), "[qa]\nenv = \"qa\"\n").unwrap();
+    fs::create_dir_all(dir.join("templates")).unwrap();
+    fs::write(dir.join("templates/a.conf"), "{{ env }}").unwrap();
+
+    let config_only = stats_file(&dir.join("config.toml"), None).unwrap();
+    let with_templates =
+        stats_file(&dir.join("config.toml"), Some(&dir.join("templates"))).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(!config_only.contains("Templates"));
+    assert_eq!(
+        with_templates,
+        format!("{}\nTemplates: 1\nDistinct variables: 1", config_only)
+    );
+}
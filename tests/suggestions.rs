@@ -0,0 +1,80 @@
+use tmptoml::{parse_group_path, render_str, RenderOptions, TeraRenderErr, TmpTomlErr};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+[qa.system1]
+replicas = 2
+[qa.system1.database]
+host = "db.qa"
+[production]
+env = "production"
+[production.system1]
+replicas = 5
+"#;
+
+fn render(template: &str, group_path: &str) -> Result<String, TmpTomlErr> {
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path(group_path)],
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, template, &options)
+}
+
+#[test]
+fn mistyped_group_suggests_the_closest_group() {
+    match render("{{ env }}", "prodution.system1") {
+        Err(TmpTomlErr::GroupNotFound(unknown)) => {
+            assert_eq!(unknown.name, "prodution");
+            assert_eq!(unknown.suggestions, ["production"]);
+            assert_eq!(unknown.candidates, ["production", "qa"]);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn mistyped_secondary_group_lists_the_groups_at_its_level() {
+    match render("{{ env }}", "qa.sytem1") {
+        Err(TmpTomlErr::GroupNotFound(unknown)) => {
+            assert_eq!(unknown.suggestions, ["system1"]);
+            assert_eq!(unknown.candidates, ["system1"]);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn mistyped_nested_variable_suggests_keys_of_its_parent() {
+    match render("{{ database.hots }}", "qa.system1") {
+        Err(TmpTomlErr::Render(TeraRenderErr::VariableNotFound(unknown))) => {
+            assert_eq!(unknown.name, "database.hots");
+            assert_eq!(unknown.suggestions, ["database.host"]);
+            assert_eq!(
+                unknown.to_string(),
+                "`database.hots`, did you mean `database.host`? Valid options: database.host"
+            );
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn long_candidate_lists_are_truncated() {
+    let config: String = (0..25)
+        .map(|index| format!("[env{:02}.system1]\nreplicas = 1\n", index))
+        .collect();
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("staging.system1")],
+        ..RenderOptions::default()
+    };
+    match render_str(&config, "{{ replicas }}", &options) {
+        Err(TmpTomlErr::GroupNotFound(unknown)) => {
+            assert_eq!(unknown.candidates.len(), 25);
+            let message = unknown.to_string();
+            assert!(message.starts_with("`staging`. Valid options: env00, env01,"));
+            assert!(message.ends_with("env19 and 5 more"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,49 @@
+use std::fs;
+use tmptoml::{
+    parse_group_path, parse_toml_str, render_str, select_tagged_file, tagged_group_paths,
+    RenderOptions,
+};
+
+const CONFIG: &str = r#"
+[prod]
+_tags = ["prod"]
+[prod.payments]
+_tags = ["prod", "pci"]
+host = "pay.prod"
+[prod.blog]
+host = "blog.prod"
+[qa.payments]
+_tags = ["pci"]
+host = "pay.qa"
+"#;
+
+#[test]
+fn sections_are_selected_by_any_of_their_tags() {
+    let config = parse_toml_str(CONFIG).unwrap();
+    let pci = tagged_group_paths(&config, &["pci".to_string()]).unwrap();
+    assert_eq!(
+        pci,
+        vec![
+            parse_group_path("prod.payments"),
+            parse_group_path("qa.payments")
+        ]
+    );
+
+    let path = std::env::temp_dir().join(format!("tmptoml-tags-{}.toml", std::process::id()));
+    fs::write(&path, CONFIG).unwrap();
+    let selected = select_tagged_file(&path, &["pci".to_string()], &[parse_group_path("qa")]);
+    let untagged = select_tagged_file(&path, &["sox".to_string()], &[]);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(selected.unwrap(), vec![parse_group_path("qa.payments")]);
+    assert!(untagged.is_err());
+}
+
+#[test]
+fn tags_are_not_template_values() {
+    let options = RenderOptions {
+        group_paths: vec![parse_group_path("prod.payments")],
+        ..RenderOptions::default()
+    };
+    let rendered = render_str(CONFIG, "{{ __tera_context }}", &options).unwrap();
+    assert!(!rendered.contains("_tags"));
+}
@@ -0,0 +1,82 @@
+use std::{fs, process::Command, time::Duration};
+use tmptoml::{format_timing, render_template_with_warnings, RenderOptions, Timings};
+
+const PHASES: [&str; 6] = [
+    "config read/parse",
+    "context building",
+    "warnings",
+    "template compilation",
+    "rendering",
+    "total",
+];
+
+#[test]
+fn timings_report_every_phase_and_their_total() {
+    let timings = Timings {
+        config: Duration::from_micros(1500),
+        context: Duration::from_micros(250),
+        warnings: Duration::from_micros(1),
+        compile: Duration::from_millis(2),
+        render: Duration::from_millis(10),
+    };
+    assert_eq!(timings.total(), Duration::from_micros(13751));
+    assert_eq!(
+        timings.to_string(),
+        [
+            "config read/parse         1.500ms",
+            "context building          0.250ms",
+            "warnings                  0.001ms",
+            "template compilation      2.000ms",
+            "rendering                10.000ms",
+            "total                    13.751ms",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        format_timing("rendering", Duration::from_secs(12)),
+        "rendering             12000.000ms"
+    );
+}
+
+#[test]
+fn renders_measure_their_phases() {
+    let dir = std::env::temp_dir().join(format!("tmptoml-timings-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), "[qa]\ncount = 2000\n").unwrap();
+    fs::write(
+        dir.join("template"),
+        "{% for i in range(end=count) %}{{ i }}{% endfor %}",
+    )
+    .unwrap();
+
+    let options = RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        ..RenderOptions::default()
+    };
+    let rendered =
+        render_template_with_warnings(&dir.join("config.toml"), &dir.join("template"), &options)
+            .unwrap();
+    let cli = Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+        .current_dir(&dir)
+        .args(["render", "config.toml", "template", "qa", "--timings"])
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let timings = rendered.timings;
+    assert!(timings.render > Duration::ZERO);
+    assert!(timings.total() >= timings.render + timings.compile);
+
+    assert!(cli.status.success());
+    let stdout = String::from_utf8(cli.stdout).unwrap();
+    assert!(stdout.starts_with("012345"));
+    assert!(!stdout.contains("total"));
+    let stderr = String::from_utf8(cli.stderr).unwrap();
+    let phases: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.ends_with("ms"))
+        .filter_map(|line| line.rsplit_once(char::is_whitespace))
+        .map(|(phase, _)| phase.trim())
+        .collect();
+    assert_eq!(phases, PHASES);
+}
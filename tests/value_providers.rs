@@ -0,0 +1,63 @@
+use tmptoml::{
+    parse_group_path, render_str, RenderOptions, TmpTomlErr, ValueProvider, ValueProviders,
+};
+
+struct Vault;
+
+impl ValueProvider for Vault {
+    fn prefix(&self) -> &str {
+        "vault://"
+    }
+
+    fn resolve(&self, reference: &str) -> Result<toml::Value, String> {
+        match reference {
+            "db#password" => Ok(toml::Value::String("hunter2".to_string())),
+            "db#port" => Ok(toml::Value::Integer(5432)),
+            other => Err(format!("no secret at {}", other)),
+        }
+    }
+}
+
+const CONFIG: &str = r#"
+[qa.system1]
+password = "vault://db#password"
+ports = ["vault://db#port"]
+motd = "file://not-a-provider"
+missing = "vault://db#missing"
+"#;
+
+fn options(providers: ValueProviders) -> RenderOptions {
+    RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        value_providers: providers,
+        ..RenderOptions::default()
+    }
+}
+
+#[test]
+fn values_with_a_provider_prefix_are_resolved() {
+    let template = "{{ password }} {{ ports.0 + 1 }} {{ motd }}";
+    let config = CONFIG.replace("missing = \"vault://db#missing\"", "");
+    let rendered = render_str(
+        &config,
+        template,
+        &options(ValueProviders::new().with(Vault)),
+    );
+    assert_eq!(rendered.unwrap(), "hunter2 5433 file://not-a-provider");
+
+    let unresolved = render_str(&config, "{{ password }}", &options(ValueProviders::new()));
+    assert_eq!(unresolved.unwrap(), "vault://db#password");
+}
+
+#[test]
+fn values_failing_to_resolve_fail_the_render() {
+    let rendered = render_str(
+        CONFIG,
+        "{{ password }}",
+        &options(ValueProviders::new().with(Vault)),
+    );
+    match rendered {
+        Err(TmpTomlErr::Provider(err)) => assert_eq!(err.reference, "vault://db#missing"),
+        other => panic!("expected a provider error, got {:?}", other),
+    }
+}
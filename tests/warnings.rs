@@ -0,0 +1,150 @@
+use std::{fs, path::PathBuf, process::Command};
+use tmptoml::{
+    parse_group_path, render_template_with_warnings, RenderOptions, Warning, WarningKind,
+};
+
+const CONFIG: &str = r#"
+[qa]
+env = "qa"
+created = 1979-05-27T07:32:00Z
+password = { value = "hunter2", sensitive = true }
+[qa.system1]
+host = "db"
+replicas = 1
+[qa.system2]
+host = "cache"
+"#;
+
+fn write_files(name: &str, template: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("tmptoml-warnings-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), CONFIG).unwrap();
+    fs::write(dir.join("template"), template).unwrap();
+    dir
+}
+
+fn warnings(name: &str, template: &str, options: RenderOptions) -> Vec<Warning> {
+    let dir = write_files(name, template);
+    let rendered =
+        render_template_with_warnings(&dir.join("config.toml"), &dir.join("template"), &options)
+            .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    rendered.warnings
+}
+
+fn system1() -> RenderOptions {
+    RenderOptions {
+        group_paths: vec![parse_group_path("qa.system1")],
+        ..RenderOptions::default()
+    }
+}
+
+fn of_kind(warnings: &[Warning], kind: WarningKind) -> Vec<(&str, &str)> {
+    warnings
+        .iter()
+        .filter(|warning| warning.kind == kind)
+        .map(|warning| (warning.key.as_str(), warning.message.as_str()))
+        .collect()
+}
+
+#[test]
+fn values_replaced_by_a_later_selection_are_shadowed() {
+    let options = RenderOptions {
+        group_paths: vec![
+            parse_group_path("qa.system1"),
+            parse_group_path("qa.system2"),
+        ],
+        ..RenderOptions::default()
+    };
+    let warnings = warnings("shadowed", "{{ host }}", options);
+    assert_eq!(
+        of_kind(&warnings, WarningKind::ShadowedKey),
+        [("host", "value of qa.system1 is shadowed by qa.system2")]
+    );
+}
+
+#[test]
+fn deprecated_keys_read_by_the_template_are_reported() {
+    let options = RenderOptions {
+        deprecated_keys: vec![
+            "env=environment".parse().unwrap(),
+            "replicas".parse().unwrap(),
+        ],
+        ..system1()
+    };
+    let warnings = warnings("deprecated", "{{ env }}", options);
+    assert_eq!(
+        of_kind(&warnings, WarningKind::DeprecatedKey),
+        [("env", "key is deprecated, use environment instead")]
+    );
+}
+
+#[test]
+fn unused_keys_are_only_reported_when_asked_for() {
+    let template = "{{ env }} {{ host }} {{ created }} {{ password }}";
+    let warnings_of = |name, warn_unused_keys| {
+        let options = RenderOptions {
+            warn_unused_keys,
+            secret_output: true,
+            ..system1()
+        };
+        warnings(name, template, options)
+    };
+    assert!(warnings_of("unused-off", false)
+        .iter()
+        .all(|warning| warning.kind != WarningKind::UnusedKey));
+    assert_eq!(
+        of_kind(&warnings_of("unused-on", true), WarningKind::UnusedKey),
+        [("replicas", "value isn't read by the template")]
+    );
+}
+
+#[test]
+fn datetimes_passed_as_strings_are_lossy() {
+    let warnings = warnings("lossy", "{{ created }}", system1());
+    assert_eq!(
+        of_kind(&warnings, WarningKind::LossyCoercion),
+        [("created", "datetime is passed as a string")]
+    );
+}
+
+#[test]
+fn sensitive_values_in_the_output_are_reported() {
+    let warnings = warnings("sensitive", "{{ password }}", system1());
+    assert_eq!(
+        of_kind(&warnings, WarningKind::SensitiveOutput)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<&str>>(),
+        ["password"]
+    );
+}
+
+#[test]
+fn deny_warnings_fails_the_render() {
+    let dir = write_files("deny", "{{ env }} {{ host }}");
+    let render = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_tmptoml"))
+            .current_dir(&dir)
+            .args(["render", "config.toml", "template", "qa", "system1"])
+            .args(["--deprecated-key", "env=environment"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let permissive = render(&[]);
+    let strict = render(&["--deny-warnings"]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(permissive.status.success());
+    assert_eq!(String::from_utf8(permissive.stdout).unwrap(), "qa db\n");
+    assert!(String::from_utf8(permissive.stderr)
+        .unwrap()
+        .contains("WARNING: env: key is deprecated, use environment instead [deprecated-key]\n"));
+    assert_eq!(strict.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(strict.stdout).unwrap(),
+        "ERROR: Rendering produced 2 warning(s) while --deny-warnings is set.\n"
+    );
+}
@@ -0,0 +1,62 @@
+use tmptoml::{render_str, RenderOptions};
+
+const CONFIG: &str = r#"
+[qa]
+replicas = 2
+name = "web"
+ports = [80, 443]
+[qa.labels]
+app = "web"
+tier = "frontend"
+[[qa.containers]]
+name = "nginx"
+args = ["-g", "daemon off;"]
+"#;
+
+fn render(template: &str) -> Result<String, String> {
+    let options = RenderOptions {
+        group_paths: vec![vec!["qa".to_string()]],
+        ..RenderOptions::default()
+    };
+    render_str(CONFIG, template, &options).map_err(|err| format!("{:?}", err))
+}
+
+#[test]
+fn toyaml_serializes_without_document_marker_or_trailing_newline() {
+    assert_eq!(
+        render("{{ labels | toyaml }}").unwrap(),
+        "app: web\ntier: frontend"
+    );
+    assert_eq!(render("{{ ports | toyaml }}").unwrap(), "- 80\n- 443");
+    assert_eq!(render("{{ replicas | toyaml }}").unwrap(), "2");
+    assert_eq!(
+        render("{{ containers | toyaml }}").unwrap(),
+        "- args:\n    - \"-g\"\n    - daemon off;\n  name: nginx"
+    );
+}
+
+#[test]
+fn nindent_nests_a_block_under_a_key() {
+    let template = "metadata:\n  labels: {{ labels | toyaml | nindent(n=4) }}\n";
+    assert_eq!(
+        render(template).unwrap(),
+        "metadata:\n  labels: \n    app: web\n    tier: frontend\n"
+    );
+    assert_eq!(render("{{ name | nindent(n=2) }}").unwrap(), "\n  web");
+}
+
+#[test]
+fn indent_pads_every_line_including_the_first() {
+    assert_eq!(
+        render("{{ ports | toyaml | indent(n=2) }}").unwrap(),
+        "  - 80\n  - 443"
+    );
+    assert_eq!(render("{{ name | indent(width=3) }}").unwrap(), "   web");
+}
+
+#[test]
+fn indent_width_is_required_and_positive() {
+    for template in ["{{ name | nindent }}", "{{ name | nindent(n=-1) }}"] {
+        assert!(render(template).is_err(), "{} rendered", template);
+    }
+}